@@ -0,0 +1,153 @@
+//! Client/server clock synchronization
+//!
+//! NTP-style offset estimation from ping/pong exchanges. Platform agnostic so
+//! it can be tested natively.
+
+/// Number of recent samples kept for filtering
+const MAX_SAMPLES: usize = 8;
+
+/// Smoothing factor for the RTT estimate (higher = faster response)
+const RTT_SMOOTHING: f64 = 0.2;
+
+/// One ping/pong exchange. Client times are local ms, server times are server ms.
+#[derive(Debug, Clone, Copy)]
+struct ClockSample {
+    offset_ms: f64,
+    rtt_ms: f64,
+}
+
+/// Filtered estimate of the server clock relative to the local clock
+#[derive(Debug, Clone, Default)]
+pub struct ClockSync {
+    samples: Vec<ClockSample>,
+    offset_ms: f64,
+    rtt_ms: f64,
+}
+
+impl ClockSync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sample from one exchange
+    /// t0: client send, t1: server receive, t2: server send, t3: client receive
+    pub fn add_sample(&mut self, t0: f64, t1: f64, t2: f64, t3: f64) {
+        // Server processing time does not count towards network round trip
+        let rtt_ms = ((t3 - t0) - (t2 - t1)).max(0.0);
+        let offset_ms = ((t1 - t0) + (t2 - t3)) / 2.0;
+
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.remove(0);
+        }
+        self.samples.push(ClockSample { offset_ms, rtt_ms });
+
+        // The sample with the lowest RTT has the least asymmetric delay,
+        // so its offset is the most trustworthy
+        if let Some(best) = self
+            .samples
+            .iter()
+            .min_by(|a, b| a.rtt_ms.total_cmp(&b.rtt_ms))
+        {
+            self.offset_ms = best.offset_ms;
+        }
+
+        if self.samples.len() == 1 {
+            self.rtt_ms = rtt_ms;
+        } else {
+            self.rtt_ms += (rtt_ms - self.rtt_ms) * RTT_SMOOTHING;
+        }
+    }
+
+    /// True once at least one exchange has completed
+    pub fn is_synced(&self) -> bool {
+        !self.samples.is_empty()
+    }
+
+    /// Estimated server clock minus local clock (ms)
+    pub fn offset_ms(&self) -> f64 {
+        self.offset_ms
+    }
+
+    /// Smoothed round trip time (ms)
+    pub fn rtt_ms(&self) -> f64 {
+        self.rtt_ms
+    }
+
+    /// Convert a local time to server time
+    pub fn to_server_time(&self, local_ms: f64) -> f64 {
+        local_ms + self.offset_ms
+    }
+
+    /// Convert a server time to local time
+    pub fn to_local_time(&self, server_ms: f64) -> f64 {
+        server_ms - self.offset_ms
+    }
+
+    /// Local ms elapsed since a server time, or None until synced.
+    /// Never negative, even if the estimate puts the server time ahead.
+    pub fn elapsed_since(&self, server_ms: f64, local_now_ms: f64) -> Option<f64> {
+        self.is_synced()
+            .then(|| (local_now_ms - self.to_local_time(server_ms)).max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initial_state() {
+        let clock = ClockSync::new();
+        assert!(!clock.is_synced());
+        assert_eq!(clock.offset_ms(), 0.0);
+    }
+
+    #[test]
+    fn test_symmetric_exchange() {
+        let mut clock = ClockSync::new();
+        // Server is 1000ms ahead, 20ms each way, 2ms processing
+        clock.add_sample(100.0, 1120.0, 1122.0, 142.0);
+
+        assert!(clock.is_synced());
+        assert_eq!(clock.offset_ms(), 1000.0);
+        assert_eq!(clock.rtt_ms(), 40.0);
+        assert_eq!(clock.to_server_time(500.0), 1500.0);
+        assert_eq!(clock.to_local_time(1500.0), 500.0);
+    }
+
+    #[test]
+    fn test_elapsed_since_server_time() {
+        let mut clock = ClockSync::new();
+        assert_eq!(clock.elapsed_since(5000.0, 600.0), None);
+
+        // Server is 1000ms ahead, so server 1500 happened at local 500
+        clock.add_sample(100.0, 1120.0, 1122.0, 142.0);
+        assert_eq!(clock.elapsed_since(1500.0, 800.0), Some(300.0));
+        assert_eq!(clock.elapsed_since(1500.0, 400.0), Some(0.0));
+    }
+
+    #[test]
+    fn test_prefers_lowest_rtt_sample() {
+        let mut clock = ClockSync::new();
+        // Clean sample: offset 1000, rtt 40
+        clock.add_sample(100.0, 1120.0, 1120.0, 140.0);
+        // Congested uplink: 200ms up, 20ms down skews the offset estimate
+        clock.add_sample(200.0, 1400.0, 1400.0, 420.0);
+
+        assert_eq!(clock.offset_ms(), 1000.0);
+        assert!(clock.rtt_ms() > 40.0, "RTT estimate should rise");
+    }
+
+    #[test]
+    fn test_old_samples_expire() {
+        let mut clock = ClockSync::new();
+        clock.add_sample(0.0, 1010.0, 1010.0, 20.0); // offset 1000, rtt 20
+        for i in 0..MAX_SAMPLES {
+            let t0 = 1000.0 + i as f64 * 100.0;
+            // Server clock drifted to 2000 ahead, rtt 60
+            clock.add_sample(t0, t0 + 2030.0, t0 + 2030.0, t0 + 60.0);
+        }
+
+        assert_eq!(clock.offset_ms(), 2000.0);
+    }
+}
@@ -16,6 +16,7 @@ pub enum FsmState {
     Waiting,
     CountdownMulti,
     PlayingMulti,
+    PausedMulti,
     GameOverLocal,
     GameOverMulti,
     Disconnected,
@@ -38,6 +39,8 @@ pub enum GameAction {
     Leave,
     PlayAgain,
     RematchStarted,
+    Paused,
+    Resumed,
 }
 
 /// Result of a state transition
@@ -143,6 +146,8 @@ impl GameFsm {
             "LEAVE" => GameAction::Leave,
             "PLAY_AGAIN" => GameAction::PlayAgain,
             "REMATCH_STARTED" => GameAction::RematchStarted,
+            "PAUSED" => GameAction::Paused,
+            "RESUMED" => GameAction::Resumed,
             _ => {
                 return TransitionResult {
                     success: false,
@@ -187,6 +192,12 @@ impl GameFsm {
             // From PlayingMulti
             (FsmState::PlayingMulti, GameAction::GameOver) => Some(FsmState::GameOverMulti),
             (FsmState::PlayingMulti, GameAction::Disconnected) => Some(FsmState::Disconnected),
+            (FsmState::PlayingMulti, GameAction::Paused) => Some(FsmState::PausedMulti),
+
+            // From PausedMulti
+            (FsmState::PausedMulti, GameAction::Resumed) => Some(FsmState::PlayingMulti),
+            (FsmState::PausedMulti, GameAction::GameOver) => Some(FsmState::GameOverMulti),
+            (FsmState::PausedMulti, GameAction::Disconnected) => Some(FsmState::Disconnected),
 
             // From GameOverLocal
             (FsmState::GameOverLocal, GameAction::PlayAgain) => Some(FsmState::CountdownLocal),
@@ -223,6 +234,7 @@ impl GameFsm {
                 | FsmState::Waiting
                 | FsmState::CountdownMulti
                 | FsmState::PlayingMulti
+                | FsmState::PausedMulti
                 | FsmState::GameOverMulti
         )
     }
@@ -293,6 +305,32 @@ mod tests {
         assert_eq!(fsm.state(), FsmState::PlayingMulti);
     }
 
+    #[test]
+    fn test_pause_and_resume_multiplayer() {
+        let mut fsm = GameFsm::new();
+        fsm.transition(GameAction::CreateMatch);
+        fsm.transition(GameAction::Connected);
+        fsm.transition(GameAction::OpponentJoined);
+        fsm.transition(GameAction::CountdownDone);
+
+        assert!(fsm.transition(GameAction::Paused).success);
+        assert_eq!(fsm.state(), FsmState::PausedMulti);
+        assert!(!fsm.is_playing());
+        assert!(fsm.is_multiplayer());
+
+        assert!(fsm.transition_str("RESUMED").success);
+        assert_eq!(fsm.state(), FsmState::PlayingMulti);
+    }
+
+    #[test]
+    fn test_local_game_cannot_pause_through_server() {
+        let mut fsm = GameFsm::new();
+        fsm.transition(GameAction::StartLocal);
+        fsm.transition(GameAction::CountdownDone);
+        assert!(!fsm.transition(GameAction::Paused).success);
+        assert_eq!(fsm.state(), FsmState::PlayingLocal);
+    }
+
     #[test]
     fn test_transition_str() {
         let mut fsm = GameFsm::new();
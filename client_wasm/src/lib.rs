@@ -1,9 +1,11 @@
 //! WebGPU client for Pong game
 
-// FSM module - platform agnostic, can be tested natively
+// FSM and clock sync modules - platform agnostic, can be tested natively
+mod clock;
 mod fsm;
 
-// Re-export FSM types (always available)
+// Re-export platform-agnostic types (always available)
+pub use clock::ClockSync;
 pub use fsm::{FsmState, GameAction, GameFsm};
 
 // Everything below requires wasm32
@@ -24,6 +26,8 @@ mod simulation;
 #[cfg(target_arch = "wasm32")]
 mod state;
 
+#[cfg(target_arch = "wasm32")]
+use game_core::TickClock;
 #[cfg(target_arch = "wasm32")]
 use prediction::ClientPredictor;
 #[cfg(target_arch = "wasm32")]
@@ -31,7 +35,7 @@ use renderer::Renderer;
 #[cfg(target_arch = "wasm32")]
 use simulation::LocalGame;
 #[cfg(target_arch = "wasm32")]
-use state::GameState;
+use state::{GameState, MatchSettings};
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 #[cfg(target_arch = "wasm32")]
@@ -48,13 +52,14 @@ pub struct Client {
     // Frame timing
     last_frame_time: f64,
     last_sim_time: f64,
-    sim_accumulator: f32,
+    sim_clock: TickClock,
     // Metrics
     fps: f32,
     fps_frame_count: u32,
     fps_last_update: f64,
     ping_ms: f32,
-    ping_pending: Option<f64>,
+    clock: ClockSync,
+    pong_echo: Vec<u8>, // Reply to the latest Pong, for the page to send straight back
     update_display_ms: f32,
     update_last_display: f64,
     // Local game
@@ -87,12 +92,13 @@ impl WasmClient {
             paddle_dir: 0,
             last_frame_time: 0.0,
             last_sim_time: 0.0,
-            sim_accumulator: 0.0,
+            sim_clock: TickClock::new(),
             fps: 0.0,
             fps_frame_count: 0,
             fps_last_update: 0.0,
             ping_ms: 0.0,
-            ping_pending: None,
+            clock: ClockSync::new(),
+            pong_echo: Vec::new(),
             update_display_ms: 0.0,
             update_last_display: 0.0,
             local_game: None,
@@ -119,9 +125,17 @@ impl WasmClient {
             .unwrap_or_else(|| js_sys::Date::now())
     }
 
+    /// "you" or "opponent" for a player id, as used in match event strings
+    fn who(&self, player_id: u8) -> &'static str {
+        if Some(player_id) == self.0.game_state.my_player_id {
+            "you"
+        } else {
+            "opponent"
+        }
+    }
+
     fn step_simulation(client: &mut Client) {
         if let Some(local_game) = &mut client.local_game {
-            const SIM_FIXED_DT: f32 = 1.0 / 60.0;
             let now_ms = Self::performance_now();
 
             if client.last_sim_time == 0.0 {
@@ -129,13 +143,10 @@ impl WasmClient {
                 return;
             }
 
-            let frame_time_ms = (now_ms - client.last_sim_time) / 1000.0;
-            client.sim_accumulator += frame_time_ms as f32;
+            let frame_secs = (now_ms - client.last_sim_time) / 1000.0;
             client.last_sim_time = now_ms;
 
-            while client.sim_accumulator >= SIM_FIXED_DT {
-                client.sim_accumulator -= SIM_FIXED_DT;
-
+            for _ in 0..client.sim_clock.advance(frame_secs as f32) {
                 let (winner, ball_data, left_y, right_y, score_left, score_right) =
                     local_game.step(client.local_paddle_y);
 
@@ -152,6 +163,7 @@ impl WasmClient {
                         ball_vx: ball_vel.x,
                         ball_vy: ball_vel.y,
                         tick: 0,
+                        server_time_ms: 0,
                         score_left,
                         score_right,
                     });
@@ -186,16 +198,11 @@ impl WasmClient {
         client.last_frame_time = now_ms;
 
         // Update local paddle for immediate response (works for both local and multiplayer)
-        const PADDLE_SPEED: f32 = 18.0;
-        const ARENA_HEIGHT: f32 = 24.0;
-        const PADDLE_HEIGHT: f32 = 4.0;
-        let half_height = PADDLE_HEIGHT / 2.0;
+        let config = client.game_state.settings.config();
 
         // Simple local integration (client authority)
-        client.local_paddle_y += client.paddle_dir as f32 * PADDLE_SPEED * render_dt;
-        client.local_paddle_y = client
-            .local_paddle_y
-            .clamp(half_height, ARENA_HEIGHT - half_height);
+        client.local_paddle_y += client.paddle_dir as f32 * config.paddle_speed * render_dt;
+        client.local_paddle_y = config.clamp_paddle_y(client.local_paddle_y);
 
         // FPS calculation
         client.fps_frame_count += 1;
@@ -232,12 +239,20 @@ impl WasmClient {
         let msg = proto::S2C::from_bytes(&bytes)
             .map_err(|e| format!("Failed to deserialize: {:?}", e))?;
 
-        if let proto::S2C::Pong { .. } = msg {
-            if let Some(sent_time) = client.ping_pending {
-                let rtt = Self::performance_now() - sent_time;
-                client.ping_ms = rtt as f32;
-                client.ping_pending = None;
-            }
+        if let proto::S2C::Pong {
+            t_ms,
+            server_recv_ms,
+            server_send_ms,
+        } = msg
+        {
+            client.clock.add_sample(
+                t_ms as f64,
+                server_recv_ms as f64,
+                server_send_ms as f64,
+                Self::performance_now(),
+            );
+            client.ping_ms = client.clock.rtt_ms() as f32;
+            client.pong_echo = network::create_pong_echo_message(server_send_ms)?;
             return Ok(());
         }
 
@@ -250,16 +265,16 @@ impl WasmClient {
                 // Reset timing to prevent massive dt on first frame
                 client.last_frame_time = 0.0;
                 client.last_sim_time = 0.0;
-                client.sim_accumulator = 0.0;
+                client.sim_clock.reset();
             }
             _ => {}
         }
 
         let is_game_state = matches!(msg, proto::S2C::GameState(_));
-        let server_tick = if let proto::S2C::GameState(snapshot) = &msg {
-            Some(snapshot.tick)
+        let (server_tick, snapshot_time_ms) = if let proto::S2C::GameState(snapshot) = &msg {
+            (Some(snapshot.tick), Some(snapshot.server_time_ms))
         } else {
-            None
+            (None, None)
         };
 
         if let Some(tick) = server_tick {
@@ -269,11 +284,24 @@ impl WasmClient {
         network::handle_message(msg, &mut client.game_state)
             .map_err(|e| JsValue::from_str(&format!("Msg error: {}", e)))?;
 
+        // Age the snapshot by its time in flight so extrapolation starts from
+        // where the server is now rather than when the message arrived.
+        // Spectator snapshots may be deliberately delayed, so they are not aged.
+        if let Some(server_time_ms) = snapshot_time_ms {
+            if client.clock.is_synced() && !client.game_state.is_spectator {
+                let server_now = client.clock.to_server_time(Self::performance_now());
+                let age_ms = server_now - server_time_ms as f64;
+                client.game_state.set_snapshot_age((age_ms / 1000.0) as f32);
+            }
+        }
+
         if is_game_state && !client.predictor.is_active() && client.local_game.is_none() {
             if let Some(snapshot) = client.game_state.get_current_snapshot() {
-                client
-                    .predictor
-                    .initialize(&snapshot, Self::performance_now());
+                client.predictor.initialize(
+                    &snapshot,
+                    &client.game_state.settings,
+                    Self::performance_now(),
+                );
                 if !client.local_paddle_initialized {
                     let pid = client.game_state.get_player_id().unwrap_or(0);
                     client.local_paddle_y = if pid == 0 {
@@ -289,14 +317,52 @@ impl WasmClient {
         Ok(())
     }
 
+    /// profile_id: Persistent id for ratings (empty to play unrated)
+    #[wasm_bindgen]
+    pub fn get_join_bytes(&self, code: String, profile_id: String) -> Vec<u8> {
+        network::create_join_message(&code, &profile_id).unwrap_or_default()
+    }
+
+    /// Build a message reclaiming our slot after a dropped connection
+    /// Returns empty bytes if we never joined as a player
+    #[wasm_bindgen]
+    pub fn get_rejoin_bytes(&self, code: String) -> Vec<u8> {
+        match self.0.game_state.session_token {
+            Some(token) => network::create_rejoin_message(&code, token).unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Build a message to watch a match read-only (optionally a few seconds behind)
+    #[wasm_bindgen]
+    pub fn get_spectate_bytes(&self, code: String, delayed: bool) -> Vec<u8> {
+        network::create_spectate_message(&code, delayed).unwrap_or_default()
+    }
+
+    #[wasm_bindgen]
+    pub fn is_spectator(&self) -> bool {
+        self.0.game_state.is_spectator
+    }
+
+    #[wasm_bindgen]
+    pub fn get_spectator_count(&self) -> u16 {
+        self.0.game_state.spectator_count
+    }
+
+    /// Offer a rematch, or accept one with the same terms
+    #[wasm_bindgen]
+    pub fn get_rematch_bytes(&self, swap_sides: bool) -> Vec<u8> {
+        network::create_rematch_message(swap_sides).unwrap_or_default()
+    }
+
     #[wasm_bindgen]
-    pub fn get_join_bytes(&self, code: String) -> Vec<u8> {
-        network::create_join_message(&code).unwrap_or_default()
+    pub fn get_pause_bytes(&self) -> Vec<u8> {
+        network::create_pause_message().unwrap_or_default()
     }
 
     #[wasm_bindgen]
-    pub fn get_restart_bytes(&self) -> Vec<u8> {
-        network::create_restart_message().unwrap_or_default()
+    pub fn get_resume_bytes(&self) -> Vec<u8> {
+        network::create_resume_message().unwrap_or_default()
     }
 
     #[wasm_bindgen]
@@ -308,9 +374,12 @@ impl WasmClient {
     #[wasm_bindgen]
     pub fn get_input_bytes(&mut self) -> Vec<u8> {
         let client = &mut self.0;
+        if client.game_state.is_spectator {
+            return Vec::new();
+        }
         if client.local_game.is_some() {
             let pid = client.game_state.get_player_id().unwrap_or(0);
-            return network::create_input_message(pid, client.local_paddle_y, 0)
+            return network::create_input_message(pid, client.local_paddle_y, 0, 0)
                 .unwrap_or_default();
         }
 
@@ -324,7 +393,18 @@ impl WasmClient {
         // because we are authoritative.
         // But if predictor is used for opponent, we might leave it be.
 
-        network::create_input_message(pid, client.local_paddle_y, seq).unwrap_or_default()
+        // Stamp with the server clock so the server can judge hits where we saw the paddle
+        let server_time_ms = if client.clock.is_synced() {
+            client
+                .clock
+                .to_server_time(Self::performance_now())
+                .max(0.0) as u64
+        } else {
+            0
+        };
+
+        network::create_input_message(pid, client.local_paddle_y, seq, server_time_ms)
+            .unwrap_or_default()
     }
 
     #[wasm_bindgen]
@@ -351,7 +431,12 @@ impl WasmClient {
     }
 
     /// Get and clear the latest match event from server
-    /// Returns: "match_found", "countdown:3", "countdown:2", "countdown:1", "game_start", "opponent_disconnected", or empty string
+    /// Returns: "match_found", "countdown:3", "countdown:2", "countdown:1", "game_start", "opponent_disconnected",
+    /// "opponent_reconnecting:15" (seconds left), "opponent_reconnected",
+    /// "paused:you:1:30" (who, their pauses left, seconds until auto-resume), "pause_denied:0",
+    /// "resume_requested:opponent", "resume_countdown:3", "resumed",
+    /// "rematch_requested:opponent:swap:30" (who, "swap" or "same" sides, seconds to accept),
+    /// "rematch_expired", or empty string
     #[wasm_bindgen]
     pub fn get_match_event(&mut self) -> String {
         use state::MatchEvent;
@@ -362,18 +447,90 @@ impl WasmClient {
             MatchEvent::Countdown(n) => format!("countdown:{}", n),
             MatchEvent::GameStart => "game_start".to_string(),
             MatchEvent::OpponentDisconnected => "opponent_disconnected".to_string(),
+            MatchEvent::OpponentReconnecting(n) => format!("opponent_reconnecting:{}", n),
+            MatchEvent::OpponentReconnected => "opponent_reconnected".to_string(),
+            MatchEvent::Paused {
+                player_id,
+                pauses_left,
+                seconds,
+            } => format!("paused:{}:{}:{}", self.who(player_id), pauses_left, seconds),
+            MatchEvent::PauseDenied(pauses_left) => format!("pause_denied:{}", pauses_left),
+            MatchEvent::ResumeRequested(player_id) => {
+                format!("resume_requested:{}", self.who(player_id))
+            }
+            MatchEvent::ResumeCountdown(n) => format!("resume_countdown:{}", n),
+            MatchEvent::Resumed => "resumed".to_string(),
+            MatchEvent::RematchRequested {
+                player_id,
+                swap_sides,
+                seconds,
+            } => format!(
+                "rematch_requested:{}:{}:{}",
+                self.who(player_id),
+                if swap_sides { "swap" } else { "same" },
+                seconds
+            ),
+            MatchEvent::RematchExpired => "rematch_expired".to_string(),
         }
     }
 
+    /// Get and clear gameplay events from server for audio/visual effects
+    /// Returns entries like "paddle_hit:0:1.9:12.0", "wall_hit:20.0:0.5", "point_scored:1"
+    #[wasm_bindgen]
+    pub fn take_game_events(&mut self) -> Vec<String> {
+        use proto::GameEvent;
+        std::mem::take(&mut self.0.game_state.game_events)
+            .into_iter()
+            .map(|event| match event {
+                GameEvent::PaddleHit { player_id, x, y } => {
+                    format!("paddle_hit:{}:{:.2}:{:.2}", player_id, x, y)
+                }
+                GameEvent::WallHit { x, y } => format!("wall_hit:{:.2}:{:.2}", x, y),
+                GameEvent::PointScored { scorer } => format!("point_scored:{}", scorer),
+            })
+            .collect()
+    }
+
+    /// Get and clear incoming chat items
+    /// Returns entries like "0:phrase:nice_shot" or "1:emote:wave" (0 = left paddle, 1 = right)
+    #[wasm_bindgen]
+    pub fn take_chat_events(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.0.game_state.chat_events)
+            .into_iter()
+            .map(|(player_id, item)| {
+                let kind = match item {
+                    proto::ChatItem::Phrase(_) => "phrase",
+                    proto::ChatItem::Emote(_) => "emote",
+                };
+                format!("{}:{}:{}", player_id, kind, network::chat_item_name(item))
+            })
+            .collect()
+    }
+
+    /// Build a chat message for a phrase or emote name (e.g. "nice_shot", "wave")
+    #[wasm_bindgen]
+    pub fn get_chat_bytes(&self, item: String) -> Vec<u8> {
+        let pid = self.0.game_state.get_player_id().unwrap_or(0);
+        network::create_chat_message(pid, &item).unwrap_or_default()
+    }
+
+    /// Build a message muting or unmuting the opponent's chat
+    #[wasm_bindgen]
+    pub fn get_mute_opponent_bytes(&self, muted: bool) -> Vec<u8> {
+        let pid = self.0.game_state.get_player_id().unwrap_or(0);
+        network::create_mute_message(pid, 1 - pid.min(1), muted).unwrap_or_default()
+    }
+
     #[wasm_bindgen]
     pub fn start_local_game(&mut self) {
         let seed = Self::performance_now() as u64;
         self.0.local_game = Some(LocalGame::new(seed));
         self.0.game_state.reset();
+        self.0.game_state.settings = MatchSettings::default();
         self.0.game_state.set_player_id(0);
         // Reset simulation timing
         self.0.last_sim_time = 0.0;
-        self.0.sim_accumulator = 0.0;
+        self.0.sim_clock.reset();
     }
 
     #[wasm_bindgen]
@@ -389,8 +546,9 @@ impl WasmClient {
     pub fn reset_for_multiplayer(&mut self) {
         // Clear any local game
         self.0.local_game = None;
-        // Reset game state
+        // Reset game state (settings arrive again in Welcome)
         self.0.game_state.reset();
+        self.0.game_state.settings = MatchSettings::default();
         // Reset predictor
         self.0.predictor = ClientPredictor::new();
         // Reset paddle state
@@ -399,7 +557,7 @@ impl WasmClient {
         // Reset timing
         self.0.last_frame_time = 0.0;
         self.0.last_sim_time = 0.0;
-        self.0.sim_accumulator = 0.0;
+        self.0.sim_clock.reset();
     }
 
     #[wasm_bindgen]
@@ -414,8 +572,33 @@ impl WasmClient {
     #[wasm_bindgen]
     pub fn send_ping(&mut self) -> Vec<u8> {
         let now = Self::performance_now();
-        self.0.ping_pending = Some(now);
-        network::create_ping_message(now as u32).unwrap_or_default()
+        // Report link quality so the server can pick our snapshot rate
+        let rtt_ms = self.0.clock.rtt_ms().round().min(u16::MAX as f64) as u16;
+        let snapshot_tick = self.0.game_state.latest_tick();
+        network::create_ping_message(now as u32, rtt_ms, snapshot_tick).unwrap_or_default()
+    }
+
+    /// Echo for the Pong just received (empty if none). Send it at once:
+    /// the server times our round trip by it.
+    #[wasm_bindgen]
+    pub fn take_pong_echo(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.0.pong_echo)
+    }
+
+    /// Milliseconds since the server started play, on the synchronized clock,
+    /// or -1 before GameStart or the first pong. Lets both players clear the
+    /// "GO!" display together regardless of message arrival.
+    #[wasm_bindgen]
+    pub fn ms_since_game_start(&self) -> f64 {
+        self.0
+            .game_state
+            .game_start_server_ms
+            .and_then(|start_ms| {
+                self.0
+                    .clock
+                    .elapsed_since(start_ms as f64, Self::performance_now())
+            })
+            .unwrap_or(-1.0)
     }
 
     #[wasm_bindgen]
@@ -1,13 +1,34 @@
 //! Network message handling
 
 use crate::state::{GameState, MatchEvent};
-use proto::{C2S, S2C};
+use proto::{ChatItem, Emote, QuickChat, Role, C2S, S2C};
 
 /// Handle incoming server message
 pub fn handle_message(msg: S2C, game_state: &mut GameState) -> Result<(), String> {
     match msg {
-        S2C::Welcome { player_id } => {
-            game_state.set_player_id(player_id);
+        S2C::Welcome {
+            player_id,
+            role,
+            session_token,
+            settings,
+        } => {
+            game_state.settings = settings;
+            match role {
+                Role::Player => {
+                    game_state.set_player_id(player_id);
+                    game_state.session_token = Some(session_token);
+                    game_state.is_spectator = false;
+                }
+                Role::Spectator => {
+                    // No paddle of our own: both paddles come from server state
+                    game_state.my_player_id = None;
+                    game_state.session_token = None;
+                    game_state.is_spectator = true;
+                }
+            }
+        }
+        S2C::SpectatorCount { count } => {
+            game_state.spectator_count = count;
         }
         S2C::MatchFound => {
             game_state.reset();
@@ -17,9 +38,10 @@ pub fn handle_message(msg: S2C, game_state: &mut GameState) -> Result<(), String
             game_state.reset();
             game_state.match_event = MatchEvent::Countdown(seconds);
         }
-        S2C::GameStart => {
+        S2C::GameStart { server_time_ms } => {
             game_state.reset();
             game_state.match_event = MatchEvent::GameStart;
+            game_state.game_start_server_ms = Some(server_time_ms);
             game_state.winner = None;
             game_state.set_scores(0, 0);
         }
@@ -27,13 +49,66 @@ pub fn handle_message(msg: S2C, game_state: &mut GameState) -> Result<(), String
             game_state.set_scores(snapshot.score_left, snapshot.score_right);
             game_state.set_current(snapshot);
         }
+        S2C::Events { tick: _, events } => {
+            game_state.game_events.extend(events);
+        }
         S2C::GameOver { winner } => {
             game_state.set_winner(winner);
         }
+        S2C::Chat { player_id, item } => {
+            game_state.chat_events.push((player_id, item));
+        }
         S2C::OpponentDisconnected => {
             game_state.match_event = MatchEvent::OpponentDisconnected;
         }
-        S2C::Pong { t_ms: _ } => {
+        S2C::OpponentReconnecting { player_id, seconds } => {
+            if Some(player_id) != game_state.my_player_id {
+                game_state.match_event = MatchEvent::OpponentReconnecting(seconds);
+            }
+        }
+        S2C::OpponentReconnected { player_id } => {
+            if Some(player_id) != game_state.my_player_id {
+                game_state.match_event = MatchEvent::OpponentReconnected;
+            }
+        }
+        S2C::Paused {
+            player_id,
+            pauses_left,
+            seconds,
+        } => {
+            game_state.match_event = MatchEvent::Paused {
+                player_id,
+                pauses_left,
+                seconds,
+            };
+        }
+        S2C::PauseDenied { pauses_left } => {
+            game_state.match_event = MatchEvent::PauseDenied(pauses_left);
+        }
+        S2C::ResumeRequested { player_id } => {
+            game_state.match_event = MatchEvent::ResumeRequested(player_id);
+        }
+        S2C::ResumeCountdown { seconds } => {
+            game_state.match_event = MatchEvent::ResumeCountdown(seconds);
+        }
+        S2C::Resumed { server_time_ms: _ } => {
+            game_state.match_event = MatchEvent::Resumed;
+        }
+        S2C::RematchRequested {
+            player_id,
+            swap_sides,
+            seconds,
+        } => {
+            game_state.match_event = MatchEvent::RematchRequested {
+                player_id,
+                swap_sides,
+                seconds,
+            };
+        }
+        S2C::RematchExpired => {
+            game_state.match_event = MatchEvent::RematchExpired;
+        }
+        S2C::Pong { .. } => {
             // Ping response handled by caller, should not reach here
             return Err("Pong message should be handled separately".to_string());
         }
@@ -42,35 +117,156 @@ pub fn handle_message(msg: S2C, game_state: &mut GameState) -> Result<(), String
 }
 
 /// Create join message bytes
-pub fn create_join_message(code: &str) -> Result<Vec<u8>, String> {
+pub fn create_join_message(code: &str, profile_id: &str) -> Result<Vec<u8>, String> {
     let code_bytes: Vec<u8> = code.bytes().take(5).collect();
     if code_bytes.len() != 5 {
         return Err("Match code must be exactly 5 characters".to_string());
     }
     let mut code_array = [0u8; 5];
     code_array.copy_from_slice(&code_bytes[..5]);
-    C2S::Join { code: code_array }
-        .to_bytes()
-        .map_err(|e| format!("Failed to serialize join message: {:?}", e))
+    C2S::Join {
+        code: code_array,
+        profile_id: (!profile_id.is_empty()).then(|| profile_id.to_string()),
+    }
+    .to_bytes()
+    .map_err(|e| format!("Failed to serialize join message: {:?}", e))
+}
+
+/// Create spectate message bytes
+pub fn create_spectate_message(code: &str, delayed: bool) -> Result<Vec<u8>, String> {
+    let code_bytes: Vec<u8> = code.bytes().take(5).collect();
+    if code_bytes.len() != 5 {
+        return Err("Match code must be exactly 5 characters".to_string());
+    }
+    let mut code_array = [0u8; 5];
+    code_array.copy_from_slice(&code_bytes[..5]);
+    C2S::Spectate {
+        code: code_array,
+        delayed,
+    }
+    .to_bytes()
+    .map_err(|e| format!("Failed to serialize spectate message: {:?}", e))
+}
+
+/// Create rejoin message bytes
+pub fn create_rejoin_message(code: &str, session_token: u64) -> Result<Vec<u8>, String> {
+    let code_bytes: Vec<u8> = code.bytes().take(5).collect();
+    if code_bytes.len() != 5 {
+        return Err("Match code must be exactly 5 characters".to_string());
+    }
+    let mut code_array = [0u8; 5];
+    code_array.copy_from_slice(&code_bytes[..5]);
+    C2S::Rejoin {
+        code: code_array,
+        session_token,
+    }
+    .to_bytes()
+    .map_err(|e| format!("Failed to serialize rejoin message: {:?}", e))
 }
 
 /// Create input message bytes
-pub fn create_input_message(player_id: u8, y: f32, seq: u32) -> Result<Vec<u8>, String> {
-    C2S::Input { player_id, y, seq }
+pub fn create_input_message(
+    player_id: u8,
+    y: f32,
+    seq: u32,
+    server_time_ms: u64,
+) -> Result<Vec<u8>, String> {
+    C2S::Input {
+        player_id,
+        y,
+        seq,
+        server_time_ms,
+    }
+    .to_bytes()
+    .map_err(|e| format!("Failed to serialize input message: {:?}", e))
+}
+
+/// Create rematch offer/accept bytes
+pub fn create_rematch_message(swap_sides: bool) -> Result<Vec<u8>, String> {
+    C2S::Rematch { swap_sides }
         .to_bytes()
-        .map_err(|e| format!("Failed to serialize input message: {:?}", e))
+        .map_err(|e| format!("Failed to serialize rematch message: {:?}", e))
 }
 
-/// Create restart message bytes
-pub fn create_restart_message() -> Result<Vec<u8>, String> {
-    C2S::Restart
+/// Create pause request bytes
+pub fn create_pause_message() -> Result<Vec<u8>, String> {
+    C2S::Pause
         .to_bytes()
-        .map_err(|e| format!("Failed to serialize restart message: {:?}", e))
+        .map_err(|e| format!("Failed to serialize pause message: {:?}", e))
 }
 
-/// Create ping message bytes
-pub fn create_ping_message(t_ms: u32) -> Result<Vec<u8>, String> {
-    C2S::Ping { t_ms }
+/// Create resume request bytes
+pub fn create_resume_message() -> Result<Vec<u8>, String> {
+    C2S::Resume
         .to_bytes()
-        .map_err(|e| format!("Failed to serialize ping message: {:?}", e))
+        .map_err(|e| format!("Failed to serialize resume message: {:?}", e))
+}
+
+/// Create chat message bytes from an item name (see `chat_item_name`)
+pub fn create_chat_message(player_id: u8, name: &str) -> Result<Vec<u8>, String> {
+    let item = parse_chat_item(name).ok_or_else(|| format!("Unknown chat item: {}", name))?;
+    C2S::Chat { player_id, item }
+        .to_bytes()
+        .map_err(|e| format!("Failed to serialize chat message: {:?}", e))
+}
+
+/// Create mute/unmute message bytes
+pub fn create_mute_message(player_id: u8, target: u8, muted: bool) -> Result<Vec<u8>, String> {
+    C2S::SetMute {
+        player_id,
+        target,
+        muted,
+    }
+    .to_bytes()
+    .map_err(|e| format!("Failed to serialize mute message: {:?}", e))
+}
+
+/// Name used by the UI for a chat item
+pub fn chat_item_name(item: ChatItem) -> &'static str {
+    match item {
+        ChatItem::Phrase(QuickChat::GoodLuck) => "good_luck",
+        ChatItem::Phrase(QuickChat::NiceShot) => "nice_shot",
+        ChatItem::Phrase(QuickChat::WellPlayed) => "well_played",
+        ChatItem::Phrase(QuickChat::Oops) => "oops",
+        ChatItem::Phrase(QuickChat::Thanks) => "thanks",
+        ChatItem::Emote(Emote::Wave) => "wave",
+        ChatItem::Emote(Emote::Laugh) => "laugh",
+        ChatItem::Emote(Emote::Wow) => "wow",
+        ChatItem::Emote(Emote::Angry) => "angry",
+    }
+}
+
+/// Parse a UI chat item name
+pub fn parse_chat_item(name: &str) -> Option<ChatItem> {
+    let item = match name {
+        "good_luck" => ChatItem::Phrase(QuickChat::GoodLuck),
+        "nice_shot" => ChatItem::Phrase(QuickChat::NiceShot),
+        "well_played" => ChatItem::Phrase(QuickChat::WellPlayed),
+        "oops" => ChatItem::Phrase(QuickChat::Oops),
+        "thanks" => ChatItem::Phrase(QuickChat::Thanks),
+        "wave" => ChatItem::Emote(Emote::Wave),
+        "laugh" => ChatItem::Emote(Emote::Laugh),
+        "wow" => ChatItem::Emote(Emote::Wow),
+        "angry" => ChatItem::Emote(Emote::Angry),
+        _ => return None,
+    };
+    Some(item)
+}
+
+/// Create the echo of a Pong's server send time
+pub fn create_pong_echo_message(server_send_ms: u64) -> Result<Vec<u8>, String> {
+    C2S::PongEcho { server_send_ms }
+        .to_bytes()
+        .map_err(|e| format!("Failed to serialize pong echo: {:?}", e))
+}
+
+/// Create ping message bytes
+pub fn create_ping_message(t_ms: u32, rtt_ms: u16, snapshot_tick: u32) -> Result<Vec<u8>, String> {
+    C2S::Ping {
+        t_ms,
+        rtt_ms,
+        snapshot_tick,
+    }
+    .to_bytes()
+    .map_err(|e| format!("Failed to serialize ping message: {:?}", e))
 }
@@ -1,7 +1,7 @@
-use crate::state::GameStateSnapshot;
+use crate::state::{GameStateSnapshot, MatchSettings};
 use game_core::{
     create_ball, create_paddle, step, Config, Events, GameMap, GameRng, NetQueue, RespawnState,
-    Score, Time,
+    Score, TickClock, Time,
 };
 use hecs::World;
 
@@ -25,7 +25,7 @@ pub struct ClientPredictor {
     pub input_history: Vec<(u32, i8)>, // (seq, paddle_dir)
 
     // Timing
-    pub accumulator: f32,
+    pub clock: TickClock,
     pub last_update_time: f64, // ms
 }
 
@@ -45,7 +45,7 @@ impl ClientPredictor {
             last_reconciled_tick: 0,
             predicted_tick: 0,
             input_history: Vec::new(),
-            accumulator: 0.0,
+            clock: TickClock::new(),
             last_update_time: 0.0,
         }
     }
@@ -54,9 +54,14 @@ impl ClientPredictor {
         self.world.is_some()
     }
 
-    pub fn initialize(&mut self, snapshot: &GameStateSnapshot, now_ms: f64) {
-        let map = GameMap::new();
-        let config = Config::new();
+    pub fn initialize(
+        &mut self,
+        snapshot: &GameStateSnapshot,
+        settings: &MatchSettings,
+        now_ms: f64,
+    ) {
+        let map = settings.map();
+        let config = settings.config();
         let mut world = World::new();
         let rng = GameRng::new(now_ms as u64);
 
@@ -72,7 +77,7 @@ impl ClientPredictor {
         );
 
         self.world = Some(world);
-        self.time = Some(Time::new(0.016, 0.0));
+        self.time = Some(Time::new(TickClock::DT, 0.0));
         self.map = Some(map);
         self.config = Some(config);
         self.score = Some(Score::new());
@@ -82,7 +87,7 @@ impl ClientPredictor {
         self.respawn_state = Some(RespawnState::new());
         self.last_reconciled_tick = snapshot.tick;
         self.predicted_tick = snapshot.tick;
-        self.accumulator = 0.0;
+        self.clock.reset();
         self.last_update_time = now_ms;
     }
 
@@ -93,8 +98,6 @@ impl ClientPredictor {
             return;
         }
 
-        const SIM_FIXED_DT: f32 = 1.0 / 60.0;
-
         if let (
             Some(ref mut world),
             Some(ref mut time),
@@ -124,14 +127,14 @@ impl ClientPredictor {
                     break;
                 }
             }
-            let mut new_y = current_y + (paddle_dir as f32) * config.paddle_speed * SIM_FIXED_DT;
+            let mut new_y = current_y + (paddle_dir as f32) * config.paddle_speed * TickClock::DT;
             let half_height = config.paddle_height / 2.0;
             new_y = new_y.clamp(half_height, config.arena_height - half_height);
 
             net_queue.push_input(player_id, new_y);
 
             // Update time
-            *time = Time::new(SIM_FIXED_DT, time.now + SIM_FIXED_DT);
+            *time = Time::new(TickClock::DT, time.now + TickClock::DT);
 
             step(
                 world,
@@ -155,20 +158,15 @@ impl ClientPredictor {
             return;
         }
 
-        const SIM_FIXED_DT: f32 = 1.0 / 60.0;
-
         // Init last time if needed
         if self.last_update_time == 0.0 {
             self.last_update_time = now_ms;
         }
 
-        let frame_time_ms = (now_ms - self.last_update_time) / 1000.0;
-        self.accumulator += frame_time_ms as f32;
+        let frame_secs = (now_ms - self.last_update_time) / 1000.0;
         self.last_update_time = now_ms;
 
-        while self.accumulator >= SIM_FIXED_DT {
-            self.accumulator -= SIM_FIXED_DT;
-
+        for _ in 0..self.clock.advance(frame_secs as f32) {
             if let (
                 Some(ref mut world),
                 Some(ref mut time),
@@ -202,13 +200,13 @@ impl ClientPredictor {
                     }
                 }
                 let mut new_y =
-                    current_y + (current_input as f32) * config.paddle_speed * SIM_FIXED_DT;
+                    current_y + (current_input as f32) * config.paddle_speed * TickClock::DT;
                 let half_height = config.paddle_height / 2.0;
                 new_y = new_y.clamp(half_height, config.arena_height - half_height);
 
                 net_queue.push_input(player_id, new_y);
 
-                *time = Time::new(SIM_FIXED_DT, time.now + SIM_FIXED_DT);
+                *time = Time::new(TickClock::DT, time.now + TickClock::DT);
 
                 step(
                     world,
@@ -292,11 +290,12 @@ mod tests {
             paddle_left_y: 12.0,
             paddle_right_y: 12.0,
             tick: 100,
+            server_time_ms: 0,
             score_left: 0,
             score_right: 0,
         };
 
-        predictor.initialize(&snapshot, 1000.0);
+        predictor.initialize(&snapshot, &MatchSettings::default(), 1000.0);
 
         assert!(predictor.is_active());
         assert_eq!(predictor.predicted_tick, 100);
@@ -315,11 +314,12 @@ mod tests {
             paddle_left_y: 12.0,
             paddle_right_y: 12.0,
             tick: 100,
+            server_time_ms: 0,
             score_left: 0,
             score_right: 0,
         };
 
-        predictor.initialize(&snapshot, 1000.0);
+        predictor.initialize(&snapshot, &MatchSettings::default(), 1000.0);
 
         // Process input
         predictor.process_input(0, 1);
@@ -338,11 +338,12 @@ mod tests {
             paddle_left_y: 12.0,
             paddle_right_y: 12.0,
             tick: 100,
+            server_time_ms: 0,
             score_left: 0,
             score_right: 0,
         };
 
-        predictor.initialize(&snapshot, 1000.0);
+        predictor.initialize(&snapshot, &MatchSettings::default(), 1000.0);
 
         // Predict forward
         predictor.process_input(0, 1); // tick 101
@@ -366,11 +367,12 @@ mod tests {
             paddle_left_y: 12.0,
             paddle_right_y: 12.0,
             tick: 100,
+            server_time_ms: 0,
             score_left: 0,
             score_right: 0,
         };
 
-        predictor.initialize(&snapshot, 1000.0);
+        predictor.initialize(&snapshot, &MatchSettings::default(), 1000.0);
 
         // Predict forward a bit
         for _ in 0..5 {
@@ -397,11 +399,12 @@ mod tests {
             paddle_left_y: 12.0,
             paddle_right_y: 12.0,
             tick: 100,
+            server_time_ms: 0,
             score_left: 0,
             score_right: 0,
         };
 
-        predictor.initialize(&snapshot, 1000.0);
+        predictor.initialize(&snapshot, &MatchSettings::default(), 1000.0);
 
         // Predict forward A LOT (latency spike or stall)
         for _ in 0..30 {
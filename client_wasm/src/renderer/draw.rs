@@ -43,11 +43,14 @@ fn update_buffers(
     local_paddle_y: f32,
     is_local_game: bool,
 ) {
-    let paddle_left_x = 1.5;
-    let paddle_right_x = 30.5;
-    let paddle_width = 0.8;
-    let paddle_height = 4.0;
-    let ball_radius = 0.5;
+    let config = game_state.settings.config();
+    renderer.set_arena(config.arena_width, config.arena_height);
+
+    let paddle_left_x = config.paddle_x(0);
+    let paddle_right_x = config.paddle_x(1);
+    let paddle_width = config.paddle_width;
+    let paddle_height = config.paddle_height;
+    let ball_radius = config.ball_radius;
 
     let my_player_id = game_state.get_player_id();
 
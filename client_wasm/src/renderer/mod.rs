@@ -4,7 +4,7 @@ pub mod pipeline;
 pub mod resources;
 pub mod shaders; // Add draw module
 
-use crate::camera::Camera;
+use crate::camera::{Camera, CameraUniform};
 use crate::mesh::{create_circle, create_rectangle, Mesh};
 use crate::state::GameState;
 use resources::{GameBuffers, InstanceData, TrailTextures};
@@ -18,6 +18,7 @@ pub struct Renderer {
     pub surface_config: SurfaceConfiguration,
     pub size: (u32, u32),
     pub camera: Camera,
+    pub arena: (f32, f32), // Arena size the camera currently frames
 
     // Pipelines
     pub main_pipeline: RenderPipeline,
@@ -42,7 +43,11 @@ pub struct Renderer {
 impl Renderer {
     pub async fn new(canvas: web_sys::HtmlCanvasElement) -> Result<Self, String> {
         let ctx = init::init_wgpu(canvas).await?;
-        let camera = Camera::orthographic(32.0, 24.0);
+        let arena = (
+            game_core::Params::ARENA_WIDTH,
+            game_core::Params::ARENA_HEIGHT,
+        );
+        let camera = Camera::orthographic(arena.0, arena.1);
 
         let buffers = resources::create_buffers(&ctx.device, &camera);
         let textures = resources::create_trail_textures(&ctx.device, &ctx.config);
@@ -99,6 +104,7 @@ impl Renderer {
             surface_config: ctx.config,
             size: ctx.size,
             camera,
+            arena,
             main_pipeline: pipes.main_pipeline,
             trail_pipeline: pipes.trail_pipeline,
             camera_bind_group,
@@ -113,6 +119,19 @@ impl Renderer {
         })
     }
 
+    /// Reframe the camera when the arena size changes (match settings)
+    pub fn set_arena(&mut self, width: f32, height: f32) {
+        if self.arena == (width, height) {
+            return;
+        }
+        self.arena = (width, height);
+        self.camera = Camera::orthographic(width, height);
+        let mut uniform = CameraUniform::new();
+        uniform.update_view_proj(&self.camera);
+        self.queue
+            .write_buffer(&self.buffers.camera, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
     pub fn draw(
         &mut self,
         game_state: &GameState,
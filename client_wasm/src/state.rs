@@ -1,6 +1,7 @@
 //! Game state management with interpolation
 
-pub use proto::GameStateSnapshot;
+pub use game_core::MatchSettings;
+pub use proto::{ChatItem, GameEvent, GameStateSnapshot};
 
 /// Events from server for match lifecycle
 #[derive(Debug, Clone, PartialEq)]
@@ -10,6 +11,23 @@ pub enum MatchEvent {
     Countdown(u8),
     GameStart,
     OpponentDisconnected,
+    OpponentReconnecting(u8),
+    OpponentReconnected,
+    Paused {
+        player_id: u8,
+        pauses_left: u8,
+        seconds: u8,
+    },
+    PauseDenied(u8),
+    ResumeRequested(u8),
+    ResumeCountdown(u8),
+    Resumed,
+    RematchRequested {
+        player_id: u8,
+        swap_sides: bool,
+        seconds: u8,
+    },
+    RematchExpired,
 }
 
 /// Game state tracking with interpolation
@@ -26,6 +44,12 @@ pub struct GameState {
     score_left: u8,
     score_right: u8,
     pub my_player_id: Option<u8>,
+    // Token from Welcome for reclaiming our slot after a dropped connection
+    pub session_token: Option<u64>,
+    pub is_spectator: bool,
+    pub spectator_count: u16,
+    // Rules the match was created with (from Welcome), kept across resets within a match
+    pub settings: MatchSettings,
     pub winner: Option<u8>,
     // Smooth correction state for ball position
     ball_display_x: f32,
@@ -35,6 +59,12 @@ pub struct GameState {
     paddle_right_display_y: f32,
     // Latest match event from server
     pub match_event: MatchEvent,
+    // Gameplay events from server not yet consumed by effects
+    pub game_events: Vec<GameEvent>,
+    // Server clock when play began (from GameStart)
+    pub game_start_server_ms: Option<u64>,
+    // Incoming chat items (sender player_id, item) not yet shown by the UI
+    pub chat_events: Vec<(u8, ChatItem)>,
 }
 
 impl GameState {
@@ -47,6 +77,7 @@ impl GameState {
             ball_vx: 0.0,
             ball_vy: 0.0,
             tick: 0,
+            server_time_ms: 0,
             score_left: 0,
             score_right: 0,
         };
@@ -58,12 +89,19 @@ impl GameState {
             score_left: 0,
             score_right: 0,
             my_player_id: None,
+            session_token: None,
+            is_spectator: false,
+            spectator_count: 0,
+            settings: MatchSettings::default(),
             winner: None,
             ball_display_x: 16.0,
             ball_display_y: 12.0,
             paddle_left_display_y: 12.0,
             paddle_right_display_y: 12.0,
             match_event: MatchEvent::None,
+            game_events: Vec::new(),
+            game_start_server_ms: None,
+            chat_events: Vec::new(),
         }
     }
 
@@ -76,6 +114,7 @@ impl GameState {
             ball_vx: 0.0,
             ball_vy: 0.0,
             tick: 0,
+            server_time_ms: 0,
             score_left: 0,
             score_right: 0,
         };
@@ -91,6 +130,8 @@ impl GameState {
         self.paddle_left_display_y = 12.0;
         self.paddle_right_display_y = 12.0;
         self.match_event = MatchEvent::None;
+        self.game_events.clear();
+        self.game_start_server_ms = None;
     }
 
     /// Update interpolation based on elapsed time
@@ -153,6 +194,12 @@ impl GameState {
         self.interpolation_alpha = 0.0;
     }
 
+    /// Account for how long the current snapshot spent in flight (seconds).
+    /// Clamped to the extrapolation window so a bad clock estimate cannot cause jumps.
+    pub fn set_snapshot_age(&mut self, age: f32) {
+        self.time_since_update = age.clamp(0.0, 0.100);
+    }
+
     pub fn set_scores(&mut self, left: u8, right: u8) {
         self.score_left = left;
         self.score_right = right;
@@ -181,6 +228,11 @@ impl GameState {
     pub fn get_current_snapshot(&self) -> Option<GameStateSnapshot> {
         Some(self.current.clone())
     }
+
+    /// Tick of the newest snapshot received
+    pub fn latest_tick(&self) -> u32 {
+        self.current.tick
+    }
 }
 
 #[cfg(test)]
@@ -214,6 +266,7 @@ mod tests {
             ball_vx: 10.0,
             ball_vy: -5.0,
             tick: 100,
+            server_time_ms: 0,
             score_left: 3,
             score_right: 5,
         });
@@ -243,6 +296,7 @@ mod tests {
             ball_vx: 0.0,
             ball_vy: 0.0,
             tick: 1,
+            server_time_ms: 0,
             score_left: 0,
             score_right: 0,
         });
@@ -274,6 +328,7 @@ mod tests {
             ball_vx: 10.0,
             ball_vy: 5.0,
             tick: 1,
+            server_time_ms: 0,
             score_left: 0,
             score_right: 0,
         });
@@ -292,4 +347,18 @@ mod tests {
         assert!(after_x > initial_x, "Ball X should increase toward target");
         assert!(after_y > initial_y, "Ball Y should increase toward target");
     }
+
+    #[test]
+    fn test_snapshot_age_is_clamped() {
+        let mut state = GameState::new();
+
+        state.set_snapshot_age(0.040);
+        assert!((state.time_since_update() - 0.040).abs() < f32::EPSILON);
+
+        // Bad clock estimates must not push extrapolation past its window
+        state.set_snapshot_age(5.0);
+        assert_eq!(state.time_since_update(), 0.100);
+        state.set_snapshot_age(-1.0);
+        assert_eq!(state.time_since_update(), 0.0);
+    }
 }
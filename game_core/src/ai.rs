@@ -0,0 +1,114 @@
+use crate::{Ball, Config, Paddle};
+use hecs::World;
+
+/// Direction (-1, 0, 1) an AI-controlled paddle should move this tick
+///
+/// Strategy:
+/// 1. Simple heuristic: if ball is moving towards us, predict intersection y.
+/// 2. If intersection is significantly different from current y, move there.
+/// 3. If ball moving away, return to center to cover maximum area.
+pub fn ai_input(world: &World, config: &Config, player_id: u8) -> i8 {
+    let ball_data = world
+        .query::<&Ball>()
+        .iter()
+        .next()
+        .map(|(_e, ball)| (ball.pos, ball.vel));
+    let paddle_data = world
+        .query::<&Paddle>()
+        .iter()
+        .find(|(_e, p)| p.player_id == player_id)
+        .map(|(_e, p)| p.y);
+
+    let (Some((ball_pos, ball_vel)), Some(paddle_y)) = (ball_data, paddle_data) else {
+        return 0;
+    };
+
+    // Velocity towards this paddle's side of the arena
+    let approach_speed = if player_id == 0 {
+        -ball_vel.x
+    } else {
+        ball_vel.x
+    };
+
+    let (target_y, deadzone) = if approach_speed > 0.0 {
+        let distance = (config.paddle_x(player_id) - ball_pos.x).abs();
+        let time_to_reach = distance / approach_speed.max(0.1);
+        let predicted_y = ball_pos.y + ball_vel.y * time_to_reach;
+        (predicted_y + (ball_vel.y * 0.3), 0.3)
+    } else {
+        (config.arena_height / 2.0, 0.5)
+    };
+
+    let diff = target_y - paddle_y;
+    if diff > deadzone {
+        1
+    } else if diff < -deadzone {
+        -1
+    } else {
+        0
+    }
+}
+
+/// Paddle position an AI-controlled paddle moves to over one step of `dt`
+/// seconds, or None if it has no paddle
+pub fn ai_paddle_y(world: &World, config: &Config, player_id: u8, dt: f32) -> Option<f32> {
+    let paddle_y = world
+        .query::<&Paddle>()
+        .iter()
+        .find(|(_e, p)| p.player_id == player_id)
+        .map(|(_e, p)| p.y)?;
+    let dir = ai_input(world, config, player_id);
+    Some(config.clamp_paddle_y(paddle_y + f32::from(dir) * config.paddle_speed * dt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_ball, create_paddle};
+
+    fn world_with_ball(pos: glam::Vec2, vel: glam::Vec2, paddle_y: f32) -> World {
+        let mut world = World::new();
+        create_ball(&mut world, pos, vel);
+        create_paddle(&mut world, 0, paddle_y);
+        create_paddle(&mut world, 1, paddle_y);
+        world
+    }
+
+    #[test]
+    fn test_ai_tracks_approaching_ball_on_either_side() {
+        let config = Config::new();
+
+        // Ball heading right, low in the arena: right paddle moves down (+y)
+        let world = world_with_ball(glam::Vec2::new(16.0, 20.0), glam::Vec2::new(8.0, 0.0), 12.0);
+        assert_eq!(ai_input(&world, &config, 1), 1);
+
+        // Ball heading left, high in the arena: left paddle moves up (-y)
+        let world = world_with_ball(glam::Vec2::new(16.0, 4.0), glam::Vec2::new(-8.0, 0.0), 12.0);
+        assert_eq!(ai_input(&world, &config, 0), -1);
+    }
+
+    #[test]
+    fn test_ai_recenters_when_ball_moves_away() {
+        let config = Config::new();
+        let world = world_with_ball(glam::Vec2::new(16.0, 20.0), glam::Vec2::new(-8.0, 0.0), 4.0);
+        assert_eq!(ai_input(&world, &config, 1), 1);
+
+        let world = world_with_ball(
+            glam::Vec2::new(16.0, 20.0),
+            glam::Vec2::new(-8.0, 0.0),
+            12.0,
+        );
+        assert_eq!(ai_input(&world, &config, 1), 0);
+    }
+
+    #[test]
+    fn test_ai_paddle_y_stays_in_arena() {
+        let config = Config::new();
+        let top = config.paddle_height / 2.0;
+        let world = world_with_ball(glam::Vec2::new(16.0, 0.5), glam::Vec2::new(8.0, 0.0), top);
+        let y = ai_paddle_y(&world, &config, 1, 1.0 / 60.0).unwrap();
+        assert_eq!(y, top);
+
+        assert_eq!(ai_paddle_y(&World::new(), &config, 1, 1.0 / 60.0), None);
+    }
+}
@@ -0,0 +1,105 @@
+/// Fixed-rate simulation clock shared by the server, client prediction and
+/// local play, so a tick number means the same simulated time everywhere.
+///
+/// Real time is credited with `advance`, which returns how many ticks are
+/// now due. How often the host calls it (a Durable Object alarm, an
+/// animation frame) does not change the simulation rate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TickClock {
+    accumulator: f32, // Seconds of real time not yet simulated
+}
+
+impl TickClock {
+    /// Simulation rate
+    pub const TICK_HZ: u32 = 60;
+
+    /// Simulated seconds per tick
+    pub const DT: f32 = 1.0 / Self::TICK_HZ as f32;
+
+    /// Ticks between snapshots when nothing better is known (20 Hz)
+    pub const SNAPSHOT_INTERVAL_TICKS: u32 = 3;
+
+    /// Most ticks run for one `advance`; the rest of the backlog waits
+    pub const MAX_CATCH_UP_TICKS: u32 = 10;
+
+    /// Most real time credited by one `advance`, so a stall (a hibernated
+    /// object, a background tab) is not replayed as a burst
+    pub const MAX_ELAPSED: f32 = 0.1;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Credit `elapsed` seconds of real time and return the number of ticks
+    /// to run now
+    pub fn advance(&mut self, elapsed: f32) -> u32 {
+        self.accumulator += elapsed.clamp(0.0, Self::MAX_ELAPSED);
+        let mut ticks = 0;
+        while self.accumulator >= Self::DT && ticks < Self::MAX_CATCH_UP_TICKS {
+            self.accumulator -= Self::DT;
+            ticks += 1;
+        }
+        ticks
+    }
+
+    /// Real time owed to the simulation but not yet stepped, in seconds
+    pub fn accumulator(&self) -> f32 {
+        self.accumulator
+    }
+
+    /// Drop any owed time, e.g. when play resumes from a frozen state
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+
+    /// Simulated seconds at the start of `tick`
+    pub fn seconds_at(tick: u32) -> f32 {
+        tick as f32 * Self::DT
+    }
+
+    /// Whole ticks covering `seconds` of simulated time
+    pub fn ticks_in(seconds: f32) -> u32 {
+        (seconds * Self::TICK_HZ as f32).round() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advance_runs_one_tick_per_dt() {
+        let mut clock = TickClock::new();
+        assert_eq!(clock.advance(TickClock::DT * 0.5), 0);
+        assert_eq!(clock.advance(TickClock::DT * 0.5), 1);
+
+        // Same 0.31s at different cadences runs the same 18 ticks
+        let mut coarse = TickClock::new();
+        let mut fine = TickClock::new();
+        let coarse_ticks: u32 = (0..4).map(|_| coarse.advance(0.0775)).sum();
+        let fine_ticks: u32 = (0..31).map(|_| fine.advance(0.01)).sum();
+        assert_eq!(coarse_ticks, 18);
+        assert_eq!(fine_ticks, 18);
+    }
+
+    #[test]
+    fn test_advance_caps_stalls_and_catch_up() {
+        // A long stall only credits MAX_ELAPSED
+        let mut clock = TickClock::new();
+        let mut capped = TickClock::new();
+        assert_eq!(clock.advance(5.0), capped.advance(TickClock::MAX_ELAPSED));
+        assert!(clock.accumulator() < TickClock::DT);
+
+        let ticks: u32 = (0..3).map(|_| clock.advance(TickClock::MAX_ELAPSED)).sum();
+        assert!(ticks <= 3 * TickClock::MAX_CATCH_UP_TICKS);
+
+        clock.reset();
+        assert_eq!(clock.accumulator(), 0.0);
+    }
+
+    #[test]
+    fn test_tick_time_round_trips() {
+        assert_eq!(TickClock::ticks_in(1.0), TickClock::TICK_HZ);
+        assert_eq!(TickClock::ticks_in(TickClock::seconds_at(900)), 900);
+    }
+}
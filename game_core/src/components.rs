@@ -71,6 +71,13 @@ impl PaddleIntent {
     }
 }
 
+/// Earlier paddle position that hits are judged against instead of the live one.
+/// Set by the server for lag compensation; never present on clients.
+#[derive(Debug, Clone, Copy)]
+pub struct RewoundPaddle {
+    pub y: f32,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -22,13 +22,14 @@ impl Params {
     // Score
     pub const WIN_SCORE: u8 = 5;
 
-    // Physics
-    pub const FIXED_DT: f32 = 0.0166;
+    // Physics (one step per simulation tick)
+    pub const FIXED_DT: f32 = crate::TickClock::DT;
     pub const MAX_DT: f32 = 0.1;
 }
 
 /// Game configuration
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Config {
     pub arena_width: f32,
     pub arena_height: f32,
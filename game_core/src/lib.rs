@@ -1,13 +1,19 @@
+pub mod ai;
+pub mod clock;
 pub mod components;
 pub mod config;
 pub mod map;
 pub mod resources;
+pub mod settings;
 pub mod systems;
 
+pub use ai::*;
+pub use clock::*;
 pub use components::*;
 pub use config::*;
 pub use map::*;
 pub use resources::*;
+pub use settings::*;
 pub use systems::*;
 
 use hecs::World;
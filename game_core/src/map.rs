@@ -16,6 +16,11 @@ impl GameMap {
         }
     }
 
+    /// Create an arena of a given size
+    pub fn with_size(width: f32, height: f32) -> Self {
+        Self { width, height }
+    }
+
     /// Get spawn position for paddle based on player ID
     pub fn paddle_spawn(&self, player_id: u8) -> Vec2 {
         let x = if player_id == 0 {
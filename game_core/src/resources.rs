@@ -14,7 +14,7 @@ impl Time {
 impl Default for Time {
     fn default() -> Self {
         Self {
-            dt: 0.016,
+            dt: crate::TickClock::DT,
             now: 0.0,
         }
     }
@@ -22,6 +22,7 @@ impl Default for Time {
 
 /// Game score tracking
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Score {
     pub left: u8,  // Left player score
     pub right: u8, // Right player score
@@ -74,6 +75,8 @@ pub struct Events {
     pub right_scored: bool,
     pub ball_hit_paddle: bool,
     pub ball_hit_wall: bool,
+    pub paddle_contact: Option<(u8, glam::Vec2)>, // (player_id, contact point) of the paddle hit
+    pub wall_contact: Option<glam::Vec2>,         // Contact point of the wall hit
 }
 
 /// Respawn state for managing ball respawn delays after scoring
@@ -112,6 +115,8 @@ impl Events {
         self.right_scored = false;
         self.ball_hit_paddle = false;
         self.ball_hit_wall = false;
+        self.paddle_contact = None;
+        self.wall_contact = None;
     }
 }
 
@@ -207,6 +212,8 @@ mod tests {
         events.right_scored = true;
         events.ball_hit_paddle = true;
         events.ball_hit_wall = true;
+        events.paddle_contact = Some((0, glam::Vec2::new(1.9, 12.0)));
+        events.wall_contact = Some(glam::Vec2::new(16.0, 0.5));
 
         events.clear();
 
@@ -214,6 +221,8 @@ mod tests {
         assert!(!events.right_scored);
         assert!(!events.ball_hit_paddle);
         assert!(!events.ball_hit_wall);
+        assert!(events.paddle_contact.is_none());
+        assert!(events.wall_contact.is_none());
     }
 
     #[test]
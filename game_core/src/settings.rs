@@ -0,0 +1,238 @@
+use crate::{Config, GameMap, Params};
+use std::fmt;
+
+/// Lowest and highest points-to-win a match can be created with
+pub const MIN_WIN_SCORE: u8 = 1;
+pub const MAX_WIN_SCORE: u8 = 21;
+
+/// Ball and paddle speed preset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpeedPreset {
+    Slow,
+    #[default]
+    Normal,
+    Fast,
+}
+
+impl SpeedPreset {
+    pub fn multiplier(self) -> f32 {
+        match self {
+            SpeedPreset::Slow => 0.75,
+            SpeedPreset::Normal => 1.0,
+            SpeedPreset::Fast => 1.3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PaddleSize {
+    Small,
+    #[default]
+    Normal,
+    Large,
+}
+
+impl PaddleSize {
+    pub fn height(self) -> f32 {
+        match self {
+            PaddleSize::Small => 3.0,
+            PaddleSize::Normal => Params::PADDLE_HEIGHT,
+            PaddleSize::Large => 5.5,
+        }
+    }
+}
+
+/// Arena shape
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MapLayout {
+    /// Standard 32 x 24 arena
+    #[default]
+    Classic,
+    /// Longer 40 x 24 arena, more time to react
+    Wide,
+}
+
+impl MapLayout {
+    /// Arena (width, height)
+    pub fn size(self) -> (f32, f32) {
+        match self {
+            MapLayout::Classic => (Params::ARENA_WIDTH, Params::ARENA_HEIGHT),
+            MapLayout::Wide => (40.0, Params::ARENA_HEIGHT),
+        }
+    }
+}
+
+/// Why match settings were rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SettingsError {
+    WinScoreOutOfRange(u8),
+    UnknownOption { name: String, value: String },
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::WinScoreOutOfRange(score) => write!(
+                f,
+                "win score {score} must be between {MIN_WIN_SCORE} and {MAX_WIN_SCORE}"
+            ),
+            SettingsError::UnknownOption { name, value } => {
+                write!(f, "unknown value '{value}' for {name}")
+            }
+        }
+    }
+}
+
+/// Settings chosen when a match is created. Both server and clients build
+/// their `Config` and `GameMap` from these so simulation and prediction agree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchSettings {
+    pub win_score: u8,
+    pub speed: SpeedPreset,
+    pub paddle_size: PaddleSize,
+    pub layout: MapLayout,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self {
+            win_score: Params::WIN_SCORE,
+            speed: SpeedPreset::default(),
+            paddle_size: PaddleSize::default(),
+            layout: MapLayout::default(),
+        }
+    }
+}
+
+impl MatchSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build settings from `name=value` options (as in a query string).
+    /// Missing options keep their defaults; unknown names are ignored.
+    pub fn from_options<'a>(
+        options: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, SettingsError> {
+        let mut settings = Self::default();
+        for (name, value) in options {
+            let unknown = || SettingsError::UnknownOption {
+                name: name.to_string(),
+                value: value.to_string(),
+            };
+            match name {
+                "win_score" => settings.win_score = value.parse().map_err(|_| unknown())?,
+                "speed" => {
+                    settings.speed = match value {
+                        "slow" => SpeedPreset::Slow,
+                        "normal" => SpeedPreset::Normal,
+                        "fast" => SpeedPreset::Fast,
+                        _ => return Err(unknown()),
+                    }
+                }
+                "paddle" => {
+                    settings.paddle_size = match value {
+                        "small" => PaddleSize::Small,
+                        "normal" => PaddleSize::Normal,
+                        "large" => PaddleSize::Large,
+                        _ => return Err(unknown()),
+                    }
+                }
+                "layout" => {
+                    settings.layout = match value {
+                        "classic" => MapLayout::Classic,
+                        "wide" => MapLayout::Wide,
+                        _ => return Err(unknown()),
+                    }
+                }
+                _ => {}
+            }
+        }
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if !(MIN_WIN_SCORE..=MAX_WIN_SCORE).contains(&self.win_score) {
+            return Err(SettingsError::WinScoreOutOfRange(self.win_score));
+        }
+        Ok(())
+    }
+
+    /// Simulation config for these settings
+    pub fn config(&self) -> Config {
+        let (arena_width, arena_height) = self.layout.size();
+        let speed = self.speed.multiplier();
+        Config {
+            arena_width,
+            arena_height,
+            paddle_height: self.paddle_size.height(),
+            paddle_speed: Params::PADDLE_SPEED * speed,
+            ball_speed_initial: Params::BALL_SPEED_INITIAL * speed,
+            ball_speed_max: Params::BALL_SPEED_MAX * speed,
+            win_score: self.win_score,
+            ..Config::default()
+        }
+    }
+
+    /// Arena for these settings
+    pub fn map(&self) -> GameMap {
+        let (width, height) = self.layout.size();
+        GameMap::with_size(width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_settings_match_default_config() {
+        let config = MatchSettings::new().config();
+        let default = Config::new();
+        assert_eq!(config.win_score, default.win_score);
+        assert_eq!(config.paddle_height, default.paddle_height);
+        assert_eq!(config.ball_speed_initial, default.ball_speed_initial);
+        assert_eq!(config.arena_width, default.arena_width);
+        assert_eq!(MatchSettings::new().map().width, GameMap::new().width);
+    }
+
+    #[test]
+    fn test_from_options() {
+        let settings = MatchSettings::from_options([
+            ("win_score", "11"),
+            ("speed", "fast"),
+            ("paddle", "small"),
+            ("layout", "wide"),
+            ("ignored", "x"),
+        ])
+        .unwrap();
+        assert_eq!(settings.win_score, 11);
+        assert_eq!(settings.speed, SpeedPreset::Fast);
+        assert_eq!(settings.paddle_size, PaddleSize::Small);
+
+        let config = settings.config();
+        assert_eq!(config.arena_width, 40.0);
+        assert_eq!(config.paddle_x(1), 38.5);
+        assert!(config.ball_speed_initial > Params::BALL_SPEED_INITIAL);
+        assert_eq!(settings.map().width, config.arena_width);
+    }
+
+    #[test]
+    fn test_rejects_invalid_options() {
+        assert_eq!(
+            MatchSettings::from_options([("win_score", "0")]),
+            Err(SettingsError::WinScoreOutOfRange(0))
+        );
+        assert_eq!(
+            MatchSettings::from_options([("win_score", "99")]),
+            Err(SettingsError::WinScoreOutOfRange(99))
+        );
+        assert!(MatchSettings::from_options([("win_score", "five")]).is_err());
+        assert!(MatchSettings::from_options([("speed", "ludicrous")]).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Ball, Config, Events, GameMap, Paddle, PaddleIntent};
+use crate::{Ball, Config, Events, GameMap, Paddle, PaddleIntent, RewoundPaddle};
 use hecs::World;
 
 /// Check ball collisions with walls and paddles
@@ -13,14 +13,16 @@ pub fn check_collisions(world: &mut World, map: &GameMap, config: &Config, event
 
         // Paddle collisions
         // Collect paddle info first to avoid borrow conflicts
-        let paddles: Vec<(u8, f32, i8)> = world
-            .query::<(&Paddle, &PaddleIntent)>()
+        let paddles: Vec<(u8, f32, i8, Option<f32>)> = world
+            .query::<(&Paddle, &PaddleIntent, Option<&RewoundPaddle>)>()
             .iter()
-            .map(|(_e, (p, intent))| (p.player_id, p.y, intent.dir))
+            .map(|(_e, (p, intent, rewound))| (p.player_id, p.y, intent.dir, rewound.map(|r| r.y)))
             .collect();
 
-        for (player_id, paddle_y, paddle_dir) in paddles {
-            handle_paddle_collision(ball, player_id, paddle_y, paddle_dir, config, events);
+        for (player_id, paddle_y, paddle_dir, rewound_y) in paddles {
+            // Lag compensation: judge the paddle where its player saw it instead
+            let judged_y = rewound_y.unwrap_or(paddle_y);
+            handle_paddle_collision(ball, player_id, judged_y, paddle_dir, config, events);
         }
     }
 }
@@ -42,6 +44,7 @@ fn handle_wall_collision(ball: &mut Ball, map: &GameMap, config: &Config, events
         ball.pos = pos;
         ball.vel = vel;
         events.ball_hit_wall = true;
+        events.wall_contact = Some(pos);
     }
 }
 
@@ -70,6 +73,7 @@ fn handle_paddle_collision(
         if should_bounce {
             resolve_paddle_collision(ball, player_id, paddle_y, paddle_dir, config);
             events.ball_hit_paddle = true;
+            events.paddle_contact = Some((player_id, ball.pos));
         }
     }
 }
@@ -158,6 +162,11 @@ mod tests {
             );
         }
         assert!(events.ball_hit_wall, "Should trigger ball_hit_wall event");
+        assert_eq!(
+            events.wall_contact,
+            Some(glam::Vec2::new(ball_pos.x, config.ball_radius)),
+            "Should record wall contact point"
+        );
     }
 
     #[test]
@@ -216,6 +225,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_ball_collides_with_rewound_paddle() {
+        let (mut world, config, map, mut events) = setup_world();
+        let paddle_x = config.paddle_x(0);
+        // Paddle has since moved away, but was at y=12 when its player saw the ball
+        let paddle = create_paddle(&mut world, 0, 20.0);
+        world.insert_one(paddle, RewoundPaddle { y: 12.0 }).unwrap();
+
+        let paddle_half_width = config.paddle_width / 2.0;
+        let ball_pos = glam::Vec2::new(
+            paddle_x + paddle_half_width - config.ball_radius * 0.5,
+            12.0,
+        );
+        create_ball(&mut world, ball_pos, glam::Vec2::new(-8.0, 0.0));
+
+        check_collisions(&mut world, &map, &config, &mut events);
+
+        for (_entity, ball) in world.query::<&Ball>().iter() {
+            assert!(ball.vel.x > 0.0, "Rewound paddle should return the ball");
+        }
+        assert!(events.ball_hit_paddle);
+    }
+
+    #[test]
+    fn test_rewound_paddle_replaces_live_one() {
+        let (mut world, config, map, mut events) = setup_world();
+        let paddle_x = config.paddle_x(0);
+        // Live paddle is at the ball, but its player saw it elsewhere
+        let paddle = create_paddle(&mut world, 0, 12.0);
+        world.insert_one(paddle, RewoundPaddle { y: 20.0 }).unwrap();
+
+        let paddle_half_width = config.paddle_width / 2.0;
+        let ball_pos = glam::Vec2::new(
+            paddle_x + paddle_half_width - config.ball_radius * 0.5,
+            12.0,
+        );
+        create_ball(&mut world, ball_pos, glam::Vec2::new(-8.0, 0.0));
+
+        check_collisions(&mut world, &map, &config, &mut events);
+
+        assert!(!events.ball_hit_paddle);
+    }
+
     #[test]
     fn test_ball_collides_with_right_paddle() {
         let (mut world, config, map, mut events) = setup_world();
@@ -246,6 +298,10 @@ mod tests {
             events.ball_hit_paddle,
             "Should trigger ball_hit_paddle event"
         );
+        let (hit_player, contact) = events.paddle_contact.expect("Should record contact point");
+        assert_eq!(hit_player, 1, "Contact should belong to right paddle");
+        assert!(contact.x < paddle_x, "Contact should be on paddle face");
+        assert_eq!(contact.y, paddle_y, "Contact should be at ball height");
     }
 
     #[test]
@@ -97,6 +97,35 @@ let renderLoopId = null;
 let pingIntervalId = null;
 let inputIntervalId = null;
 let currentMatchCode = null;
+let rejoinAttempts = 0;
+
+// Persistent id our rating is kept under (random, stored in this browser)
+const PROFILE_ID_KEY = "pongo.profileId";
+function getProfileId() {
+  try {
+    let id = localStorage.getItem(PROFILE_ID_KEY);
+    if (!id) {
+      id = crypto.randomUUID().replace(/-/g, "");
+      localStorage.setItem(PROFILE_ID_KEY, id);
+    }
+    return id;
+  } catch (e) {
+    return ""; // Storage unavailable: play unrated
+  }
+}
+
+async function fetchOwnRating() {
+  const id = getProfileId();
+  if (!id) return null;
+  try {
+    const response = await fetch(`/ratings/${id}`);
+    if (!response.ok) return null;
+    return (await response.json()).rating.rating;
+  } catch (e) {
+    return null;
+  }
+}
+const MAX_REJOIN_ATTEMPTS = 5;
 
 // ========================================
 // Finite State Machine (Rust-backed)
@@ -110,6 +139,7 @@ const GameState = {
   WAITING: FsmState.Waiting,
   COUNTDOWN_MULTI: FsmState.CountdownMulti,
   PLAYING_MULTI: FsmState.PlayingMulti,
+  PAUSED_MULTI: FsmState.PausedMulti,
   GAME_OVER_LOCAL: FsmState.GameOverLocal,
   GAME_OVER_MULTI: FsmState.GameOverMulti,
   DISCONNECTED: FsmState.Disconnected,
@@ -148,7 +178,7 @@ const FSM = {
 
       console.log(`FSM: ${result.from_state} --[${action}]--> ${result.to_state}`);
       await this.exitState(prevState);
-      await this.enterState(rustFsm.state);
+      await this.enterState(rustFsm.state, prevState);
       return true;
     } finally {
       this.isTransitioning = false;
@@ -172,12 +202,16 @@ const FSM = {
       case FsmState.GameOverLocal:
         hideVictoryOverlay();
         break;
+      case FsmState.PausedMulti:
+        stopEventPolling();
+        hidePauseOverlay();
+        break;
       case FsmState.Connecting:
         break;
     }
   },
 
-  async enterState(state) {
+  async enterState(state, prevState) {
     updateUIForState(state);
     switch (state) {
       case FsmState.Idle:
@@ -199,7 +233,10 @@ const FSM = {
         await enterCountdownMulti();
         break;
       case FsmState.PlayingMulti:
-        enterPlayingMulti();
+        enterPlayingMulti(prevState === FsmState.PausedMulti);
+        break;
+      case FsmState.PausedMulti:
+        startEventPolling();
         break;
       case FsmState.GameOverLocal:
       case FsmState.GameOverMulti:
@@ -274,17 +311,26 @@ async function enterConnecting() {
     window.history.pushState({}, "", url);
   }
 
+  rejoinAttempts = 0;
+  openMatchSocket(code, () => client.get_join_bytes(code, getProfileId()));
+}
+
+// Open the match socket, sending the given join (or rejoin) message once connected
+function openMatchSocket(code, joinBytes) {
   const protocol = window.location.protocol === "https:" ? "wss:" : "ws:";
   const wsUrl = `${protocol}//${window.location.host}/ws/${code}`;
 
   try {
-    ws = new WebSocket(wsUrl);
+    const socket = new WebSocket(wsUrl);
+    ws = socket;
     ws.binaryType = "arraybuffer";
 
     ws.onopen = () => {
       try {
-        ws.send(client.get_join_bytes(code));
-        FSM.transition("CONNECTED");
+        socket.send(joinBytes());
+        if (FSM.state === GameState.CONNECTING) {
+          FSM.transition("CONNECTED");
+        }
       } catch (e) {
         console.error("Join error:", e);
         FSM.transition("CONNECTION_FAILED");
@@ -296,12 +342,23 @@ async function enterConnecting() {
         try {
           client.on_message(new Uint8Array(event.data));
 
+          // Answer a pong at once so the server can time our round trip
+          const pongEcho = client.take_pong_echo();
+          if (pongEcho.length > 0) {
+            ws.send(pongEcho);
+          }
+
           // Poll for match events from server
           const matchEvent = client.get_match_event();
           if (matchEvent) {
             handleMatchEvent(matchEvent);
           }
 
+          // Play effects for authoritative gameplay events
+          for (const gameEvent of client.take_game_events()) {
+            handleGameEvent(gameEvent);
+          }
+
           // Update score if playing
           if (FSM.state === GameState.PLAYING_MULTI) {
             const score = client.get_score();
@@ -322,9 +379,23 @@ async function enterConnecting() {
     };
 
     ws.onclose = () => {
+      // Ignore sockets we have already replaced or closed ourselves
+      if (socket !== ws) {
+        return;
+      }
+      if (
+        (FSM.state === GameState.PLAYING_MULTI || FSM.state === GameState.PAUSED_MULTI) &&
+        tryRejoin(code)
+      ) {
+        return;
+      }
       if (FSM.state === GameState.WAITING || FSM.state === GameState.COUNTDOWN_MULTI) {
         FSM.transition("DISCONNECTED");
-      } else if (FSM.state === GameState.PLAYING_MULTI || FSM.state === GameState.GAME_OVER_MULTI) {
+      } else if (
+        FSM.state === GameState.PLAYING_MULTI ||
+        FSM.state === GameState.PAUSED_MULTI ||
+        FSM.state === GameState.GAME_OVER_MULTI
+      ) {
         FSM.transition("DISCONNECTED");
       } else if (FSM.state === GameState.CONNECTING) {
         FSM.transition("CONNECTION_FAILED");
@@ -335,6 +406,22 @@ async function enterConnecting() {
   }
 }
 
+// Reclaim our slot after a dropped connection while the server holds it open
+function tryRejoin(code) {
+  const rejoinBytes = client ? client.get_rejoin_bytes(code) : null;
+  if (!rejoinBytes || rejoinBytes.length === 0 || rejoinAttempts >= MAX_REJOIN_ATTEMPTS) {
+    return false;
+  }
+  rejoinAttempts++;
+  console.log(`Connection lost, rejoining (attempt ${rejoinAttempts})`);
+  setTimeout(() => {
+    if (FSM.state === GameState.PLAYING_MULTI || FSM.state === GameState.PAUSED_MULTI) {
+      openMatchSocket(code, () => rejoinBytes);
+    }
+  }, 1000);
+  return true;
+}
+
 function enterWaiting() {
   // Start polling for events while waiting
   startEventPolling();
@@ -368,16 +455,65 @@ function handleMatchEvent(event) {
       FSM.transition("REMATCH_STARTED");
     }
   } else if (event === "game_start") {
-    hideCountdownNumber();
+    showGo();
     if (FSM.state === GameState.COUNTDOWN_MULTI) {
       FSM.transition("COUNTDOWN_DONE");
     }
+  } else if (event.startsWith("opponent_reconnecting:")) {
+    // Play is frozen server-side; show how long they have left
+    const seconds = parseInt(event.split(":")[1]);
+    showCountdownNumber(seconds);
+  } else if (event === "opponent_reconnected") {
+    hideCountdownNumber();
+  } else if (event.startsWith("paused:")) {
+    // paused:<who>:<pauses left>:<seconds>
+    const [, who, pausesLeft, seconds] = event.split(":");
+    showPauseOverlay(
+      who === "you" ? "You paused" : "Opponent paused",
+      `Resumes automatically in ${seconds}s - press P when ready (${pausesLeft} pauses left)`
+    );
+    if (FSM.state === GameState.PLAYING_MULTI) {
+      FSM.transition("PAUSED");
+    }
+  } else if (event.startsWith("pause_denied:")) {
+    const pausesLeft = parseInt(event.split(":")[1]);
+    console.log(`Pause refused (${pausesLeft} pauses left)`);
+  } else if (event.startsWith("resume_requested:")) {
+    const who = event.split(":")[1];
+    setPauseDetail(who === "you" ? "Waiting for opponent..." : "Opponent is ready - press P");
+  } else if (event.startsWith("resume_countdown:")) {
+    hidePauseOverlay();
+    showCountdownNumber(parseInt(event.split(":")[1]));
+  } else if (event === "resumed") {
+    hideCountdownNumber();
+    if (FSM.state === GameState.PAUSED_MULTI) {
+      FSM.transition("RESUMED");
+    }
+  } else if (event.startsWith("rematch_requested:")) {
+    // rematch_requested:<who>:<swap|same>:<seconds>
+    const [, who, sides, seconds] = event.split(":");
+    const terms = sides === "swap" ? " (swap sides)" : "";
+    document.getElementById("swapSidesToggle").checked = sides === "swap";
+    if (who === "you") {
+      setRematchStatus(`Rematch offered${terms} - waiting for opponent`);
+    } else {
+      setRematchStatus(`Opponent wants a rematch${terms} - ${seconds}s to accept`);
+      const btn = document.getElementById("playAgainBtn");
+      btn.textContent = "Accept Rematch";
+      btn.disabled = false;
+    }
+  } else if (event === "rematch_expired") {
+    setRematchStatus("Rematch offer expired");
+    const btn = document.getElementById("playAgainBtn");
+    btn.textContent = "Play Again";
+    btn.disabled = false;
   } else if (event === "opponent_disconnected") {
     // Handle opponent leaving at any multiplayer stage
     if (
       FSM.state === GameState.WAITING ||
       FSM.state === GameState.COUNTDOWN_MULTI ||
       FSM.state === GameState.PLAYING_MULTI ||
+      FSM.state === GameState.PAUSED_MULTI ||
       FSM.state === GameState.GAME_OVER_MULTI
     ) {
       FSM.transition("DISCONNECTED");
@@ -385,8 +521,48 @@ function handleMatchEvent(event) {
   }
 }
 
+// Pause overlay shown while an online match is paused
+function showPauseOverlay(title, detail) {
+  document.getElementById("pauseText").textContent = title;
+  setPauseDetail(detail);
+  document.getElementById("pauseOverlay").classList.add("show");
+}
+
+function setPauseDetail(detail) {
+  document.getElementById("pauseDetail").textContent = detail;
+}
+
+function hidePauseOverlay() {
+  document.getElementById("pauseOverlay").classList.remove("show");
+}
+
+// Ask the server to pause, or say we are ready to resume
+function sendPauseToggle() {
+  if (!ws || ws.readyState !== WebSocket.OPEN || !client) return;
+  let bytes = null;
+  if (FSM.state === GameState.PLAYING_MULTI) {
+    bytes = client.get_pause_bytes();
+  } else if (FSM.state === GameState.PAUSED_MULTI) {
+    bytes = client.get_resume_bytes();
+  }
+  if (bytes && bytes.length > 0) {
+    ws.send(bytes);
+  }
+}
+
+// Handle gameplay events from server (hits are in sync with server physics)
+function handleGameEvent(event) {
+  if (event.startsWith("paddle_hit:")) {
+    playSound("paddle");
+  } else if (event.startsWith("wall_hit:")) {
+    playSound("wall");
+  }
+  // point_scored is covered by the score flash in updateScore
+}
+
 // Show a single countdown number
 function showCountdownNumber(n) {
+  goShownAt = null;
   const el = document.getElementById("countdown");
   el.textContent = n > 0 ? n.toString() : "GO!";
   el.classList.add("show");
@@ -395,9 +571,36 @@ function showCountdownNumber(n) {
 }
 
 function hideCountdownNumber() {
+  goShownAt = null;
   document.getElementById("countdown").classList.remove("show");
 }
 
+// "GO!" stays up until this long after the server started play, timed on the
+// synced clock so both players see it clear together
+const GO_DISPLAY_MS = 800;
+let goShownAt = null; // performance.now() when "GO!" went up
+
+function showGo() {
+  const el = document.getElementById("countdown");
+  el.textContent = "GO!";
+  el.classList.add("show");
+  playSound("paddle");
+  goShownAt = performance.now();
+}
+
+// Called every frame: clear "GO!" once play has run long enough
+function updateGo() {
+  if (goShownAt === null || !client) return;
+  let sinceStart = client.ms_since_game_start();
+  if (sinceStart < 0) {
+    // Clock not synced yet: count from when it went up
+    sinceStart = performance.now() - goShownAt;
+  }
+  if (sinceStart >= GO_DISPLAY_MS) {
+    hideCountdownNumber();
+  }
+}
+
 let eventPollingId = null;
 function startEventPolling() {
   stopEventPolling();
@@ -407,6 +610,15 @@ function startEventPolling() {
       if (matchEvent) {
         handleMatchEvent(matchEvent);
       }
+      // The match can end while paused (forfeit after a dropped connection)
+      if (FSM.state === GameState.PAUSED_MULTI) {
+        const winner = client.get_winner();
+        if (winner) {
+          hidePauseOverlay();
+          showVictory(winner);
+          FSM.transition("GAME_OVER");
+        }
+      }
     }
   }, 100);
 }
@@ -418,8 +630,11 @@ function stopEventPolling() {
   }
 }
 
-function enterPlayingMulti() {
-  lastScore = [0, 0];
+function enterPlayingMulti(resuming) {
+  // Keep the score when resuming so it does not flash as a new point
+  if (!resuming) {
+    lastScore = [0, 0];
+  }
   document.body.classList.add("game-active");
   setupInputIfNeeded();
   startRenderLoop();
@@ -492,6 +707,11 @@ function updateUIForState(state) {
       activeMatchControls.style.display = "none";
       break;
 
+    case GameState.PAUSED_MULTI:
+      playBtn.textContent = "Paused";
+      playBtn.classList.remove("playing");
+      break;
+
     case GameState.GAME_OVER_LOCAL:
     case GameState.GAME_OVER_MULTI:
       playBtn.textContent = "Game Over";
@@ -542,6 +762,7 @@ function startRenderLoop() {
       try {
         client.render();
         updateMetrics();
+        updateGo();
         const score = client.get_score();
         if (score.length >= 2) {
           updateScore(score[0], score[1]);
@@ -605,8 +826,9 @@ function startPingInterval() {
 // ========================================
 function closeWebSocket() {
   if (ws) {
-    ws.close();
+    const socket = ws;
     ws = null;
+    socket.close();
   }
 }
 
@@ -656,6 +878,10 @@ function setupInputIfNeeded() {
   window.addEventListener(
     "keydown",
     (e) => {
+      if ((e.key === "p" || e.key === "P") && !e.repeat) {
+        sendPauseToggle();
+        return;
+      }
       const gameKeys = ["ArrowUp", "ArrowDown", "w", "W", "s", "S"];
       if (gameKeys.includes(e.key)) {
         e.preventDefault();
@@ -854,6 +1080,12 @@ function showVictory(winner) {
   btn.textContent = "Play Again";
   btn.disabled = false;
 
+  // Rematch terms only apply online
+  const online = FSM.state !== GameState.PLAYING_LOCAL;
+  document.getElementById("swapSidesOption").style.display = online ? "" : "none";
+  document.getElementById("swapSidesToggle").checked = false;
+  setRematchStatus("");
+
   overlay.classList.add("show");
   document.body.classList.remove("game-active");
 }
@@ -862,6 +1094,10 @@ function hideVictoryOverlay() {
   document.getElementById("victoryOverlay").classList.remove("show");
 }
 
+function setRematchStatus(text) {
+  document.getElementById("rematchStatus").textContent = text;
+}
+
 // ========================================
 // Public API / Event Handlers
 // ========================================
@@ -875,8 +1111,18 @@ const startLocalGame = async function () {
 
 const createMatch = async function () {
   if (FSM.state !== GameState.IDLE) return;
+  await cancelQuickPlay();
   try {
-    const response = await fetch("/create");
+    const params = new URLSearchParams({
+      win_score: document.getElementById("settingWinScore").value,
+      speed: document.getElementById("settingSpeed").value,
+      paddle: document.getElementById("settingPaddle").value,
+      layout: document.getElementById("settingLayout").value,
+    });
+    const bot = document.getElementById("settingBot").value;
+    if (bot) params.set("bot", bot);
+    const response = await fetch(`/create?${params}`);
+    if (!response.ok) throw new Error(await response.text());
     const data = await response.json();
     document.getElementById("matchCode").value = data.code;
     currentMatchCode = data.code;
@@ -991,6 +1237,96 @@ document.getElementById("joinCodeInput").addEventListener("keydown", (e) => {
   }
 });
 
+// Quick play: queue for an opponent, then join the match we are paired into
+const QUICK_PLAY_POLL_MS = 1000;
+let quickPlayTicket = null;
+let quickPlayTimer = null;
+
+function setQuickPlayStatus(text, searching) {
+  document.getElementById("quickPlayStatus").textContent = text;
+  document.getElementById("quickPlayBtn").textContent = searching ? "Cancel Search" : "Quick Play";
+}
+
+function stopQuickPlay() {
+  if (quickPlayTimer) clearTimeout(quickPlayTimer);
+  quickPlayTimer = null;
+  quickPlayTicket = null;
+}
+
+async function cancelQuickPlay() {
+  const ticket = quickPlayTicket;
+  stopQuickPlay();
+  setQuickPlayStatus("", false);
+  if (ticket) {
+    try {
+      await fetch(`/quickplay/${ticket}`, { method: "DELETE" });
+    } catch (e) {
+      console.warn("Could not leave queue:", e);
+    }
+  }
+}
+
+async function handleQuickPlayResult(result) {
+  switch (result.status) {
+    case "waiting":
+      setQuickPlayStatus(`Searching for an opponent... ${Math.floor(result.waited_ms / 1000)}s`, true);
+      quickPlayTimer = setTimeout(pollQuickPlay, QUICK_PLAY_POLL_MS);
+      break;
+    case "matched":
+      stopQuickPlay();
+      setQuickPlayStatus("", false);
+      document.getElementById("matchCode").value = result.code;
+      await joinMatch();
+      break;
+    case "timed_out":
+      stopQuickPlay();
+      setQuickPlayStatus("No opponent found. Try again?", false);
+      break;
+    default:
+      stopQuickPlay();
+      setQuickPlayStatus("Search expired. Try again?", false);
+  }
+}
+
+async function pollQuickPlay() {
+  const ticket = quickPlayTicket;
+  if (!ticket) return;
+  try {
+    const response = await fetch(`/quickplay/${ticket}`);
+    if (ticket !== quickPlayTicket) return; // Cancelled while in flight
+    await handleQuickPlayResult(await response.json());
+  } catch (e) {
+    console.warn("Quick play poll failed, retrying:", e);
+    if (ticket === quickPlayTicket) {
+      quickPlayTimer = setTimeout(pollQuickPlay, QUICK_PLAY_POLL_MS);
+    }
+  }
+}
+
+const quickPlay = async function () {
+  if (quickPlayTicket) {
+    await cancelQuickPlay();
+    return;
+  }
+  if (FSM.state !== GameState.IDLE) return;
+  try {
+    setQuickPlayStatus("Searching for an opponent...", true);
+    const rating = await fetchOwnRating();
+    const response = await fetch("/quickplay", {
+      method: "POST",
+      body: JSON.stringify(rating === null ? {} : { rating }),
+    });
+    if (!response.ok) throw new Error(await response.text());
+    const data = await response.json();
+    quickPlayTicket = data.ticket;
+    await handleQuickPlayResult(data.result);
+  } catch (e) {
+    console.error("Quick play error:", e);
+    stopQuickPlay();
+    setQuickPlayStatus("Quick play is unavailable right now", false);
+  }
+};
+
 const joinMatch = async function () {
   if (FSM.state !== GameState.IDLE) return;
   const code = document.getElementById("matchCode").value.trim().toUpperCase();
@@ -1012,14 +1348,14 @@ document.getElementById("playAgainBtn").addEventListener("click", () => {
   if (FSM.state === GameState.GAME_OVER_LOCAL) {
     FSM.transition("PLAY_AGAIN");
   } else if (FSM.state === GameState.GAME_OVER_MULTI) {
-    // Send restart request
+    // Offer (or accept) a rematch; play restarts once both players agree
     if (client && ws && ws.readyState === WebSocket.OPEN) {
       try {
-        const bytes = client.get_restart_bytes();
-        ws.send(bytes);
-        // Immediate reset for better UX
-        client.reset_local_state();
-        hideVictoryOverlay();
+        const swapSides = document.getElementById("swapSidesToggle").checked;
+        ws.send(client.get_rematch_bytes(swapSides));
+        const btn = document.getElementById("playAgainBtn");
+        btn.textContent = "Waiting...";
+        btn.disabled = true;
       } catch (e) {
         console.error(e);
       }
@@ -1037,6 +1373,7 @@ async function main() {
     document.getElementById("playBtn").disabled = false;
     document.getElementById("playBtn").addEventListener("click", startLocalGame);
     document.getElementById("createBtn").addEventListener("click", createMatch);
+    document.getElementById("quickPlayBtn").addEventListener("click", quickPlay);
     document.getElementById("quitBtn").addEventListener("click", () => {
       // Stop the local game and return to idle
       stopGameLoop();
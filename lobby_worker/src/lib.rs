@@ -1,7 +1,9 @@
+use server_do::bot::BotFill;
+use server_do::params::{self, constant_time_eq};
 use worker::*;
 
-// Export the Durable Object from server_do
-pub use server_do::MatchDO;
+// Export the Durable Objects from server_do
+pub use server_do::{HistoryDO, MatchDO, MatchmakerDO, TournamentDO};
 
 #[event(fetch)]
 pub async fn main(req: Request, env: Env, _ctx: worker::Context) -> Result<Response> {
@@ -12,6 +14,18 @@ pub async fn main(req: Request, env: Env, _ctx: worker::Context) -> Result<Respo
         .get_async("/create", handle_create)
         .get_async("/join/:code", handle_join)
         .get_async("/ws/:code", handle_websocket)
+        .get_async("/match/:code/status", handle_match_status)
+        .post_async("/match/:code/admin/:action", handle_match_admin)
+        .get_async("/matches", handle_recent_matches)
+        .get_async("/matches/:id", handle_match_result)
+        .get_async("/ratings", handle_leaderboard)
+        .get_async("/ratings/:profile", handle_player_rating)
+        .post_async("/tournaments", handle_tournament_create)
+        .get_async("/tournaments/:id", handle_tournament_state)
+        .post_async("/tournaments/:id/report", handle_tournament_report)
+        .post_async("/quickplay", handle_quickplay_join)
+        .get_async("/quickplay/:ticket", handle_quickplay_poll)
+        .delete_async("/quickplay/:ticket", handle_quickplay_leave)
         .run(req, env)
         .await
 }
@@ -21,19 +35,36 @@ async fn handle_index(_req: Request, _ctx: RouteContext<()>) -> Result<Response>
     Response::from_html(html)
 }
 
-async fn handle_create(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
+/// Create a match. Optional settings as query parameters:
+/// win_score=1..21, speed=slow|normal|fast, paddle=small|normal|large, layout=classic|wide,
+/// bot=now|wait (fill the empty slot with an AI at once, or if nobody joins in time)
+async fn handle_create(req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let (settings, bot_fill) = match params::create_options(&query_pairs(&req)?) {
+        Ok(created) => created,
+        Err(e) => return Response::error(e, 400),
+    };
+
     // Generate a random 5-character match code
-    let code = generate_match_code();
+    let code = server_do::generate_match_code();
 
     // Get the MATCH Durable Object namespace
     let match_do = ctx.env.durable_object("MATCH")?;
 
-    // Get DO stub by name (creates if doesn't exist)
-    let _stub = match_do.get_by_name(&code)?;
+    // Store the settings in the match DO (created on first use) before anyone can join
+    if !server_do::apply_match_settings(&match_do, &code, &settings).await? {
+        return Response::error("Match code already in use, please try again", 409);
+    }
+    if bot_fill != BotFill::Never
+        && !server_do::set_match_bot_fill(&match_do, &code, bot_fill).await?
+    {
+        return Response::error("Match code already in use, please try again", 409);
+    }
 
-    // Return JSON response with match code
+    // Return JSON response with match code and the settings it was created with
     Response::from_json(&serde_json::json!({
-        "code": code
+        "code": code,
+        "settings": settings,
+        "bot": bot_fill,
     }))
 }
 
@@ -139,15 +170,203 @@ async fn handle_websocket(req: Request, ctx: RouteContext<()>) -> Result<Respons
     }
 }
 
-/// Generate a random 5-character match code (A-Z, 0-9)
-fn generate_match_code() -> String {
-    use rand::Rng;
-    let mut rng = rand::thread_rng();
-    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
-    (0..5)
-        .map(|_| {
-            let idx = rng.gen_range(0..CHARS.len());
-            CHARS[idx] as char
-        })
-        .collect()
+/// Live state of a match as JSON: players, score, tick, timing and config
+async fn handle_match_status(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let code = ctx.param("code").map_or("", |v| v);
+
+    if code.len() != 5 {
+        return Response::error("Invalid match code", 400);
+    }
+
+    let stub = ctx.env.durable_object("MATCH")?.get_by_name(code)?;
+    stub.fetch_with_str("https://match/status").await
+}
+
+/// Admin action on a match: end, reset or kick?player=N.
+/// Requires `Authorization: Bearer <ADMIN_TOKEN>`; disabled when the secret is unset.
+async fn handle_match_admin(req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    if let Some(rejection) = reject_non_admin(&req, &ctx)? {
+        return Ok(rejection);
+    }
+
+    let code = ctx.param("code").map_or("", |v| v);
+    let action = ctx.param("action").map_or("", |v| v);
+    if code.len() != 5 {
+        return Response::error("Invalid match code", 400);
+    }
+
+    let query = req
+        .url()?
+        .query()
+        .map(|q| format!("?{q}"))
+        .unwrap_or_default();
+    let stub = ctx.env.durable_object("MATCH")?.get_by_name(code)?;
+    let mut init = RequestInit::new();
+    init.with_method(Method::Post);
+    let req = Request::new_with_init(&format!("https://match/admin/{action}{query}"), &init)?;
+    stub.fetch_with_request(req).await
+}
+
+/// Query parameters as owned name/value pairs
+fn query_pairs(req: &Request) -> Result<Vec<(String, String)>> {
+    Ok(req.url()?.query_pairs().into_owned().collect())
+}
+
+/// List recently finished matches, newest first (optional ?limit=N)
+async fn handle_recent_matches(req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let limit = params::limit(&query_pairs(&req)?);
+
+    fetch_history(&ctx, &format!("/recent?limit={limit}")).await
+}
+
+/// Fetch one finished match by id
+async fn handle_match_result(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let id = ctx.param("id").map_or("", |v| v);
+
+    if !params::is_valid_result_id(id) {
+        return Response::error("Invalid match id", 400);
+    }
+
+    fetch_history(&ctx, &format!("/result/{id}")).await
+}
+
+/// Highest rated players (optional ?limit=N)
+async fn handle_leaderboard(req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let limit = params::limit(&query_pairs(&req)?);
+
+    fetch_history(&ctx, &format!("/ratings?limit={limit}")).await
+}
+
+/// Rating for one persistent profile id (new profiles get the starting rating)
+async fn handle_player_rating(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let profile = ctx.param("profile").map_or("", |v| v);
+
+    if !server_do::rating::is_valid_profile_id(profile) {
+        return Response::error("Invalid profile id", 400);
+    }
+
+    fetch_history(&ctx, &format!("/ratings/{profile}")).await
+}
+
+/// Forward a read to the match history Durable Object
+async fn fetch_history(ctx: &RouteContext<()>, path: &str) -> Result<Response> {
+    let history = ctx.env.durable_object("HISTORY")?;
+    let stub = history.get_by_name(server_do::match_history::HISTORY_OBJECT_NAME)?;
+    stub.fetch_with_str(&format!("https://history{path}")).await
+}
+
+/// Create a tournament. JSON body:
+/// {"name", "format": "single_elimination"|"double_elimination",
+///  "entrants": [{"name", "profile_id"?}], "settings"?: MatchSettings}
+async fn handle_tournament_create(mut req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let body = req.text().await?;
+    let id = server_do::tournament::new_tournament_id();
+    fetch_tournament(
+        &ctx,
+        &id,
+        Method::Post,
+        &format!("/create?id={id}"),
+        Some(body),
+    )
+    .await
+}
+
+/// Bracket state as JSON
+async fn handle_tournament_state(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    match valid_tournament_id(&ctx) {
+        Some(id) => fetch_tournament(&ctx, &id, Method::Get, "/state", None).await,
+        None => Response::error("Invalid tournament id", 400),
+    }
+}
+
+/// Record a result by hand. JSON body: {"match_id", "winner_slot": 0|1}
+/// Requires `Authorization: Bearer <ADMIN_TOKEN>`; disabled when the secret is unset.
+async fn handle_tournament_report(mut req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    if let Some(rejection) = reject_non_admin(&req, &ctx)? {
+        return Ok(rejection);
+    }
+    let Some(id) = valid_tournament_id(&ctx) else {
+        return Response::error("Invalid tournament id", 400);
+    };
+    let body = req.text().await?;
+    fetch_tournament(&ctx, &id, Method::Post, "/report", Some(body)).await
+}
+
+fn valid_tournament_id(ctx: &RouteContext<()>) -> Option<String> {
+    let id = ctx.param("id")?;
+    server_do::tournament::is_valid_tournament_id(id).then(|| id.clone())
+}
+
+/// Error response for a request without the admin bearer token, or None if it has it
+fn reject_non_admin(req: &Request, ctx: &RouteContext<()>) -> Result<Option<Response>> {
+    let Ok(admin_token) = ctx.env.secret("ADMIN_TOKEN") else {
+        return Response::error("Admin actions are disabled", 403).map(Some);
+    };
+    let provided = req.headers().get("Authorization")?.unwrap_or_default();
+    let expected = format!("Bearer {admin_token}");
+    if !constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+        return Response::error("Unauthorized", 401).map(Some);
+    }
+    Ok(None)
+}
+
+/// Forward a request to one tournament's Durable Object
+async fn fetch_tournament(
+    ctx: &RouteContext<()>,
+    id: &str,
+    method: Method,
+    path: &str,
+    body: Option<String>,
+) -> Result<Response> {
+    let stub = ctx.env.durable_object("TOURNAMENT")?.get_by_name(id)?;
+    let mut init = RequestInit::new();
+    init.with_method(method)
+        .with_body(body.map(|body| wasm_bindgen::JsValue::from_str(&body)));
+    let req = Request::new_with_init(&format!("https://tournament{path}"), &init)?;
+    stub.fetch_with_request(req).await
+}
+
+/// Enter the quick-play queue. Optional JSON body: {"rating": f64, "latency_ms": u16}
+async fn handle_quickplay_join(mut req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let body = req.text().await.unwrap_or_default();
+    fetch_matchmaker(&ctx, Method::Post, "/join", Some(body)).await
+}
+
+/// Poll a quick-play ticket: waiting, matched (with a match code) or timed out
+async fn handle_quickplay_poll(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    match valid_ticket(&ctx) {
+        Some(ticket) => fetch_matchmaker(&ctx, Method::Get, &format!("/poll/{ticket}"), None).await,
+        None => Response::error("Invalid ticket", 400),
+    }
+}
+
+/// Leave the quick-play queue
+async fn handle_quickplay_leave(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    match valid_ticket(&ctx) {
+        Some(ticket) => {
+            fetch_matchmaker(&ctx, Method::Post, &format!("/leave/{ticket}"), None).await
+        }
+        None => Response::error("Invalid ticket", 400),
+    }
+}
+
+fn valid_ticket(ctx: &RouteContext<()>) -> Option<String> {
+    let ticket = ctx.param("ticket")?;
+    (ticket.len() == 16 && ticket.chars().all(|c| c.is_ascii_hexdigit())).then(|| ticket.clone())
+}
+
+/// Forward a request to the matchmaking Durable Object
+async fn fetch_matchmaker(
+    ctx: &RouteContext<()>,
+    method: Method,
+    path: &str,
+    body: Option<String>,
+) -> Result<Response> {
+    let matchmaker = ctx.env.durable_object("MATCHMAKER")?;
+    let stub = matchmaker.get_by_name(server_do::matchmaking::MATCHMAKER_OBJECT_NAME)?;
+    let mut init = RequestInit::new();
+    init.with_method(method)
+        .with_body(body.map(|body| wasm_bindgen::JsValue::from_str(&body)));
+    let req = Request::new_with_init(&format!("https://matchmaker{path}"), &init)?;
+    stub.fetch_with_request(req).await
 }
@@ -2,6 +2,7 @@
 //!
 //! Uses postcard for efficient binary serialization
 
+use game_core::MatchSettings;
 use postcard::{from_bytes, to_allocvec};
 
 // ============================================================================
@@ -11,6 +12,7 @@ use postcard::{from_bytes, to_allocvec};
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct GameStateSnapshot {
     pub tick: u32,
+    pub server_time_ms: u64, // Server clock when the snapshot was taken
     pub ball_x: f32,
     pub ball_y: f32,
     pub ball_vx: f32,
@@ -21,6 +23,56 @@ pub struct GameStateSnapshot {
     pub score_right: u8,
 }
 
+/// Role assigned to a connection when it joins a match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Role {
+    /// Controls a paddle
+    Player,
+    /// Read-only observer
+    Spectator,
+}
+
+/// Gameplay event from the authoritative simulation, used for client effects
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum GameEvent {
+    /// Ball bounced off a paddle at the contact point
+    PaddleHit { player_id: u8, x: f32, y: f32 },
+
+    /// Ball bounced off the top or bottom wall at the contact point
+    WallHit { x: f32, y: f32 },
+
+    /// A point was scored
+    PointScored {
+        scorer: u8, // 0 = left, 1 = right
+    },
+}
+
+/// Fixed quick-chat phrases
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum QuickChat {
+    GoodLuck,
+    NiceShot,
+    WellPlayed,
+    Oops,
+    Thanks,
+}
+
+/// Fixed emotes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Emote {
+    Wave,
+    Laugh,
+    Wow,
+    Angry,
+}
+
+/// A single chat item sent between players
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ChatItem {
+    Phrase(QuickChat),
+    Emote(Emote),
+}
+
 // ============================================================================
 // C2S Messages (Client to Server)
 // ============================================================================
@@ -28,17 +80,65 @@ pub struct GameStateSnapshot {
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum C2S {
     /// Join a match with code
-    Join { code: [u8; 5] },
+    /// profile_id: Persistent id the player's rating is kept under (None = unrated)
+    Join {
+        code: [u8; 5],
+        profile_id: Option<String>,
+    },
+
+    /// Reclaim a player slot after a dropped connection
+    /// session_token: Token received in Welcome
+    Rejoin { code: [u8; 5], session_token: u64 },
+
+    /// Watch a match read-only
+    /// delayed: Receive everything a few seconds behind live play
+    Spectate { code: [u8; 5], delayed: bool },
 
     /// Paddle input: absolute Y position
+    /// player_id: Sender's own id (the server uses the id bound to the socket)
     /// seq: Client-side sequence number
-    Input { player_id: u8, y: f32, seq: u32 },
+    /// server_time_ms: Client's estimate of the server clock when the input was made
+    /// (0 if not yet synced), used for lag compensation
+    Input {
+        player_id: u8,
+        y: f32,
+        seq: u32,
+        server_time_ms: u64,
+    },
 
     /// Ping for latency measurement
-    Ping { t_ms: u32 },
+    /// rtt_ms: Client's smoothed round trip, snapshot_tick: Newest snapshot tick received
+    /// (together they let the server pick this client's snapshot rate)
+    Ping {
+        t_ms: u32,
+        rtt_ms: u16,
+        snapshot_tick: u32,
+    },
+
+    /// Offer or accept a rematch (valid only in GameOver state); the match
+    /// restarts once both players agree on the same terms
+    /// swap_sides: Players change ends for the next game
+    Rematch { swap_sides: bool },
 
-    /// Request to restart the match (valid only in GameOver state)
-    Restart,
+    /// Pause play (valid only while playing, limited per player)
+    Pause,
+
+    /// Ready to resume a paused match; play resumes once every player is ready
+    Resume,
+
+    /// Send a quick-chat phrase or emote (subject to server cooldown)
+    Chat { player_id: u8, item: ChatItem },
+
+    /// Mute or unmute chat from another player
+    SetMute {
+        player_id: u8, // Player requesting the mute
+        target: u8,    // Player to mute
+        muted: bool,
+    },
+
+    /// Sent as soon as a Pong arrives, so the server can time the round trip itself
+    /// server_send_ms: The Pong's server_send_ms, echoed back unchanged
+    PongEcho { server_send_ms: u64 },
 }
 
 // ============================================================================
@@ -49,7 +149,10 @@ pub enum C2S {
 pub enum S2C {
     /// Welcome message with player assignment
     Welcome {
-        player_id: u8, // 0 = left, 1 = right
+        player_id: u8, // 0 = left, 1 = right (unused for spectators)
+        role: Role,
+        session_token: u64, // Present in Rejoin to reclaim this slot (0 for spectators)
+        settings: MatchSettings, // Rules chosen at creation; build Config/GameMap from these
     },
 
     /// Opponent has connected, match is ready
@@ -59,11 +162,15 @@ pub enum S2C {
     Countdown { seconds: u8 },
 
     /// Game is starting now - begin playing
-    GameStart,
+    /// server_time_ms: Server clock at the moment play began
+    GameStart { server_time_ms: u64 },
 
     /// Game state snapshot (only sent during PLAYING)
     GameState(GameStateSnapshot),
 
+    /// Gameplay events that occurred up to and including `tick`
+    Events { tick: u32, events: Vec<GameEvent> },
+
     /// Game over message
     GameOver {
         winner: u8, // 0 = left, 1 = right
@@ -72,8 +179,60 @@ pub enum S2C {
     /// Opponent disconnected
     OpponentDisconnected,
 
-    /// Pong response to ping
-    Pong { t_ms: u32 },
+    /// A player dropped mid-match; play is paused while their slot is held
+    /// seconds: Time left for them to rejoin before forfeiting
+    OpponentReconnecting { player_id: u8, seconds: u8 },
+
+    /// A dropped player rejoined; play resumes from the current state
+    OpponentReconnected { player_id: u8 },
+
+    /// A player paused the match; play is frozen until everyone is ready to resume
+    /// pauses_left: Pauses the pausing player has left
+    /// seconds: Time before play resumes even if not everyone is ready
+    Paused {
+        player_id: u8,
+        pauses_left: u8,
+        seconds: u8,
+    },
+
+    /// A pause request was refused (not playing, or no pauses left)
+    PauseDenied { pauses_left: u8 },
+
+    /// A player is ready to resume
+    ResumeRequested { player_id: u8 },
+
+    /// Synchronized countdown before a paused match resumes (3, 2, 1)
+    ResumeCountdown { seconds: u8 },
+
+    /// Play resumes now from the current state
+    /// server_time_ms: Server clock at the moment play resumed
+    Resumed { server_time_ms: u64 },
+
+    /// A player wants a rematch; sending the same Rematch accepts it
+    /// seconds: Until the offer expires
+    RematchRequested {
+        player_id: u8,
+        swap_sides: bool,
+        seconds: u8,
+    },
+
+    /// The rematch offer ran out without both players agreeing
+    RematchExpired,
+
+    /// Number of spectators watching changed
+    SpectatorCount { count: u16 },
+
+    /// Chat item from a player (player_id is the sender)
+    Chat { player_id: u8, item: ChatItem },
+
+    /// Pong response to ping, with server timestamps for clock sync
+    /// t_ms: Client send time echoed back
+    /// server_recv_ms / server_send_ms: Server clock when the ping arrived / pong left
+    Pong {
+        t_ms: u32,
+        server_recv_ms: u64,
+        server_send_ms: u64,
+    },
 }
 
 // ============================================================================
@@ -114,6 +273,7 @@ mod tests {
             player_id: 0,
             y: 10.0,
             seq: 1,
+            server_time_ms: 123_456,
         };
         let bytes = msg.to_bytes().expect("Serialization should succeed");
         let decoded = C2S::from_bytes(&bytes).expect("Deserialization should succeed");
@@ -123,16 +283,19 @@ mod tests {
                     player_id: p1,
                     y: y1,
                     seq: s1,
+                    server_time_ms: t1,
                 },
                 C2S::Input {
                     player_id: p2,
                     y: y2,
                     seq: s2,
+                    server_time_ms: t2,
                 },
             ) => {
                 assert_eq!(p1, p2);
                 assert!((y1 - y2).abs() < f32::EPSILON);
                 assert_eq!(s1, s2);
+                assert_eq!(t1, t2);
             }
             _ => panic!("Message type mismatch"),
         }
@@ -142,6 +305,7 @@ mod tests {
     fn test_s2c_serialization() {
         let msg = S2C::GameState(GameStateSnapshot {
             tick: 100,
+            server_time_ms: 1_700_000_000_000,
             ball_x: 16.0,
             ball_y: 12.0,
             ball_vx: 8.0,
@@ -156,9 +320,36 @@ mod tests {
         match decoded {
             S2C::GameState(snapshot) => {
                 assert_eq!(snapshot.tick, 100);
+                assert_eq!(snapshot.server_time_ms, 1_700_000_000_000);
                 assert_eq!(snapshot.ball_x, 16.0);
             }
             _ => panic!("Message type mismatch"),
         }
     }
+
+    #[test]
+    fn test_events_serialization() {
+        let msg = S2C::Events {
+            tick: 42,
+            events: vec![
+                GameEvent::PaddleHit {
+                    player_id: 1,
+                    x: 29.7,
+                    y: 11.5,
+                },
+                GameEvent::WallHit { x: 20.0, y: 0.5 },
+                GameEvent::PointScored { scorer: 0 },
+            ],
+        };
+        let bytes = msg.to_bytes().expect("Serialization should succeed");
+        let decoded = S2C::from_bytes(&bytes).expect("Deserialization should succeed");
+        match decoded {
+            S2C::Events { tick, events } => {
+                assert_eq!(tick, 42);
+                assert_eq!(events.len(), 3);
+                assert_eq!(events[2], GameEvent::PointScored { scorer: 0 });
+            }
+            _ => panic!("Message type mismatch"),
+        }
+    }
 }
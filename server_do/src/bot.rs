@@ -0,0 +1,63 @@
+//! Server-driven AI opponent for matches nobody else joins
+//!
+//! The bot occupies a normal player slot through `BotClient`, a connection
+//! that discards everything sent to it, so countdown, scoring, snapshots
+//! and results all take the same path as a two-human match. Its paddle is
+//! driven by `game_core::ai_paddle_y` feeding the net queue every tick.
+
+use crate::game_state::GameClient;
+use worker::Result;
+
+/// How long a lone player waits before `BotFill::AfterWait` fills the slot
+pub const BOT_FILL_WAIT_MS: u64 = 30_000;
+
+/// When an empty slot is given to the bot, chosen at match creation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BotFill {
+    /// Only humans play
+    #[default]
+    Never,
+    /// The bot joins as soon as the first player does
+    Immediate,
+    /// The bot joins if nobody else has after `BOT_FILL_WAIT_MS`
+    AfterWait,
+}
+
+impl BotFill {
+    /// Parse the `bot` option from `/create` (`now` or `wait`)
+    pub fn from_option(value: &str) -> Option<Self> {
+        match value {
+            "now" => Some(BotFill::Immediate),
+            "wait" => Some(BotFill::AfterWait),
+            "never" | "off" => Some(BotFill::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Connection standing in for the bot; there is nobody to send to
+pub struct BotClient;
+
+impl GameClient for BotClient {
+    fn send_bytes(&self, _bytes: &[u8]) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bot_fill_options() {
+        assert_eq!(BotFill::from_option("now"), Some(BotFill::Immediate));
+        assert_eq!(BotFill::from_option("wait"), Some(BotFill::AfterWait));
+        assert_eq!(BotFill::from_option("off"), Some(BotFill::Never));
+        assert_eq!(BotFill::from_option("later"), None);
+        assert_eq!(
+            serde_json::to_string(&BotFill::AfterWait).unwrap(),
+            "\"after_wait\""
+        );
+    }
+}
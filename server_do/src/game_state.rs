@@ -4,40 +4,94 @@ use game_core::*;
 use hecs::World;
 use js_sys::Date;
 use proto::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use worker::*;
 
-/// Server-side match lifecycle state
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum MatchState {
-    /// Waiting for players to join
-    Waiting,
-    /// Both players connected, counting down
-    Countdown,
-    /// Game in progress
-    Playing,
-    /// Game ended
-    GameOver,
-}
+use crate::bot::{BotClient, BotFill, BOT_FILL_WAIT_MS};
+use crate::input_validator::{InputError, InputValidator};
+use crate::lag_compensation::{rewind_ms, PaddleHistory};
+pub use crate::match_fsm::{MatchAction, MatchState};
+use crate::match_history::{MatchResult, PlayerResult, RallyStats, Ruleset};
+use crate::metrics::{names, ConsoleMetrics, Metrics, NoopMetrics};
+use crate::rating::is_valid_profile_id;
+use crate::snapshot_rate::{SnapshotRate, DEFAULT_INTERVAL_TICKS};
+use crate::tournament::is_valid_tournament_id;
+
+/// Minimum time between chat items from the same player
+pub const CHAT_COOLDOWN_MS: u64 = 2000;
+
+/// How far behind live play delayed spectators are kept
+pub const SPECTATOR_DELAY_MS: u64 = 3000;
+
+/// How long a dropped player has to rejoin before forfeiting
+pub const RECONNECT_GRACE_SECONDS: u8 = 15;
+
+/// Pauses each player may call per game
+pub const PAUSES_PER_PLAYER: u8 = 2;
+
+/// Longest a pause lasts before play resumes without everyone being ready
+pub const PAUSE_MAX_SECONDS: u8 = 30;
+
+/// Length of the synchronized countdown before a paused match resumes
+pub const RESUME_COUNTDOWN_SECONDS: u8 = 3;
+
+/// How long a rematch offer stays open for the other player to accept
+pub const REMATCH_OFFER_SECONDS: u8 = 30;
 
 // Abstract connection for testing
 pub trait GameClient {
     fn send_bytes(&self, bytes: &[u8]) -> Result<()>;
+
+    /// Close the connection from the server side
+    fn close(&self, _reason: &str) -> Result<()> {
+        Ok(())
+    }
 }
 
 impl GameClient for WebSocket {
     fn send_bytes(&self, bytes: &[u8]) -> Result<()> {
         self.send_with_bytes(bytes)
     }
+
+    fn close(&self, reason: &str) -> Result<()> {
+        WebSocket::close(self, Some(1000), Some(reason))
+    }
 }
 
-// Abstract environment (Time, Logging)
+// Abstract environment (Time, Logging, Metrics)
 pub trait Environment {
     fn now(&self) -> u64; // ms
     fn log(&self, msg: String);
+
+    /// Monotonic ms for timing how long code runs, if the host has a clock
+    /// that moves while it does. Workers freeze `Date.now()` for the whole
+    /// request, so the Durable Object has none and skips duration metrics.
+    fn precise_ms(&self) -> Option<f64> {
+        None
+    }
+
+    fn metrics(&self) -> &dyn Metrics {
+        &NoopMetrics
+    }
 }
 
-pub struct WasmEnv;
+pub struct WasmEnv {
+    metrics: ConsoleMetrics,
+}
+
+impl WasmEnv {
+    pub fn new() -> Self {
+        Self {
+            metrics: ConsoleMetrics::new(|line| console_log!("{}", line)),
+        }
+    }
+}
+
+impl Default for WasmEnv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Environment for WasmEnv {
     fn now(&self) -> u64 {
@@ -50,12 +104,156 @@ impl Environment for WasmEnv {
         // Or actually console_log! invokes web_sys::console::log_1.
         console_log!("{}", msg);
     }
+
+    fn metrics(&self) -> &dyn Metrics {
+        &self.metrics
+    }
+}
+
+/// Send to one connection, counting the message and its size
+pub fn send_counted(env: &dyn Environment, client: &dyn GameClient, bytes: &[u8]) {
+    env.metrics().count(names::MESSAGES_OUT, 1);
+    env.metrics().count(names::BYTES_OUT, bytes.len() as u64);
+    let _ = client.send_bytes(bytes);
 }
 
 // Track client activity
 pub struct ClientInfo {
     pub client: Box<dyn GameClient>,
+    pub last_activity: u64,        // Unix timestamp in seconds
+    pub last_chat_ms: Option<u64>, // When this player last sent chat (for cooldown)
+    pub muted: HashSet<u8>,        // Players whose chat this client does not receive
+    pub session_token: u64,        // Token for reclaiming this slot after a drop
+    pub input_validator: InputValidator,
+    pub snapshot_rate: SnapshotRate, // Per-client snapshot cadence
+    pub rewind_ms: u64, // Lag compensation for the latest input, used by the next step only
+    pub rtt_ms: u16,    // Round trip time last reported in a ping
+    pub measured_rtt_ms: u16, // Round trip timed by the server from PongEcho (0 until then)
+    pub pong_sent_ms: Option<u64>, // Server send time of the Pong awaiting its echo
+}
+
+impl ClientInfo {
+    pub fn new(client: Box<dyn GameClient>, now_ms: u64, session_token: u64) -> Self {
+        Self {
+            client,
+            last_activity: now_ms / 1000,
+            last_chat_ms: None,
+            muted: HashSet::new(),
+            session_token,
+            input_validator: InputValidator::new(),
+            snapshot_rate: SnapshotRate::new(),
+            rewind_ms: 0,
+            rtt_ms: 0,
+            measured_rtt_ms: 0,
+            pong_sent_ms: None,
+        }
+    }
+}
+
+// Slot held open for a player who dropped mid-match
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct PendingReconnect {
+    pub player_id: u8,
+    pub session_token: u64,
+    pub seconds_remaining: u8,
+}
+
+/// A pause in progress
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct PauseInfo {
+    pub paused_by: u8,
+    pub seconds_remaining: u8, // Until play resumes even if not everyone is ready
+    pub ready: [bool; 2],      // Players who asked to resume
+    pub resume_countdown: Option<u8>, // Seconds left once everyone is ready
+}
+
+/// A rematch offer waiting for both players to agree
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct RematchOffer {
+    pub votes: [bool; 2], // Players who agreed to these terms
+    pub swap_sides: bool, // Players change ends for the next game
+    pub expires_ms: u64,  // Offer lapses at this server time
+}
+
+// Player slot as kept in storage (the socket itself survives hibernation separately)
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct PersistedPlayer {
+    pub player_id: u8,
+    pub session_token: u64,
+}
+
+/// Essential match state kept in Durable Object storage so an evicted
+/// object can resume the match instead of resetting to Waiting
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PersistedMatch {
+    pub code: String,
+    pub settings: MatchSettings,
+    pub match_started_ms: u64,
+    pub match_state: MatchState,
+    pub countdown_remaining: u8,
+    pub score: Score,
+    pub config: Config,
+    pub tick: u32,
+    pub seed: u64,
+    pub players: Vec<PersistedPlayer>,
+    pub profiles: HashMap<u8, String>,
+    pub pending_reconnect: Option<PendingReconnect>,
+    pub tournament_id: Option<String>,
+    pub bot_fill: BotFill,
+    pub bot: Option<u8>,
+    pub pause: Option<PauseInfo>,
+    pub pauses_used: [u8; 2],
+    pub rematch: Option<RematchOffer>,
+}
+
+/// A player slot as reported by the status endpoint
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PlayerStatus {
+    pub player_id: u8,
+    pub connected: bool,    // False while the slot is held for a reconnect
     pub last_activity: u64, // Unix timestamp in seconds
+    pub rtt_ms: u16,
+    pub snapshot_interval_ticks: u32,
+    pub profile_id: Option<String>,
+    pub bot: bool,
+}
+
+/// A held slot as reported by the status endpoint. The session token is
+/// left out: the endpoint is public, and the token is all a rejoin needs.
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct ReconnectStatus {
+    pub player_id: u8,
+    pub seconds_remaining: u8,
+}
+
+/// Snapshot of a running match for introspection
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MatchStatus {
+    pub code: String,
+    pub match_state: MatchState,
+    pub countdown_remaining: u8,
+    pub players: Vec<PlayerStatus>,
+    pub spectators: usize,
+    pub pending_reconnect: Option<ReconnectStatus>,
+    pub score: Score,
+    pub tick: u32,
+    pub accumulator_ms: f32, // Simulation time owed but not yet stepped
+    pub last_tick_ms: u64,
+    pub settings: MatchSettings,
+    pub config: Config,
+    pub tournament_id: Option<String>,
+    pub bot_fill: BotFill,
+    pub pause: Option<PauseInfo>,
+    pub pauses_used: [u8; 2],
+    pub rematch: Option<RematchOffer>,
+}
+
+// Read-only connection watching the match
+pub struct SpectatorInfo {
+    pub client: Box<dyn GameClient>,
+    pub delayed: bool,  // Receive everything SPECTATOR_DELAY_MS behind live play
+    pub joined_ms: u64, // Delayed spectators get only what was queued from here on
+    pub catch_up: Vec<Vec<u8>>, // Catch-up held back from a delayed spectator until the delay passes
 }
 
 // Game state wrapper for interior mutability
@@ -65,19 +263,41 @@ pub struct GameState {
     pub time: Time,
     pub map: GameMap,
     pub config: Config,
+    pub settings: MatchSettings, // Chosen at creation; map and config are built from these
     pub score: Score,
     pub events: Events,
     pub net_queue: NetQueue,
     pub rng: GameRng,
+    pub seed: u64, // Seed rng was created from, so a restored match replays the same serves
     pub respawn_state: RespawnState,
     pub clients: HashMap<u8, ClientInfo>, // player_id (0=left, 1=right) -> ClientInfo
-    pub next_player_id: u8,
+    pub profiles: HashMap<u8, String>, // player_id -> persistent profile id, kept while a slot is held
+    pub spectators: HashMap<u32, SpectatorInfo>, // spectator_id -> SpectatorInfo
+    pub next_spectator_id: u32,
+    pub delayed_messages: VecDeque<(u64, Vec<u8>)>, // (server ms, encoded message) for delayed spectators
     pub match_state: MatchState,
     pub countdown_remaining: u8, // Countdown seconds remaining (3, 2, 1, 0)
+    pub pending_reconnect: Option<PendingReconnect>,
     pub tick: u32,
     pub last_input: HashMap<u8, i8>, // Track last input per player to reduce logging
     pub last_tick_time: u64,         // Unix timestamp in ms
-    pub accumulator: f32,            // For alarm loop catch-up timing
+    pub clock: TickClock,            // Real time owed to the simulation
+    pub pending_events: Vec<GameEvent>, // Gameplay events not yet sent to clients
+    pub needs_persist: bool,         // Essential state changed since last saved
+    pub paddle_history: PaddleHistory, // Recent paddle positions for lag compensation
+    pub code: String,                // Match code players joined with
+    pub match_started_ms: u64,       // When the current game began (GameStart)
+    pub rallies: RallyStats,
+    pub current_rally_hits: u32,
+    pub finished_result: Option<MatchResult>, // Result not yet handed to match history
+    pub tournament_id: Option<String>,        // Tournament this match's result is reported to
+    pub bot_fill: BotFill,                    // When an empty slot is given to the AI
+    pub bot: Option<u8>,                      // Slot the AI is playing, if any
+    pub alone_since_ms: Option<u64>, // When the only player started waiting for an opponent
+    pub pause: Option<PauseInfo>,
+    pub pauses_used: [u8; 2], // Pauses each player has called this game
+    pub rematch: Option<RematchOffer>,
+    pub sides_swapped: bool, // Player ids changed ends; sockets must be rebound to them
 }
 
 impl GameState {
@@ -89,7 +309,8 @@ impl GameState {
         let score = Score::new();
         let events = Events::new();
         let net_queue = NetQueue::new();
-        let rng = GameRng::default();
+        let seed = 12345;
+        let rng = GameRng::new(seed);
 
         // Create ball at center
         let ball_pos = map.ball_spawn();
@@ -104,51 +325,235 @@ impl GameState {
             time,
             map,
             config,
+            settings: MatchSettings::default(),
             score,
             events,
             net_queue,
             rng,
+            seed,
             respawn_state: RespawnState::new(),
             clients: HashMap::new(),
-            next_player_id: 0,
+            profiles: HashMap::new(),
+            spectators: HashMap::new(),
+            next_spectator_id: 0,
+            delayed_messages: VecDeque::new(),
             match_state: MatchState::Waiting,
             countdown_remaining: 3,
+            pending_reconnect: None,
             tick: 0,
             last_input: HashMap::new(),
             last_tick_time: now,
-            accumulator: 0.0,
+            clock: TickClock::new(),
+            pending_events: Vec::new(),
+            needs_persist: false,
+            paddle_history: PaddleHistory::new(),
+            code: String::new(),
+            match_started_ms: now,
+            rallies: RallyStats::default(),
+            current_rally_hits: 0,
+            finished_result: None,
+            tournament_id: None,
+            bot_fill: BotFill::Never,
+            bot: None,
+            alone_since_ms: None,
+            pause: None,
+            pauses_used: [0; 2],
+            rematch: None,
+            sides_swapped: false,
+        }
+    }
+
+    /// Move the lifecycle on by `action` if the transition table allows it.
+    /// Illegal transitions are logged and leave the state unchanged.
+    pub fn transition(&mut self, action: MatchAction) -> bool {
+        let from = self.match_state;
+        match from.next(action) {
+            Some(to) => {
+                self.match_state = to;
+                true
+            }
+            None => {
+                self.env.log(format!(
+                    "DO: Ignoring illegal transition {from:?} + {action:?}"
+                ));
+                false
+            }
+        }
+    }
+
+    /// Remember the match code from the first join
+    pub fn set_code(&mut self, code: &[u8; 5]) {
+        if self.code.is_empty() {
+            self.code = String::from_utf8_lossy(code).into_owned();
+        }
+    }
+
+    /// Apply settings chosen at creation. Only allowed before anyone has joined,
+    /// so both players always see the rules they were invited to.
+    pub fn apply_settings(&mut self, settings: MatchSettings) -> std::result::Result<(), String> {
+        settings.validate().map_err(|e| e.to_string())?;
+        if self.match_state != MatchState::Waiting || !self.clients.is_empty() {
+            return Err("settings can only be changed before players join".to_string());
+        }
+
+        self.settings = settings;
+        self.config = settings.config();
+        self.map = settings.map();
+        self.world.clear();
+        let ball_vel = glam::Vec2::new(self.config.ball_speed_initial, 0.0);
+        create_ball(&mut self.world, self.map.ball_spawn(), ball_vel);
+        self.needs_persist = true;
+        Ok(())
+    }
+
+    /// Report this match's result to a tournament. Like settings, only
+    /// allowed before anyone has joined.
+    pub fn link_tournament(&mut self, tournament_id: String) -> std::result::Result<(), String> {
+        if !is_valid_tournament_id(&tournament_id) {
+            return Err(format!("invalid tournament id '{tournament_id}'"));
+        }
+        if self.match_state != MatchState::Waiting || !self.clients.is_empty() {
+            return Err("tournament can only be linked before players join".to_string());
+        }
+        self.tournament_id = Some(tournament_id);
+        self.needs_persist = true;
+        Ok(())
+    }
+
+    /// Choose when an empty slot is filled by the AI, before anyone joins
+    pub fn set_bot_fill(&mut self, bot_fill: BotFill) -> std::result::Result<(), String> {
+        if self.match_state != MatchState::Waiting || !self.clients.is_empty() {
+            return Err("bot fill can only be chosen before players join".to_string());
         }
+        self.bot_fill = bot_fill;
+        self.needs_persist = true;
+        Ok(())
+    }
+
+    /// True if `player_id` is the AI's slot
+    pub fn is_bot(&self, player_id: u8) -> bool {
+        self.bot == Some(player_id)
+    }
+
+    /// Fill the empty slot with the AI once a lone player has waited as long
+    /// as `bot_fill` asks, and keep a seated bot from timing out as idle.
+    /// Returns true if the bot joined.
+    pub fn tick_bot(&mut self) -> bool {
+        let now_ms = self.env.now();
+        if let Some(client_info) = self.bot.and_then(|bot| self.clients.get_mut(&bot)) {
+            client_info.last_activity = now_ms / 1000;
+        }
+
+        let alone = self.match_state == MatchState::Waiting
+            && self.clients.len() == 1
+            && self.bot.is_none()
+            && self.pending_reconnect.is_none();
+        if !alone || self.bot_fill == BotFill::Never {
+            self.alone_since_ms = None;
+            return false;
+        }
+
+        let alone_since_ms = *self.alone_since_ms.get_or_insert(now_ms);
+        let due = match self.bot_fill {
+            BotFill::Never => false,
+            BotFill::Immediate => true,
+            BotFill::AfterWait => now_ms.saturating_sub(alone_since_ms) >= BOT_FILL_WAIT_MS,
+        };
+        if !due {
+            return false;
+        }
+
+        let Some((player_id, _)) = self.add_player(Box::new(BotClient)) else {
+            return false;
+        };
+        self.env.log(format!(
+            "DO: No opponent joined, bot takes slot {player_id}"
+        ));
+        self.bot = Some(player_id);
+        self.alone_since_ms = None;
+        true
+    }
+
+    /// Remove the bot once no human is left to play it (and none is
+    /// expected back), so the match can go idle
+    fn release_lonely_bot(&mut self) {
+        let Some(bot) = self.bot else {
+            return;
+        };
+        let humans_left = self.clients.keys().any(|&player_id| player_id != bot);
+        if humans_left || self.pending_reconnect.is_some() {
+            return;
+        }
+        self.env
+            .log("DO: Last player left, removing bot".to_string());
+        self.clients.remove(&bot);
+        self.despawn_paddle(bot);
+        self.bot = None;
+        self.transition(MatchAction::AllLeft);
+        self.countdown_remaining = 3;
+        self.needs_persist = true;
+    }
+
+    /// Feed the bot's paddle position for this tick
+    fn drive_bot(&mut self, now_ms: u64) {
+        let Some(bot) = self.bot else {
+            return;
+        };
+        if let Some(y) = ai_paddle_y(&self.world, &self.config, bot, self.time.dt) {
+            self.net_queue.push_input(bot, y);
+        }
+        if let Some(client_info) = self.clients.get_mut(&bot) {
+            client_info.last_activity = now_ms / 1000;
+        }
+    }
+
+    /// Record the profile a player's rating is kept under. Invalid ids are
+    /// treated as unrated rather than rejecting the join.
+    pub fn set_profile(&mut self, player_id: u8, profile_id: Option<String>) {
+        match profile_id.filter(|id| is_valid_profile_id(id)) {
+            Some(id) => {
+                self.profiles.insert(player_id, id);
+            }
+            None => {
+                self.profiles.remove(&player_id);
+            }
+        }
+        self.needs_persist = true;
     }
 
     /// Try to add a player. Returns (player_id, was_empty) if successful.
     pub fn add_player(&mut self, client: Box<dyn GameClient>) -> Option<(u8, bool)> {
-        if self.clients.len() >= 2 {
+        // A held slot belongs to the dropped player until the grace period ends
+        if self.clients.len() >= 2 || self.pending_reconnect.is_some() {
             return None;
         }
 
-        let player_id = self.next_player_id;
-        self.next_player_id = (self.next_player_id + 1) % 2;
+        // Whatever was left of an abandoned game is not resumed
+        if self.match_state == MatchState::Abandoned {
+            self.reset_game();
+            self.transition(MatchAction::Reopened);
+            self.countdown_remaining = 3;
+        }
+
+        // First free slot; with one player left, the other slot is the free one
+        let player_id = (0..2).find(|player_id| !self.clients.contains_key(player_id))?;
 
         let was_empty = self.clients.is_empty();
-        let now = self.env.now() / 1000;
+        let session_token = self.new_session_token();
 
         self.clients.insert(
             player_id,
-            ClientInfo {
-                client,
-                last_activity: now,
-            },
+            ClientInfo::new(client, self.env.now(), session_token),
         );
 
-        // Spawn paddle
-        let paddle_y = self.map.paddle_spawn(player_id).y;
-        create_paddle(&mut self.world, player_id, paddle_y);
+        self.spawn_paddle(player_id);
+        self.needs_persist = true;
 
         // Check if match can start
         if self.clients.len() == 2 && self.match_state == MatchState::Waiting {
             self.env
                 .log("DO: Both players connected, starting countdown".to_string());
-            self.match_state = MatchState::Countdown;
+            self.transition(MatchAction::PlayersReady);
             self.countdown_remaining = 3;
             self.broadcast_to_all(&S2C::MatchFound);
         }
@@ -156,19 +561,108 @@ impl GameState {
         Some((player_id, was_empty))
     }
 
-    /// Broadcast a message to all connected clients
-    pub fn broadcast_to_all(&self, msg: &S2C) {
+    /// Broadcast a message to all connected clients and spectators.
+    /// Delayed spectators get it once it has aged past the spectator delay.
+    pub fn broadcast_to_all(&mut self, msg: &S2C) {
         if let Ok(bytes) = msg.to_bytes() {
             for client_info in self.clients.values() {
-                let _ = client_info.client.send_bytes(&bytes);
+                send_counted(&*self.env, &*client_info.client, &bytes);
             }
+            self.send_to_spectators(bytes);
         }
     }
 
-    pub fn remove_player(&mut self, player_id: u8) {
-        self.clients.remove(&player_id);
+    /// Send live spectators a message now and queue it for delayed ones
+    fn send_to_spectators(&mut self, bytes: Vec<u8>) {
+        for spectator in self.spectators.values().filter(|s| !s.delayed) {
+            send_counted(&*self.env, &*spectator.client, &bytes);
+        }
+        if self.spectators.values().any(|s| s.delayed) {
+            self.delayed_messages.push_back((self.env.now(), bytes));
+        }
+    }
+
+    /// Add a read-only spectator and catch it up on the match lifecycle and
+    /// the arena. Returns the spectator id.
+    pub fn add_spectator(&mut self, client: Box<dyn GameClient>, delayed: bool) -> u32 {
+        let spectator_id = self.next_spectator_id;
+        self.next_spectator_id = self.next_spectator_id.wrapping_add(1);
+
+        // Bring the spectator's UI to the same lifecycle stage as the players
+        let catch_up = match self.match_state {
+            MatchState::Waiting | MatchState::Abandoned => None,
+            MatchState::Countdown => Some(S2C::MatchFound),
+            MatchState::Playing | MatchState::AwaitingReconnect | MatchState::Paused => {
+                Some(S2C::GameStart {
+                    server_time_ms: self.match_started_ms,
+                })
+            }
+            MatchState::GameOver => self
+                .score
+                .has_winner(self.config.win_score)
+                .map(|winner| S2C::GameOver { winner }),
+        };
+        // Then the current state, so the spectator can draw the arena immediately
+        let mut catch_up: Vec<Vec<u8>> = catch_up
+            .into_iter()
+            .chain([self.generate_state_message()])
+            .filter_map(|msg| msg.to_bytes().ok())
+            .collect();
+        if !delayed {
+            for bytes in catch_up.drain(..) {
+                send_counted(&*self.env, &*client, &bytes);
+            }
+        }
 
-        // Despawn paddle
+        self.spectators.insert(
+            spectator_id,
+            SpectatorInfo {
+                client,
+                delayed,
+                joined_ms: self.env.now(),
+                catch_up,
+            },
+        );
+        self.env.log(format!(
+            "DO: Spectator {spectator_id} joined (delayed: {delayed}), {} watching",
+            self.spectators.len()
+        ));
+        self.broadcast_spectator_count();
+
+        spectator_id
+    }
+
+    pub fn remove_spectator(&mut self, spectator_id: u32) {
+        if self.spectators.remove(&spectator_id).is_some() {
+            self.broadcast_spectator_count();
+        }
+        if !self.spectators.values().any(|s| s.delayed) {
+            self.delayed_messages.clear();
+        }
+    }
+
+    fn broadcast_spectator_count(&mut self) {
+        let count = self.spectators.len().min(u16::MAX as usize) as u16;
+        self.broadcast_to_all(&S2C::SpectatorCount { count });
+    }
+
+    /// Random token a player presents to reclaim their slot after a drop
+    fn new_session_token(&self) -> u64 {
+        let mut bytes = [0u8; 8];
+        if let Err(e) = getrandom::getrandom(&mut bytes) {
+            self.env
+                .log(format!("DO: No secure randomness for session token: {e}"));
+            bytes = self.env.now().to_le_bytes();
+        }
+        u64::from_le_bytes(bytes).max(1) // 0 is reserved for spectators
+    }
+
+    fn spawn_paddle(&mut self, player_id: u8) {
+        let paddle_y = self.map.paddle_spawn(player_id).y;
+        create_paddle(&mut self.world, player_id, paddle_y);
+    }
+
+    fn despawn_paddle(&mut self, player_id: u8) {
         let entity_to_despawn =
             self.world
                 .query::<(&Paddle,)>()
@@ -184,62 +678,543 @@ impl GameState {
         if let Some(entity) = entity_to_despawn {
             let _ = self.world.despawn(entity);
         }
+    }
+
+    pub fn remove_player(&mut self, player_id: u8) {
+        // Close and error events can both arrive for one socket
+        let Some(removed) = self.clients.remove(&player_id) else {
+            return;
+        };
+        self.needs_persist = true;
+        if self.is_bot(player_id) {
+            self.bot = None;
+        }
+
+        // A drop ends any pause; the reconnect hold takes over
+        self.pause = None;
+
+        let leave = if self.clients.is_empty() {
+            MatchAction::AllLeft
+        } else {
+            MatchAction::PlayerLeft
+        };
 
         // Handle disconnection based on match state
         match self.match_state {
-            MatchState::Playing => {
-                // Forfeit: remaining player wins
-                if let Some(&remaining_player) = self.clients.keys().next() {
-                    self.broadcast_game_over(remaining_player);
+            MatchState::Playing | MatchState::Paused if !self.clients.is_empty() => {
+                // Hold the slot (and paddle) so a network blip doesn't forfeit the match
+                self.env.log(format!(
+                    "DO: Player {player_id} dropped, holding slot for {RECONNECT_GRACE_SECONDS}s"
+                ));
+                self.pending_reconnect = Some(PendingReconnect {
+                    player_id,
+                    session_token: removed.session_token,
+                    seconds_remaining: RECONNECT_GRACE_SECONDS,
+                });
+                self.transition(MatchAction::PlayerDropped);
+                self.broadcast_to_all(&S2C::OpponentReconnecting {
+                    player_id,
+                    seconds: RECONNECT_GRACE_SECONDS,
+                });
+                return;
+            }
+            MatchState::AwaitingReconnect => {
+                // Nobody left to resume with: release the held slot too
+                if let Some(pending) = self.pending_reconnect.take() {
+                    self.despawn_paddle(pending.player_id);
                 }
-                self.match_state = MatchState::GameOver;
+                self.transition(MatchAction::AllLeft);
+                self.countdown_remaining = 3;
+            }
+            MatchState::Playing | MatchState::Paused => {
+                // Last one out: nobody to award the match to
+                self.transition(MatchAction::AllLeft);
             }
             MatchState::Countdown => {
                 // Cancel countdown, notify remaining player
                 self.broadcast_to_all(&S2C::OpponentDisconnected);
-                self.match_state = MatchState::Waiting;
+                self.transition(leave);
                 self.countdown_remaining = 3;
             }
             MatchState::GameOver => {
+                self.rematch = None;
                 // Notify remaining player that opponent left (won't rematch)
                 self.broadcast_to_all(&S2C::OpponentDisconnected);
-                // Reset to waiting state
-                self.match_state = MatchState::Waiting;
+                self.transition(leave);
             }
             MatchState::Waiting => {
-                // Just update state
-                if self.clients.is_empty() {
-                    self.match_state = MatchState::Waiting;
+                self.transition(leave);
+            }
+            MatchState::Abandoned => {}
+        }
+
+        self.despawn_paddle(player_id);
+        self.release_lonely_bot();
+    }
+
+    /// Reclaim a held slot with its session token and resume play.
+    /// Returns the restored player_id.
+    pub fn rejoin_player(&mut self, client: Box<dyn GameClient>, session_token: u64) -> Option<u8> {
+        let pending = self
+            .pending_reconnect
+            .filter(|pending| pending.session_token == session_token)?;
+        let player_id = pending.player_id;
+        self.pending_reconnect = None;
+
+        let now_ms = self.env.now();
+        for msg in [
+            S2C::Welcome {
+                player_id,
+                role: Role::Player,
+                session_token,
+                settings: self.settings,
+            },
+            S2C::GameStart {
+                server_time_ms: now_ms,
+            },
+        ] {
+            if let Ok(bytes) = msg.to_bytes() {
+                send_counted(&*self.env, &*client, &bytes);
+            }
+        }
+
+        self.clients
+            .insert(player_id, ClientInfo::new(client, now_ms, session_token));
+
+        // Resume from the frozen state without a catch-up burst
+        self.env
+            .log(format!("DO: Player {player_id} rejoined, resuming match"));
+        self.transition(MatchAction::PlayerRejoined);
+        self.last_tick_time = now_ms;
+        self.clock.reset();
+        self.needs_persist = true;
+        self.broadcast_to_all(&S2C::OpponentReconnected { player_id });
+
+        Some(player_id)
+    }
+
+    /// Process one second of the reconnect grace period, forfeiting the
+    /// dropped player when it runs out
+    pub fn tick_reconnect_grace(&mut self) {
+        if self.match_state != MatchState::AwaitingReconnect {
+            return;
+        }
+        let Some(pending) = self.pending_reconnect.as_mut() else {
+            return;
+        };
+
+        pending.seconds_remaining = pending.seconds_remaining.saturating_sub(1);
+        let pending = *pending;
+
+        if pending.seconds_remaining > 0 {
+            self.broadcast_to_all(&S2C::OpponentReconnecting {
+                player_id: pending.player_id,
+                seconds: pending.seconds_remaining,
+            });
+            return;
+        }
+
+        self.env.log(format!(
+            "DO: Player {} did not rejoin in time, forfeiting",
+            pending.player_id
+        ));
+        self.pending_reconnect = None;
+        self.needs_persist = true;
+        self.despawn_paddle(pending.player_id);
+        match self.clients.keys().next() {
+            Some(&remaining_player) => self.finish_match(remaining_player, true),
+            None => {
+                self.transition(MatchAction::AllLeft);
+            }
+        }
+        self.release_lonely_bot();
+    }
+
+    /// True if `player_id`'s slot is still held by the connection given
+    /// `session_token`
+    pub fn owns_slot(&self, player_id: u8, session_token: u64) -> bool {
+        self.clients
+            .get(&player_id)
+            .is_some_and(|client_info| client_info.session_token == session_token)
+    }
+
+    /// Close a player's connection and remove them from the match
+    pub fn kick_player(&mut self, player_id: u8, reason: &str) {
+        if let Some(client_info) = self.clients.get(&player_id) {
+            let _ = client_info.client.close(reason);
+        }
+        self.remove_player(player_id);
+    }
+
+    /// Validate and queue an input from the socket bound to `player_id`.
+    /// Sockets that keep sending invalid inputs are disconnected.
+    pub fn handle_input(
+        &mut self,
+        player_id: u8,
+        claimed: u8,
+        y: f32,
+        input_server_ms: u64,
+    ) -> std::result::Result<(), InputError> {
+        let now_ms = self.env.now();
+        let arena_height = self.map.height;
+        let Some(client_info) = self.clients.get_mut(&player_id) else {
+            return Ok(());
+        };
+
+        match client_info
+            .input_validator
+            .validate(now_ms, player_id, claimed, y, arena_height)
+        {
+            Ok(y) => {
+                client_info.last_activity = now_ms / 1000;
+                client_info.rewind_ms =
+                    rewind_ms(now_ms, input_server_ms, client_info.measured_rtt_ms);
+                self.net_queue.push_input(player_id, y);
+                Ok(())
+            }
+            Err(e) if client_info.input_validator.is_abusive() => {
+                let count = client_info.input_validator.violations();
+                self.env.log(format!(
+                    "DO: Disconnecting player {player_id} after {count} invalid inputs (last: {e})"
+                ));
+                self.forfeit_player(player_id, "Too many invalid inputs");
+                Err(InputError::TooManyViolations { count })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Relay a chat item to all clients that have not muted the sender.
+    /// Returns false if the sender is unknown or still on cooldown.
+    pub fn handle_chat(&mut self, player_id: u8, item: ChatItem) -> bool {
+        let now_ms = self.env.now();
+
+        let Some(sender) = self.clients.get_mut(&player_id) else {
+            return false;
+        };
+
+        if let Some(last_ms) = sender.last_chat_ms {
+            if now_ms.saturating_sub(last_ms) < CHAT_COOLDOWN_MS {
+                self.env.log(format!(
+                    "DO: Chat from player {player_id} dropped (cooldown)"
+                ));
+                return false;
+            }
+        }
+        sender.last_chat_ms = Some(now_ms);
+        sender.last_activity = now_ms / 1000;
+
+        let msg = S2C::Chat { player_id, item };
+        if let Ok(bytes) = msg.to_bytes() {
+            for client_info in self.clients.values() {
+                if !client_info.muted.contains(&player_id) {
+                    send_counted(&*self.env, &*client_info.client, &bytes);
                 }
             }
+            self.send_to_spectators(bytes);
         }
+        true
     }
 
-    pub fn handle_input(&mut self, player_id: u8, y: f32) {
+    /// Mute or unmute chat from `target` for `player_id`
+    pub fn set_muted(&mut self, player_id: u8, target: u8, muted: bool) {
+        if player_id == target {
+            return;
+        }
         if let Some(client_info) = self.clients.get_mut(&player_id) {
-            let now = self.env.now() / 1000;
-            client_info.last_activity = now;
-            self.net_queue.push_input(player_id, y);
+            if muted {
+                client_info.muted.insert(target);
+            } else {
+                client_info.muted.remove(&target);
+            }
         }
     }
 
-    /// Reset game state for a rematch
-    pub fn restart_match(&mut self) {
+    /// Answer a ping with server receive/send times for client clock sync.
+    /// `player_id` is the sending player, if the socket belongs to one.
+    pub fn handle_ping(
+        &mut self,
+        player_id: Option<u8>,
+        t_ms: u32,
+        rtt_ms: u16,
+        snapshot_tick: u32,
+    ) -> S2C {
+        let server_recv_ms = self.env.now();
+        let server_send_ms = self.env.now();
+
+        if let Some(client_info) = player_id.and_then(|id| self.clients.get_mut(&id)) {
+            client_info.last_activity = server_recv_ms / 1000;
+            client_info.rtt_ms = rtt_ms;
+            client_info.pong_sent_ms = Some(server_send_ms);
+            let previous = client_info.snapshot_rate.interval_ticks();
+            client_info.snapshot_rate.on_report(rtt_ms, snapshot_tick);
+            let interval = client_info.snapshot_rate.interval_ticks();
+            if interval != previous {
+                self.env.log(format!(
+                    "DO: Snapshot interval for player {} now {interval} ticks (rtt {rtt_ms}ms)",
+                    player_id.unwrap_or_default()
+                ));
+            }
+        }
+
+        S2C::Pong {
+            t_ms,
+            server_recv_ms,
+            server_send_ms,
+        }
+    }
+
+    /// Time a player's round trip from the echo of our latest Pong. Only that
+    /// Pong's exact timestamp counts, so a client can slow the measurement
+    /// down but not backdate it.
+    pub fn handle_pong_echo(&mut self, player_id: u8, server_send_ms: u64) {
+        let now_ms = self.env.now();
+        let Some(client_info) = self.clients.get_mut(&player_id) else {
+            return;
+        };
+        if client_info.pong_sent_ms != Some(server_send_ms) {
+            return;
+        }
+        client_info.pong_sent_ms = None;
+        let rtt_ms = now_ms.saturating_sub(server_send_ms);
+        client_info.measured_rtt_ms = rtt_ms.min(u64::from(u16::MAX)) as u16;
+    }
+
+    /// Offer or accept a rematch. An offer with different terms replaces the
+    /// open one. The bot always agrees. Returns true if the match restarted.
+    pub fn request_rematch(&mut self, player_id: u8, swap_sides: bool) -> bool {
         if self.match_state != MatchState::GameOver {
+            return false;
+        }
+
+        let now_ms = self.env.now();
+        let mut offer = match self.rematch {
+            Some(offer) if offer.swap_sides == swap_sides && now_ms < offer.expires_ms => offer,
+            _ => RematchOffer {
+                votes: [false; 2],
+                swap_sides,
+                expires_ms: now_ms + u64::from(REMATCH_OFFER_SECONDS) * 1000,
+            },
+        };
+        offer.votes[usize::from(player_id.min(1))] = true;
+        if let Some(bot) = self.bot {
+            offer.votes[usize::from(bot.min(1))] = true;
+        }
+
+        let agreed = self.clients.len() == 2
+            && self
+                .clients
+                .keys()
+                .all(|&id| offer.votes[usize::from(id.min(1))]);
+        if !agreed {
+            let seconds = offer.expires_ms.saturating_sub(now_ms).div_ceil(1000);
+            self.rematch = Some(offer);
+            self.needs_persist = true;
+            self.broadcast_to_all(&S2C::RematchRequested {
+                player_id,
+                swap_sides,
+                seconds: u8::try_from(seconds).unwrap_or(u8::MAX),
+            });
+            return false;
+        }
+
+        self.env
+            .log(format!("DO: Rematch agreed (swap sides: {swap_sides})"));
+        self.rematch = None;
+        if swap_sides {
+            self.swap_sides();
+        }
+        self.reset_game();
+        self.start_countdown(MatchAction::RematchAgreed);
+        true
+    }
+
+    /// Withdraw a rematch offer nobody accepted in time
+    pub fn tick_rematch(&mut self) {
+        let Some(offer) = self.rematch else {
             return;
+        };
+        if self.env.now() < offer.expires_ms {
+            return;
+        }
+        self.env.log("DO: Rematch offer expired".to_string());
+        self.rematch = None;
+        self.needs_persist = true;
+        self.broadcast_to_all(&S2C::RematchExpired);
+    }
+
+    /// Move every player to the other end and tell them their new ids
+    fn swap_sides(&mut self) {
+        let other = |player_id: u8| 1 - player_id.min(1);
+        self.clients = self
+            .clients
+            .drain()
+            .map(|(player_id, mut client_info)| {
+                client_info.muted = client_info.muted.iter().map(|&id| other(id)).collect();
+                (other(player_id), client_info)
+            })
+            .collect();
+        self.profiles = self
+            .profiles
+            .drain()
+            .map(|(player_id, profile)| (other(player_id), profile))
+            .collect();
+        self.bot = self.bot.map(other);
+        self.sides_swapped = true;
+
+        for (&player_id, client_info) in &self.clients {
+            let welcome = S2C::Welcome {
+                player_id,
+                role: Role::Player,
+                session_token: client_info.session_token,
+                settings: self.settings,
+            };
+            if let Ok(bytes) = welcome.to_bytes() {
+                send_counted(&*self.env, &*client_info.client, &bytes);
+            }
+        }
+    }
+
+    /// True once after sides were swapped, so sockets can be rebound to their new ids
+    pub fn take_sides_swapped(&mut self) -> bool {
+        std::mem::take(&mut self.sides_swapped)
+    }
+
+    /// Start the game over from 0-0 with the connected players, whatever the
+    /// current state. Returns false if there is nobody to play.
+    pub fn reset_match(&mut self) -> bool {
+        if self.clients.is_empty() {
+            return false;
+        }
+        self.env.log("DO: Match reset by admin".to_string());
+
+        // A held slot can't be resumed into a fresh game
+        if let Some(pending) = self.pending_reconnect.take() {
+            self.profiles.remove(&pending.player_id);
+            self.broadcast_to_all(&S2C::OpponentDisconnected);
         }
+        self.finished_result = None;
+        self.reset_game();
+        self.transition(MatchAction::Reset);
+        self.countdown_remaining = 3;
 
-        self.env.log("DO: Restarting match".to_string());
+        if self.clients.len() == 2 {
+            self.start_countdown(MatchAction::PlayersReady);
+        }
+        true
+    }
 
-        // Reset game data
+    /// Kick a player on an admin's request
+    pub fn admin_kick(&mut self, player_id: u8) -> bool {
+        self.forfeit_player(player_id, "Kicked by admin")
+    }
+
+    /// Disconnect a player for good. Unlike a dropped connection the slot is
+    /// not held and their session token dies with it: a match in progress is
+    /// forfeited to the opponent. Returns false if the player is not connected.
+    pub fn forfeit_player(&mut self, player_id: u8, reason: &str) -> bool {
+        if !self.clients.contains_key(&player_id) {
+            return false;
+        }
+        self.kick_player(player_id, reason);
+
+        let held = self
+            .pending_reconnect
+            .is_some_and(|pending| pending.player_id == player_id);
+        if held {
+            self.pending_reconnect = None;
+            self.despawn_paddle(player_id);
+            if let Some(&remaining_player) = self.clients.keys().next() {
+                self.finish_match(remaining_player, true);
+            }
+            self.release_lonely_bot();
+        }
+        self.profiles.remove(&player_id);
+        true
+    }
+
+    /// Close every player and spectator connection
+    pub fn close_all(&mut self, reason: &str) {
+        for (_, client_info) in self.clients.drain() {
+            let _ = client_info.client.close(reason);
+        }
+        for (_, spectator) in self.spectators.drain() {
+            let _ = spectator.client.close(reason);
+        }
+    }
+
+    /// Current match state for the status endpoint
+    pub fn status(&self) -> MatchStatus {
+        let mut players: Vec<PlayerStatus> = self
+            .clients
+            .iter()
+            .map(|(&player_id, client_info)| PlayerStatus {
+                player_id,
+                connected: true,
+                last_activity: client_info.last_activity,
+                rtt_ms: client_info.rtt_ms,
+                snapshot_interval_ticks: client_info.snapshot_rate.interval_ticks(),
+                profile_id: self.profiles.get(&player_id).cloned(),
+                bot: self.is_bot(player_id),
+            })
+            .collect();
+        if let Some(pending) = self.pending_reconnect {
+            players.push(PlayerStatus {
+                player_id: pending.player_id,
+                connected: false,
+                last_activity: 0,
+                rtt_ms: 0,
+                snapshot_interval_ticks: 0,
+                profile_id: self.profiles.get(&pending.player_id).cloned(),
+                bot: false,
+            });
+        }
+        players.sort_by_key(|player| player.player_id);
+
+        MatchStatus {
+            code: self.code.clone(),
+            match_state: self.match_state,
+            countdown_remaining: self.countdown_remaining,
+            players,
+            spectators: self.spectators.len(),
+            pending_reconnect: self.pending_reconnect.map(|pending| ReconnectStatus {
+                player_id: pending.player_id,
+                seconds_remaining: pending.seconds_remaining,
+            }),
+            score: self.score,
+            tick: self.tick,
+            accumulator_ms: self.clock.accumulator() * 1000.0,
+            last_tick_ms: self.last_tick_time,
+            settings: self.settings,
+            config: self.config.clone(),
+            tournament_id: self.tournament_id.clone(),
+            bot_fill: self.bot_fill,
+            pause: self.pause,
+            pauses_used: self.pauses_used,
+            rematch: self.rematch,
+        }
+    }
+
+    /// Clear score, world and timing and respawn entities for the connected players
+    fn reset_game(&mut self) {
+        self.rematch = None;
+        self.pause = None;
+        self.pauses_used = [0; 2];
         self.score = Score::new();
         self.events = Events::new();
         self.tick = 0;
         self.last_input.clear();
         self.net_queue = NetQueue::new();
-        self.accumulator = 0.0;
+        self.pending_events.clear();
+        self.paddle_history.clear();
+        self.rallies = RallyStats::default();
+        self.current_rally_hits = 0;
+        self.clock.reset();
         self.last_tick_time = self.env.now();
         self.time = Time::default();
+        for client_info in self.clients.values_mut() {
+            client_info.snapshot_rate.restart();
+        }
 
         // Reset world entities (keep clients)
         self.world.clear();
@@ -250,19 +1225,273 @@ impl GameState {
         create_ball(&mut self.world, ball_pos, ball_vel);
 
         // Respawn paddles
-        for &player_id in self.clients.keys() {
-            let paddle_y = self.map.paddle_spawn(player_id).y;
-            create_paddle(&mut self.world, player_id, paddle_y);
+        let player_ids: Vec<u8> = self.clients.keys().copied().collect();
+        for player_id in player_ids {
+            self.spawn_paddle(player_id);
         }
+        self.needs_persist = true;
+    }
 
-        // Set state to countdown
-        self.match_state = MatchState::Countdown;
+    fn start_countdown(&mut self, action: MatchAction) {
+        if !self.transition(action) {
+            return;
+        }
         self.countdown_remaining = 3;
+        self.needs_persist = true;
 
         // Notify clients
         self.broadcast_to_all(&S2C::Countdown { seconds: 3 });
     }
 
+    /// Essential state for storage
+    pub fn persisted(&self) -> PersistedMatch {
+        let mut players: Vec<PersistedPlayer> = self
+            .clients
+            .iter()
+            .map(|(&player_id, info)| PersistedPlayer {
+                player_id,
+                session_token: info.session_token,
+            })
+            .collect();
+        players.sort_by_key(|player| player.player_id);
+
+        PersistedMatch {
+            code: self.code.clone(),
+            settings: self.settings,
+            match_started_ms: self.match_started_ms,
+            match_state: self.match_state,
+            countdown_remaining: self.countdown_remaining,
+            score: self.score,
+            config: self.config.clone(),
+            tick: self.tick,
+            seed: self.seed,
+            players,
+            profiles: self.profiles.clone(),
+            pending_reconnect: self.pending_reconnect,
+            tournament_id: self.tournament_id.clone(),
+            bot_fill: self.bot_fill,
+            bot: self.bot,
+            pause: self.pause,
+            pauses_used: self.pauses_used,
+            rematch: self.rematch,
+        }
+    }
+
+    /// Take the state to save if anything essential changed since the last call
+    pub fn take_persist(&mut self) -> Option<PersistedMatch> {
+        if !std::mem::take(&mut self.needs_persist) {
+            return None;
+        }
+        Some(self.persisted())
+    }
+
+    /// Rebuild a match from storage after eviction. `sockets` are the player
+    /// sockets that survived hibernation; saved players without one get the
+    /// usual reconnect grace period if the match was in progress.
+    pub fn restore(&mut self, saved: PersistedMatch, sockets: Vec<(u8, Box<dyn GameClient>)>) {
+        let mut sockets: HashMap<u8, Box<dyn GameClient>> = sockets.into_iter().collect();
+        let now_ms = self.env.now();
+
+        self.code = saved.code;
+        self.match_started_ms = saved.match_started_ms;
+        self.settings = saved.settings;
+        self.map = saved.settings.map();
+        self.config = saved.config;
+        self.world.clear();
+        let ball_vel = glam::Vec2::new(self.config.ball_speed_initial, 0.0);
+        create_ball(&mut self.world, self.map.ball_spawn(), ball_vel);
+        self.score = saved.score;
+        self.tick = saved.tick;
+        self.seed = saved.seed;
+        self.rng = GameRng::new(saved.seed);
+        self.profiles = saved.profiles;
+        self.tournament_id = saved.tournament_id;
+        self.bot_fill = saved.bot_fill;
+        self.pauses_used = saved.pauses_used;
+        self.countdown_remaining = saved.countdown_remaining;
+        self.last_tick_time = now_ms;
+        self.clock.reset();
+
+        // The bot has no socket to survive hibernation; it just takes its seat again
+        if let Some(bot) = saved.bot {
+            sockets.insert(bot, Box::new(BotClient));
+            self.bot = Some(bot);
+        }
+
+        let mut missing = Vec::new();
+        for player in &saved.players {
+            let Some(client) = sockets.remove(&player.player_id) else {
+                missing.push(*player);
+                continue;
+            };
+            self.clients.insert(
+                player.player_id,
+                ClientInfo::new(client, now_ms, player.session_token),
+            );
+            self.spawn_paddle(player.player_id);
+        }
+
+        let held = saved.pending_reconnect.or_else(|| {
+            missing.first().map(|player| PendingReconnect {
+                player_id: player.player_id,
+                session_token: player.session_token,
+                seconds_remaining: RECONNECT_GRACE_SECONDS,
+            })
+        });
+
+        // Rebuilding rather than transitioning: the saved state is reconciled
+        // with whichever sockets survived
+        self.match_state = match saved.match_state {
+            state if state.in_progress() && self.clients.is_empty() => {
+                // Nobody came back, so start over rather than keep the stale game
+                self.reset_game();
+                self.countdown_remaining = 3;
+                MatchState::Waiting
+            }
+            state if state.in_progress() => match held {
+                Some(pending) => {
+                    self.spawn_paddle(pending.player_id);
+                    self.pending_reconnect = Some(pending);
+                    MatchState::AwaitingReconnect
+                }
+                None if saved.pause.is_some() => {
+                    self.pause = saved.pause;
+                    MatchState::Paused
+                }
+                None => MatchState::Playing,
+            },
+            MatchState::Countdown if self.clients.len() < 2 => {
+                self.countdown_remaining = 3;
+                MatchState::Waiting
+            }
+            state => state,
+        };
+        if self.match_state == MatchState::GameOver {
+            self.rematch = saved.rematch;
+        }
+
+        self.env.log(format!(
+            "DO: Restored match in {:?} at {}-{} with {} players",
+            self.match_state,
+            self.score.left,
+            self.score.right,
+            self.clients.len()
+        ));
+        self.needs_persist = true;
+    }
+
+    /// Pause play at a player's request, using one of their pauses
+    pub fn request_pause(&mut self, player_id: u8) -> std::result::Result<(), String> {
+        let slot = usize::from(player_id.min(1));
+        let pauses_left = PAUSES_PER_PLAYER.saturating_sub(self.pauses_used[slot]);
+        let refusal = if self.match_state != MatchState::Playing {
+            Some("can only pause during play".to_string())
+        } else if pauses_left == 0 {
+            Some(format!("no pauses left (of {PAUSES_PER_PLAYER})"))
+        } else {
+            None
+        };
+        if let Some(reason) = refusal {
+            if let Some(client_info) = self.clients.get(&player_id) {
+                if let Ok(bytes) = (S2C::PauseDenied { pauses_left }).to_bytes() {
+                    send_counted(&*self.env, &*client_info.client, &bytes);
+                }
+            }
+            return Err(reason);
+        }
+
+        self.env
+            .log(format!("DO: Player {player_id} paused the match"));
+        self.pauses_used[slot] += 1;
+        self.pause = Some(PauseInfo {
+            paused_by: player_id,
+            seconds_remaining: PAUSE_MAX_SECONDS,
+            ready: [false; 2],
+            resume_countdown: None,
+        });
+        self.transition(MatchAction::PauseRequested);
+        self.needs_persist = true;
+        self.broadcast_to_all(&S2C::Paused {
+            player_id,
+            pauses_left: pauses_left - 1,
+            seconds: PAUSE_MAX_SECONDS,
+        });
+        Ok(())
+    }
+
+    /// Mark a player ready to resume. Once every human player is ready the
+    /// resume countdown starts. Returns true if it started.
+    pub fn request_resume(&mut self, player_id: u8) -> bool {
+        if self.match_state != MatchState::Paused {
+            return false;
+        }
+        let bot = self.bot;
+        let humans: Vec<u8> = self
+            .clients
+            .keys()
+            .copied()
+            .filter(|&id| Some(id) != bot)
+            .collect();
+        let Some(pause) = self.pause.as_mut() else {
+            return false;
+        };
+        if pause.resume_countdown.is_some() {
+            return false;
+        }
+
+        pause.ready[usize::from(player_id.min(1))] = true;
+        let all_ready = humans.iter().all(|&id| pause.ready[usize::from(id.min(1))]);
+        if all_ready {
+            pause.resume_countdown = Some(RESUME_COUNTDOWN_SECONDS);
+        }
+        self.needs_persist = true;
+        self.broadcast_to_all(&S2C::ResumeRequested { player_id });
+        all_ready
+    }
+
+    /// Process one second of a pause: count down to resuming once everyone
+    /// is ready, or start that countdown when the pause runs out.
+    /// Returns true if play resumed.
+    pub fn tick_pause(&mut self) -> bool {
+        if self.match_state != MatchState::Paused {
+            return false;
+        }
+        let Some(pause) = self.pause.as_mut() else {
+            return false;
+        };
+
+        match pause.resume_countdown {
+            Some(0) => {}
+            Some(seconds) => {
+                pause.resume_countdown = Some(seconds - 1);
+                self.broadcast_to_all(&S2C::ResumeCountdown { seconds });
+                return false;
+            }
+            None => {
+                pause.seconds_remaining = pause.seconds_remaining.saturating_sub(1);
+                if pause.seconds_remaining == 0 {
+                    self.env
+                        .log("DO: Pause ran out, resuming match".to_string());
+                    pause.resume_countdown = Some(RESUME_COUNTDOWN_SECONDS);
+                }
+                return false;
+            }
+        }
+
+        // Resume from the frozen state without a catch-up burst
+        let now_ms = self.env.now();
+        self.env.log("DO: Resuming paused match".to_string());
+        self.pause = None;
+        self.transition(MatchAction::Resumed);
+        self.last_tick_time = now_ms;
+        self.clock.reset();
+        self.needs_persist = true;
+        self.broadcast_to_all(&S2C::Resumed {
+            server_time_ms: now_ms,
+        });
+        true
+    }
+
     /// Process one countdown tick. Returns true if countdown finished.
     pub fn tick_countdown(&mut self) -> bool {
         if self.match_state != MatchState::Countdown {
@@ -281,8 +1510,15 @@ impl GameState {
             // Countdown finished, start game
             self.env
                 .log("DO: Countdown complete, starting game!".to_string());
-            self.match_state = MatchState::Playing;
-            self.broadcast_to_all(&S2C::GameStart);
+            self.transition(MatchAction::CountdownFinished);
+            self.match_started_ms = self.env.now();
+            // Play starts from now, not from whenever the loop last ticked
+            self.last_tick_time = self.match_started_ms;
+            self.clock.reset();
+            self.needs_persist = true;
+            self.broadcast_to_all(&S2C::GameStart {
+                server_time_ms: self.env.now(),
+            });
             true
         }
     }
@@ -292,16 +1528,13 @@ impl GameState {
             return None;
         }
 
-        self.time.dt = 0.016; // ~60 Hz
+        self.time.dt = TickClock::DT;
         self.tick += 1;
+        self.env.metrics().count(names::TICKS, 1);
 
-        if self.tick % 60 == 0 {
-            self.env.log(format!(
-                "DO: Game running, tick={}, clients={}",
-                self.tick,
-                self.clients.len()
-            ));
-        }
+        let now_ms = self.env.now();
+        self.drive_bot(now_ms);
+        self.apply_rewind(now_ms);
 
         game_core::step(
             &mut self.world,
@@ -315,16 +1548,155 @@ impl GameState {
             &mut self.respawn_state,
         );
 
+        self.record_paddle_history(now_ms);
+        self.collect_events();
+        if self.events.left_scored || self.events.right_scored {
+            self.needs_persist = true;
+        }
+
         // Return winner if any
         if let Some(winner) = self.score.has_winner(self.config.win_score) {
-            self.broadcast_game_over(winner);
-            self.match_state = MatchState::GameOver;
+            // Flush the final point before announcing the winner
+            self.broadcast_events();
+            self.finish_match(winner, false);
             return Some(winner);
         }
 
         None
     }
 
+    /// Judge hits this step against where each player saw their paddle when
+    /// making the input being applied. The rewind is used up by this step.
+    fn apply_rewind(&mut self, now_ms: u64) {
+        let mut rewinds = [0; 2];
+        for (&player_id, client_info) in self.clients.iter_mut() {
+            rewinds[usize::from(player_id.min(1))] = std::mem::take(&mut client_info.rewind_ms);
+        }
+        let rewound: Vec<(hecs::Entity, Option<f32>)> = self
+            .world
+            .query::<&Paddle>()
+            .iter()
+            .map(|(entity, paddle)| {
+                let rewind = rewinds[usize::from(paddle.player_id.min(1))];
+                let y = (rewind > 0)
+                    .then(|| {
+                        self.paddle_history
+                            .y_at(paddle.player_id, now_ms.saturating_sub(rewind))
+                    })
+                    .flatten();
+                (entity, y)
+            })
+            .collect();
+
+        for (entity, y) in rewound {
+            match y {
+                Some(y) => {
+                    let _ = self.world.insert_one(entity, RewoundPaddle { y });
+                }
+                None => {
+                    let _ = self.world.remove_one::<RewoundPaddle>(entity);
+                }
+            }
+        }
+    }
+
+    fn record_paddle_history(&mut self, now_ms: u64) {
+        let mut paddles = [None; 2];
+        for (_entity, paddle) in self.world.query::<&Paddle>().iter() {
+            paddles[usize::from(paddle.player_id.min(1))] = Some(paddle.y);
+        }
+        self.paddle_history.record(now_ms, paddles);
+    }
+
+    /// End the match, announcing the winner and keeping its result for history
+    fn finish_match(&mut self, winner: u8, forfeit: bool) {
+        self.broadcast_game_over(winner);
+        self.transition(if forfeit {
+            MatchAction::Forfeited
+        } else {
+            MatchAction::WinnerDecided
+        });
+        self.needs_persist = true;
+
+        let ended_ms = self.env.now();
+        self.finished_result = Some(MatchResult {
+            id: MatchResult::make_id(ended_ms, &self.code),
+            code: self.code.clone(),
+            players: vec![
+                PlayerResult {
+                    player_id: 0,
+                    score: self.score.left,
+                    profile_id: self.profiles.get(&0).cloned(),
+                },
+                PlayerResult {
+                    player_id: 1,
+                    score: self.score.right,
+                    profile_id: self.profiles.get(&1).cloned(),
+                },
+            ],
+            winner,
+            forfeit,
+            started_ms: self.match_started_ms,
+            ended_ms,
+            duration_ms: ended_ms.saturating_sub(self.match_started_ms),
+            rallies: self.rallies,
+            ruleset: Ruleset::from(&self.config),
+        });
+    }
+
+    /// Take the result of a match that just ended, if any
+    pub fn take_finished_result(&mut self) -> Option<MatchResult> {
+        self.finished_result.take()
+    }
+
+    /// Convert this tick's simulation events into wire events
+    fn collect_events(&mut self) {
+        if self.events.ball_hit_paddle {
+            self.rallies.paddle_hits += 1;
+            self.current_rally_hits += 1;
+        }
+        if self.events.left_scored || self.events.right_scored {
+            self.rallies.points += 1;
+            self.rallies.longest_rally = self.rallies.longest_rally.max(self.current_rally_hits);
+            self.current_rally_hits = 0;
+        }
+
+        if let Some((player_id, contact)) = self.events.paddle_contact {
+            self.pending_events.push(GameEvent::PaddleHit {
+                player_id,
+                x: contact.x,
+                y: contact.y,
+            });
+        }
+        if let Some(contact) = self.events.wall_contact {
+            self.pending_events.push(GameEvent::WallHit {
+                x: contact.x,
+                y: contact.y,
+            });
+        }
+        if self.events.left_scored {
+            self.pending_events
+                .push(GameEvent::PointScored { scorer: 0 });
+        }
+        if self.events.right_scored {
+            self.pending_events
+                .push(GameEvent::PointScored { scorer: 1 });
+        }
+    }
+
+    /// Send any pending gameplay events to all clients
+    pub fn broadcast_events(&mut self) {
+        if self.pending_events.is_empty() {
+            return;
+        }
+
+        let msg = S2C::Events {
+            tick: self.tick,
+            events: std::mem::take(&mut self.pending_events),
+        };
+        self.broadcast_to_all(&msg);
+    }
+
     pub fn generate_state_message(&self) -> S2C {
         // Get ball position and velocity
         let (ball_x, ball_y, ball_vx, ball_vy) = self
@@ -338,10 +1710,8 @@ impl GameState {
         // Get paddle positions
         let mut paddle_left_y = 12.0;
         let mut paddle_right_y = 12.0;
-        let mut paddle_count = 0;
 
         for (_e, paddle) in self.world.query::<&Paddle>().iter() {
-            paddle_count += 1;
             if paddle.player_id == 0 {
                 paddle_left_y = paddle.y;
             } else if paddle.player_id == 1 {
@@ -349,14 +1719,9 @@ impl GameState {
             }
         }
 
-        if self.tick % 60 == 0 {
-            self.env.log(format!(
-                "DO: Paddle state - count={paddle_count}, left_y={paddle_left_y:.1}, right_y={paddle_right_y:.1}"
-            ));
-        }
-
         S2C::GameState(GameStateSnapshot {
             tick: self.tick,
+            server_time_ms: self.env.now(),
             ball_x,
             ball_y,
             ball_vx,
@@ -368,25 +1733,75 @@ impl GameState {
         })
     }
 
-    pub fn broadcast_state(&self) {
-        if self.clients.is_empty() {
-            return;
-        }
+    /// Send snapshots to players whose own cadence is due (everyone if `force`).
+    /// Spectators follow the default cadence.
+    pub fn send_snapshots(&mut self, force: bool) {
+        let tick = self.tick;
+        let spectators_due = force || tick == 1 || tick % DEFAULT_INTERVAL_TICKS == 0;
+        let players_due = force
+            || self
+                .clients
+                .values()
+                .any(|client_info| client_info.snapshot_rate.is_due(tick));
 
-        let state_msg = self.generate_state_message();
-        if let Ok(bytes) = state_msg.to_bytes() {
-            for client_info in self.clients.values() {
-                let _ = client_info.client.send_bytes(&bytes);
+        if players_due || (spectators_due && !self.spectators.is_empty()) {
+            if let Ok(bytes) = self.generate_state_message().to_bytes() {
+                for client_info in self.clients.values_mut() {
+                    if force || client_info.snapshot_rate.is_due(tick) {
+                        send_counted(&*self.env, &*client_info.client, &bytes);
+                        client_info.snapshot_rate.record_sent(tick);
+                    }
+                }
+                if spectators_due {
+                    self.send_to_spectators(bytes);
+                }
             }
         }
+
+        self.flush_delayed_messages();
     }
 
-    pub fn broadcast_game_over(&self, winner: u8) {
-        let msg = S2C::GameOver { winner };
-        if let Ok(bytes) = msg.to_bytes() {
-            for client_info in self.clients.values() {
-                let _ = client_info.client.send_bytes(&bytes);
+    /// True if this tick produced events worth an immediate snapshot
+    pub fn has_priority_events(&self) -> bool {
+        self.pending_events.iter().any(|event| {
+            matches!(
+                event,
+                GameEvent::PaddleHit { .. } | GameEvent::PointScored { .. }
+            )
+        })
+    }
+
+    /// Whether delayed spectators are still owed anything
+    pub fn has_delayed_messages(&self) -> bool {
+        !self.delayed_messages.is_empty()
+            || self.spectators.values().any(|s| !s.catch_up.is_empty())
+    }
+
+    /// Send delayed spectators the messages that have aged past the delay
+    pub fn flush_delayed_messages(&mut self) {
+        let now = self.env.now();
+        for spectator in self.spectators.values_mut() {
+            if now.saturating_sub(spectator.joined_ms) < SPECTATOR_DELAY_MS {
+                continue;
+            }
+            for bytes in spectator.catch_up.drain(..) {
+                send_counted(&*self.env, &*spectator.client, &bytes);
             }
         }
+        while let Some((sent_ms, _)) = self.delayed_messages.front() {
+            if now.saturating_sub(*sent_ms) < SPECTATOR_DELAY_MS {
+                break;
+            }
+            if let Some((sent_ms, bytes)) = self.delayed_messages.pop_front() {
+                let watching = self.spectators.values();
+                for spectator in watching.filter(|s| s.delayed && s.joined_ms <= sent_ms) {
+                    send_counted(&*self.env, &*spectator.client, &bytes);
+                }
+            }
+        }
+    }
+
+    pub fn broadcast_game_over(&mut self, winner: u8) {
+        self.broadcast_to_all(&S2C::GameOver { winner });
     }
 }
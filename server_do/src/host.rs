@@ -0,0 +1,507 @@
+//! Match handling shared by every host of a `GameState`
+//!
+//! The Durable Object and the native server differ in how they hold
+//! sockets, schedule timers and store matches. What a message, a closed
+//! connection or a timer run does to the match is decided here, so both
+//! hosts play the same game.
+
+use crate::game_state::{send_counted, GameClient, GameState, MatchState};
+use crate::metrics::names;
+use crate::rate_limit::{ConnectionLimiter, MessageKind, RateLimitError};
+use proto::*;
+
+/// How often the loop runs during play. The tick clock decides how many
+/// simulation ticks each run owes, so this only sets latency, not game speed.
+pub const PLAY_ALARM_MS: u64 = 16;
+
+/// Players silent for longer than this are disconnected
+pub const IDLE_TIMEOUT_SECONDS: u64 = 120;
+
+/// Role a connection is bound to once it joins
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SocketRole {
+    /// `session_token` is the slot's token when the socket took it, so a
+    /// socket whose slot has since gone to someone else cannot act for them
+    Player {
+        player_id: u8,
+        session_token: u64,
+    },
+    Spectator {
+        spectator_id: u32,
+        delayed: bool,
+    },
+}
+
+impl SocketRole {
+    /// The same connection after players changed ends
+    pub fn swapped(self) -> Self {
+        match self {
+            SocketRole::Player {
+                player_id,
+                session_token,
+            } => SocketRole::Player {
+                player_id: 1 - player_id.min(1),
+                session_token,
+            },
+            spectator => spectator,
+        }
+    }
+
+    pub fn player_id(self) -> Option<u8> {
+        match self {
+            SocketRole::Player { player_id, .. } => Some(player_id),
+            SocketRole::Spectator { .. } => None,
+        }
+    }
+
+    fn player(gs: &GameState, player_id: u8) -> Self {
+        SocketRole::Player {
+            player_id,
+            session_token: gs.clients[&player_id].session_token,
+        }
+    }
+}
+
+/// The player slot `role` still holds, if it is a player's
+fn owned_slot(gs: &GameState, role: Option<SocketRole>) -> Option<u8> {
+    match role {
+        Some(SocketRole::Player {
+            player_id,
+            session_token,
+        }) if gs.owns_slot(player_id, session_token) => Some(player_id),
+        _ => None,
+    }
+}
+
+/// What the host has to do after a message
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Handled {
+    /// Bind the connection to this role
+    pub bind: Option<SocketRole>,
+    /// Start the match loop now (first player in, or a rejoin resumed play)
+    pub start_loop: bool,
+    /// Every player connection now belongs to the other slot
+    pub sides_swapped: bool,
+}
+
+/// Decode and handle one binary message from a connection. Messages over
+/// the connection's rate limits are dropped, and a connection that keeps
+/// exceeding them is closed. A player closed this way forfeits, like one
+/// kicked for invalid inputs, so they cannot rejoin and flood again.
+pub fn handle_bytes<C>(
+    gs: &mut GameState,
+    conn: &C,
+    role: Option<SocketRole>,
+    limiter: &mut ConnectionLimiter,
+    bytes: &[u8],
+) -> Handled
+where
+    C: GameClient + Clone + 'static,
+{
+    gs.env.metrics().count(names::MESSAGES_IN, 1);
+    gs.env.metrics().count(names::BYTES_IN, bytes.len() as u64);
+    let msg = match C2S::from_bytes(bytes) {
+        Ok(msg) => msg,
+        Err(e) => {
+            gs.env
+                .log(format!("DO: Failed to parse C2S message: {e:?}"));
+            return Handled::default();
+        }
+    };
+
+    match limiter.check(gs.env.now(), MessageKind::of(&msg)) {
+        Ok(()) => handle_message(gs, conn, role, msg),
+        Err(RateLimitError::Dropped { .. }) => {
+            gs.env.metrics().count(names::MESSAGES_DROPPED, 1);
+            Handled::default()
+        }
+        Err(e @ RateLimitError::TooManyDropped { .. }) => {
+            gs.env.metrics().count(names::MESSAGES_DROPPED, 1);
+            gs.env.metrics().count(names::RATE_LIMIT_DISCONNECTS, 1);
+            gs.env
+                .log(format!("DO: Disconnecting socket with role {role:?}: {e}"));
+            match owned_slot(gs, role) {
+                Some(player_id) => {
+                    gs.forfeit_player(player_id, "Too many messages");
+                }
+                None => {
+                    let _ = conn.close("Too many messages");
+                    handle_close(gs, role, "rate limit");
+                }
+            }
+            Handled::default()
+        }
+    }
+}
+
+/// Handle one message from a connection currently bound to `role`
+pub fn handle_message<C>(
+    gs: &mut GameState,
+    conn: &C,
+    role: Option<SocketRole>,
+    msg: C2S,
+) -> Handled
+where
+    C: GameClient + Clone + 'static,
+{
+    let mut handled = match (role, msg) {
+        // Pings are answered for any socket; only players count as active
+        (
+            _,
+            C2S::Ping {
+                t_ms,
+                rtt_ms,
+                snapshot_tick,
+            },
+        ) => {
+            let player_id = owned_slot(gs, role);
+            let pong = gs.handle_ping(player_id, t_ms, rtt_ms, snapshot_tick);
+            if let Ok(bytes) = pong.to_bytes() {
+                send_counted(&*gs.env, conn, &bytes);
+            }
+            Handled::default()
+        }
+        (None, C2S::Join { code, profile_id }) => {
+            gs.set_code(&code);
+            let Some((player_id, was_empty)) = gs.add_player(Box::new(conn.clone())) else {
+                gs.env
+                    .log("DO: Match full, rejecting new player".to_string());
+                return Handled::default();
+            };
+            gs.set_profile(player_id, profile_id);
+            gs.env.log(format!(
+                "DO: Player {player_id} joining (clients was empty: {was_empty})"
+            ));
+
+            let welcome = S2C::Welcome {
+                player_id,
+                role: Role::Player,
+                session_token: gs.clients[&player_id].session_token,
+                settings: gs.settings,
+            };
+            if let Ok(bytes) = welcome.to_bytes() {
+                send_counted(&*gs.env, conn, &bytes);
+            }
+
+            // Initial state to everyone
+            if let Ok(bytes) = gs.generate_state_message().to_bytes() {
+                for client_info in gs.clients.values() {
+                    send_counted(&*gs.env, &*client_info.client, &bytes);
+                }
+            }
+            Handled {
+                bind: Some(SocketRole::player(gs, player_id)),
+                start_loop: was_empty,
+                ..Handled::default()
+            }
+        }
+        (
+            None,
+            C2S::Rejoin {
+                code: _,
+                session_token,
+            },
+        ) => match gs.rejoin_player(Box::new(conn.clone()), session_token) {
+            Some(player_id) => {
+                if let Ok(bytes) = gs.generate_state_message().to_bytes() {
+                    send_counted(&*gs.env, conn, &bytes);
+                }
+                // Restart the simulation loop promptly
+                Handled {
+                    bind: Some(SocketRole::player(gs, player_id)),
+                    start_loop: true,
+                    ..Handled::default()
+                }
+            }
+            None => {
+                gs.env
+                    .log("DO: Rejoin rejected (no held slot for token)".to_string());
+                let _ = conn.close("Rejoin rejected");
+                Handled::default()
+            }
+        },
+        (None, C2S::Spectate { code: _, delayed }) => {
+            let welcome = S2C::Welcome {
+                player_id: 0,
+                role: Role::Spectator,
+                session_token: 0,
+                settings: gs.settings,
+            };
+            if let Ok(bytes) = welcome.to_bytes() {
+                send_counted(&*gs.env, conn, &bytes);
+            }
+
+            let spectator_id = gs.add_spectator(Box::new(conn.clone()), delayed);
+            Handled {
+                bind: Some(SocketRole::Spectator {
+                    spectator_id,
+                    delayed,
+                }),
+                ..Handled::default()
+            }
+        }
+        (role @ Some(SocketRole::Player { player_id, .. }), msg)
+            if owned_slot(gs, role).is_some() =>
+        {
+            handle_player_message(gs, player_id, msg);
+            Handled::default()
+        }
+        // Spectators are read-only, unjoined sockets may only join and
+        // sockets that lost their slot may do nothing
+        (role, msg) => {
+            gs.env.log(format!(
+                "DO: Ignoring {msg:?} from socket with role {role:?}"
+            ));
+            Handled::default()
+        }
+    };
+
+    // A rematch with swapped sides moved each player to the other id
+    handled.sides_swapped = gs.take_sides_swapped();
+    handled
+}
+
+/// Handle a message from a connection bound to `player_id`.
+/// Ids claimed inside the message are never trusted.
+fn handle_player_message(gs: &mut GameState, player_id: u8, msg: C2S) {
+    match msg {
+        C2S::Input {
+            player_id: claimed,
+            y,
+            seq: _,
+            server_time_ms,
+        } => {
+            if let Err(e) = gs.handle_input(player_id, claimed, y, server_time_ms) {
+                gs.env
+                    .log(format!("DO: Rejected input from player {player_id}: {e}"));
+            }
+        }
+        C2S::Rematch { swap_sides } => {
+            gs.request_rematch(player_id, swap_sides);
+        }
+        C2S::Pause => {
+            if let Err(e) = gs.request_pause(player_id) {
+                gs.env
+                    .log(format!("DO: Pause from player {player_id} refused: {e}"));
+            }
+        }
+        C2S::Resume => {
+            gs.request_resume(player_id);
+        }
+        C2S::Chat { player_id: _, item } => {
+            gs.handle_chat(player_id, item);
+        }
+        C2S::SetMute {
+            player_id: _,
+            target,
+            muted,
+        } => {
+            gs.set_muted(player_id, target, muted);
+        }
+        C2S::Join { .. } | C2S::Rejoin { .. } | C2S::Spectate { .. } => {
+            gs.env.log(format!(
+                "DO: Player {player_id} tried to join twice, ignoring"
+            ));
+        }
+        C2S::PongEcho { server_send_ms } => {
+            gs.handle_pong_echo(player_id, server_send_ms);
+        }
+        C2S::Ping { .. } => {
+            // Answered before dispatch
+        }
+    }
+}
+
+/// Free whatever a closed or failed connection held. `cause` is for the log.
+pub fn handle_close(gs: &mut GameState, role: Option<SocketRole>, cause: &str) {
+    match role {
+        Some(SocketRole::Player { player_id, .. }) if owned_slot(gs, role).is_none() => {
+            gs.env.log(format!(
+                "DO: Ignoring {cause} from a socket no longer holding slot {player_id}"
+            ));
+        }
+        Some(SocketRole::Player { player_id, .. }) => {
+            gs.env
+                .log(format!("DO: Removing player {player_id} after {cause}"));
+            gs.remove_player(player_id);
+        }
+        Some(SocketRole::Spectator { spectator_id, .. }) => {
+            gs.env.log(format!(
+                "DO: Removing spectator {spectator_id} after {cause}"
+            ));
+            gs.remove_spectator(spectator_id);
+        }
+        None => {
+            gs.env
+                .log("DO: Socket closed before joining, nothing to clean up".to_string());
+        }
+    }
+}
+
+/// Alarm loop for hosts that keep their own timers, run against the match's
+/// clock (`Environment::now`). The Durable Object leaves timing to the
+/// Workers runtime and calls `run_alarm` itself; the native server and the
+/// test harness ask the driver when the next run is due.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AlarmDriver {
+    next_ms: Option<u64>, // Match time of the next run; None while the loop is stopped
+}
+
+impl AlarmDriver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match time the next run is due, if the loop is running
+    pub fn next_ms(&self) -> Option<u64> {
+        self.next_ms
+    }
+
+    /// Start (or hurry) the loop so it runs one play interval from now
+    pub fn start(&mut self, gs: &GameState) {
+        self.next_ms = Some(gs.env.now() + PLAY_ALARM_MS);
+    }
+
+    /// Follow up on a handled message
+    pub fn after_message(&mut self, gs: &GameState, handled: &Handled) {
+        if handled.start_loop {
+            self.start(gs);
+        }
+    }
+
+    /// Run the loop if it is due by the match clock. Returns true if it ran.
+    /// A late call runs once, like a late alarm; the tick clock catches up.
+    pub fn run_due(&mut self, gs: &mut GameState) -> bool {
+        match self.next_ms {
+            Some(due_ms) if due_ms <= gs.env.now() => {
+                self.next_ms = run_alarm(gs).map(|delay_ms| gs.env.now() + delay_ms);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// One run of the match loop: drop idle players, then advance whatever the
+/// current state needs. Returns how long until the next run, or None once
+/// nobody is connected and the loop can stop. Either way the caller should
+/// record any result the run finished.
+pub fn run_alarm(gs: &mut GameState) -> Option<u64> {
+    let now_ms = gs.env.now();
+    let now_seconds = now_ms / 1000;
+
+    let mut idle = Vec::new();
+    for (player_id, client_info) in gs.clients.iter() {
+        let elapsed = now_seconds.saturating_sub(client_info.last_activity);
+        if elapsed > IDLE_TIMEOUT_SECONDS {
+            gs.env.log(format!(
+                "DO: Client {} idle for {}s (now: {}, last: {}), disconnecting",
+                player_id, elapsed, now_seconds, client_info.last_activity
+            ));
+            idle.push(*player_id);
+        }
+    }
+    // In slot order, so who is kicked first does not depend on map order
+    idle.sort_unstable();
+    for player_id in idle {
+        gs.kick_player(player_id, "Idle timeout");
+    }
+
+    // Seat the AI if a lone player has waited long enough
+    gs.tick_bot();
+    gs.flush_delayed_messages();
+
+    // Delayed spectators are owed the rest of the queue even once play stops
+    let idle_ms = if gs.has_delayed_messages() {
+        PLAY_ALARM_MS
+    } else {
+        500
+    };
+
+    if gs.clients.is_empty() {
+        if gs.has_delayed_messages() {
+            return Some(PLAY_ALARM_MS);
+        }
+        gs.env
+            .log("DO: No clients remaining, stopping alarm loop".to_string());
+        return None;
+    }
+
+    let next_ms = match gs.match_state {
+        // Low frequency, just for idle checks
+        MatchState::Waiting | MatchState::Abandoned => idle_ms,
+        MatchState::Countdown => {
+            // Count down every second, then get straight into play
+            if gs.tick_countdown() {
+                PLAY_ALARM_MS
+            } else {
+                1000
+            }
+        }
+        MatchState::Playing => {
+            run_ticks(gs, now_ms);
+            PLAY_ALARM_MS
+        }
+        MatchState::AwaitingReconnect => {
+            // Count down the grace period every second
+            gs.tick_reconnect_grace();
+            1000
+        }
+        MatchState::Paused => {
+            // Count down the pause every second, then get straight back to play
+            if gs.tick_pause() {
+                PLAY_ALARM_MS
+            } else {
+                1000
+            }
+        }
+        MatchState::GameOver => {
+            // Low frequency, for cleanup and expiring rematch offers
+            gs.tick_rematch();
+            idle_ms
+        }
+    };
+
+    gs.env.metrics().flush(now_ms);
+    Some(next_ms)
+}
+
+/// Run however many ticks the clock says are due, then send what changed
+fn run_ticks(gs: &mut GameState, now_ms: u64) {
+    let elapsed_ms = now_ms.saturating_sub(gs.last_tick_time);
+    gs.last_tick_time = now_ms;
+
+    let steps_run = gs.clock.advance(elapsed_ms as f32 / 1000.0);
+    let steps_started_ms = gs.env.precise_ms();
+    for _ in 0..steps_run {
+        gs.step();
+    }
+
+    let metrics = gs.env.metrics();
+    metrics.observe(names::TICK_INTERVAL_MS, elapsed_ms as f64);
+    metrics.observe(names::ALARM_STEPS, f64::from(steps_run));
+    if let (Some(started_ms), Some(ended_ms)) = (steps_started_ms, gs.env.precise_ms()) {
+        if steps_run > 0 {
+            let step_ms = ended_ms - started_ms;
+            metrics.observe(names::TICK_DURATION_MS, step_ms / f64::from(steps_run));
+        }
+    }
+    if steps_run > 1 {
+        metrics.count(names::CATCH_UP, 1);
+        metrics.event(
+            names::CATCH_UP,
+            &[
+                ("steps", f64::from(steps_run)),
+                ("accumulator_ms", f64::from(gs.clock.accumulator() * 1000.0)),
+                ("tick", f64::from(gs.tick)),
+            ],
+        );
+    }
+
+    // Snapshots follow each player's cadence, but hits and points go out at once
+    let force = gs.has_priority_events();
+    gs.send_snapshots(force);
+
+    // Events go out every alarm so effects stay in sync with physics
+    gs.broadcast_events();
+}
@@ -0,0 +1,161 @@
+//! Server-side validation of paddle inputs
+//!
+//! The server never trusts client inputs: ids must match the socket's bound
+//! player and positions must be finite and inside the arena. Sockets with
+//! many violations in a short span are disconnected; the odd bad input over
+//! a long match is forgiven. How often inputs may arrive is left to the
+//! connection's rate limiter (`rate_limit`).
+
+use std::fmt;
+
+/// Violations within one violation window that get a socket disconnected
+pub const MAX_INPUT_VIOLATIONS: u32 = 20;
+
+/// Violations are counted afresh once this long has passed since the
+/// window's first one (ms)
+pub const VIOLATION_WINDOW_MS: u64 = 10_000;
+
+/// Why an input was rejected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputError {
+    /// Input claimed a player id other than the one bound to the socket
+    SpoofedPlayerId { claimed: u8, actual: u8 },
+    /// Target y was NaN or infinite
+    NonFiniteY,
+    /// Too many violations; the socket has been disconnected
+    TooManyViolations { count: u32 },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::SpoofedPlayerId { claimed, actual } => {
+                write!(f, "player {actual} sent input claiming player {claimed}")
+            }
+            InputError::NonFiniteY => write!(f, "non-finite paddle position"),
+            InputError::TooManyViolations { count } => {
+                write!(f, "disconnected after {count} invalid inputs")
+            }
+        }
+    }
+}
+
+/// Per-socket input checks and violation tracking
+#[derive(Debug, Default)]
+pub struct InputValidator {
+    violations: u32, // In the current violation window
+    violations_since_ms: u64,
+}
+
+impl InputValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check one input from `player_id`'s socket, returning the sanitized y.
+    /// Out of range positions are clamped to the arena rather than rejected.
+    pub fn validate(
+        &mut self,
+        now_ms: u64,
+        player_id: u8,
+        claimed: u8,
+        y: f32,
+        arena_height: f32,
+    ) -> Result<f32, InputError> {
+        let result = Self::check(player_id, claimed, y, arena_height);
+        if result.is_err() {
+            if self.violations == 0
+                || now_ms.saturating_sub(self.violations_since_ms) >= VIOLATION_WINDOW_MS
+            {
+                self.violations = 0;
+                self.violations_since_ms = now_ms;
+            }
+            self.violations += 1;
+        }
+        result
+    }
+
+    fn check(player_id: u8, claimed: u8, y: f32, arena_height: f32) -> Result<f32, InputError> {
+        if claimed != player_id {
+            return Err(InputError::SpoofedPlayerId {
+                claimed,
+                actual: player_id,
+            });
+        }
+        if !y.is_finite() {
+            return Err(InputError::NonFiniteY);
+        }
+
+        Ok(y.clamp(0.0, arena_height))
+    }
+
+    /// Number of rejected inputs from this socket in the current window
+    pub fn violations(&self) -> u32 {
+        self.violations
+    }
+
+    /// True once the socket should be disconnected
+    pub fn is_abusive(&self) -> bool {
+        self.violations >= MAX_INPUT_VIOLATIONS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accepts_and_clamps() {
+        let mut validator = InputValidator::new();
+        assert_eq!(validator.validate(0, 1, 1, 12.0, 24.0), Ok(12.0));
+        assert_eq!(validator.validate(0, 1, 1, 50.0, 24.0), Ok(24.0));
+        assert_eq!(validator.validate(0, 1, 1, -3.0, 24.0), Ok(0.0));
+        assert_eq!(validator.violations(), 0);
+    }
+
+    #[test]
+    fn test_rejects_spoofed_id_and_non_finite() {
+        let mut validator = InputValidator::new();
+        assert_eq!(
+            validator.validate(0, 1, 0, 12.0, 24.0),
+            Err(InputError::SpoofedPlayerId {
+                claimed: 0,
+                actual: 1
+            })
+        );
+        assert_eq!(
+            validator.validate(0, 1, 1, f32::NAN, 24.0),
+            Err(InputError::NonFiniteY)
+        );
+        assert_eq!(
+            validator.validate(0, 1, 1, f32::INFINITY, 24.0),
+            Err(InputError::NonFiniteY)
+        );
+        assert_eq!(validator.violations(), 3);
+    }
+
+    #[test]
+    fn test_abusive_after_max_violations() {
+        let mut validator = InputValidator::new();
+        for _ in 0..MAX_INPUT_VIOLATIONS - 1 {
+            let _ = validator.validate(0, 0, 0, f32::NAN, 24.0);
+        }
+        assert!(!validator.is_abusive());
+        let _ = validator.validate(0, 0, 0, f32::NAN, 24.0);
+        assert!(validator.is_abusive());
+    }
+
+    #[test]
+    fn test_violations_are_forgiven_over_time() {
+        let mut validator = InputValidator::new();
+        // Just under the limit in every window, for a long match
+        for window in 0..30 {
+            let now_ms = window * VIOLATION_WINDOW_MS;
+            for _ in 0..MAX_INPUT_VIOLATIONS - 1 {
+                let _ = validator.validate(now_ms, 0, 0, f32::NAN, 24.0);
+            }
+            assert!(!validator.is_abusive());
+        }
+        assert_eq!(validator.violations(), MAX_INPUT_VIOLATIONS - 1);
+    }
+}
@@ -0,0 +1,111 @@
+//! Server-side lag compensation for paddle hits
+//!
+//! Inputs reach the server one trip after the player made them, so a
+//! high-ping player's paddle is always behind what they saw. The server keeps
+//! a short history of paddle positions and also accepts hits against where a
+//! paddle was at the player's input timestamp, within a bounded rewind window
+//! that is never longer than the player's own round trip.
+
+use std::collections::VecDeque;
+
+/// Furthest back a hit may be judged (ms)
+pub const MAX_REWIND_MS: u64 = 150;
+
+/// Extra history kept beyond the rewind window so lookups at its edge resolve
+const HISTORY_MARGIN_MS: u64 = 50;
+
+/// Recent paddle positions by server time
+#[derive(Debug, Default)]
+pub struct PaddleHistory {
+    samples: VecDeque<(u64, [Option<f32>; 2])>, // (server ms, y for player 0 and 1)
+}
+
+impl PaddleHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record paddle positions after a tick, dropping samples that can no longer be rewound to
+    pub fn record(&mut self, now_ms: u64, paddles: [Option<f32>; 2]) {
+        self.samples.push_back((now_ms, paddles));
+        let oldest_needed = now_ms.saturating_sub(MAX_REWIND_MS + HISTORY_MARGIN_MS);
+        while self
+            .samples
+            .front()
+            .is_some_and(|&(sample_ms, _)| sample_ms < oldest_needed)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Where `player_id`'s paddle was at `time_ms` (the newest sample not after it)
+    pub fn y_at(&self, player_id: u8, time_ms: u64) -> Option<f32> {
+        let slot = usize::from(player_id.min(1));
+        self.samples
+            .iter()
+            .rev()
+            .find(|&&(sample_ms, _)| sample_ms <= time_ms)
+            .or_else(|| self.samples.front())
+            .and_then(|(_, paddles)| paddles[slot])
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+/// How far to rewind for an input stamped `input_server_ms`, bounded to the
+/// window and to the sender's round trip `rtt_ms` as timed by the server: a
+/// player cannot have seen further back than that. Unstamped (0) or
+/// future-dated inputs, and players not yet timed, get no compensation.
+pub fn rewind_ms(now_ms: u64, input_server_ms: u64, rtt_ms: u16) -> u64 {
+    if input_server_ms == 0 || input_server_ms > now_ms {
+        return 0;
+    }
+    (now_ms - input_server_ms)
+        .min(u64::from(rtt_ms))
+        .min(MAX_REWIND_MS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_y_at_picks_sample_at_or_before_time() {
+        let mut history = PaddleHistory::new();
+        history.record(1000, [Some(10.0), Some(12.0)]);
+        history.record(1016, [Some(11.0), Some(12.0)]);
+        history.record(1032, [Some(12.0), None]);
+
+        assert_eq!(history.y_at(0, 1020), Some(11.0));
+        assert_eq!(history.y_at(0, 1032), Some(12.0));
+        assert_eq!(history.y_at(1, 1032), None);
+        // Older than anything kept: the oldest sample is the best answer
+        assert_eq!(history.y_at(0, 500), Some(10.0));
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut history = PaddleHistory::new();
+        for i in 0..100 {
+            history.record(1000 + i * 16, [Some(i as f32), None]);
+        }
+        let oldest_kept = history.samples.front().unwrap().0;
+        assert!(1000 + 99 * 16 - oldest_kept <= MAX_REWIND_MS + HISTORY_MARGIN_MS);
+    }
+
+    #[test]
+    fn test_rewind_is_bounded() {
+        assert_eq!(rewind_ms(5000, 4920, 100), 80);
+        assert_eq!(rewind_ms(5000, 1000, 400), MAX_REWIND_MS);
+        assert_eq!(rewind_ms(5000, 0, 100), 0);
+        assert_eq!(rewind_ms(5000, 5100, 100), 0);
+    }
+
+    #[test]
+    fn test_rewind_never_exceeds_rtt() {
+        assert_eq!(rewind_ms(5000, 4900, 40), 40);
+        assert_eq!(rewind_ms(5000, 4900, 0), 0);
+    }
+}
@@ -1,39 +1,203 @@
 #![allow(unknown_lints)]
 #![allow(clippy::manual_is_multiple_of)]
-use proto::*;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::time::Duration;
 use worker::*;
 
-mod game_state;
-use game_state::{GameState, WasmEnv};
+pub mod bot;
+pub mod game_state;
+pub mod host;
+mod input_validator;
+mod lag_compensation;
+mod match_fsm;
+pub mod match_history;
+pub use match_history::HistoryDO;
+pub mod matchmaking;
+pub mod metrics;
+pub mod params;
+pub use matchmaking::MatchmakerDO;
+pub mod tournament;
+pub use tournament::TournamentDO;
+pub mod rate_limit;
+pub mod rating;
+mod snapshot_rate;
+mod storage;
+use game_state::{GameClient, GameState, PersistedMatch, SpectatorInfo, WasmEnv};
+use host::{SocketRole, PLAY_ALARM_MS};
+use rate_limit::{ConnectionLimiter, RateLimits};
 
 #[cfg(test)]
 mod tests;
 
+/// Storage key for the persisted match
+const MATCH_STORAGE_KEY: &str = "match";
+
+/// Wait before offering a result the history or tournament failed to take again
+const RESULT_RETRY_MS: u64 = 5_000;
+
+/// Generate a random 5-character match code (A-Z, 0-9)
+pub fn generate_match_code() -> String {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    let mut bytes = [0u8; 5];
+    if let Err(e) = getrandom::getrandom(&mut bytes) {
+        console_error!("No secure randomness for match code: {e}");
+        bytes.copy_from_slice(&Date::now().as_millis().to_le_bytes()[..5]);
+    }
+    bytes
+        .iter()
+        .map(|&b| CHARS[usize::from(b) % CHARS.len()] as char)
+        .collect()
+}
+
+/// Make the match `code` report its result to a tournament.
+/// Returns false if the match already has players (code in use).
+pub async fn link_match_to_tournament(
+    matches: &ObjectNamespace,
+    code: &str,
+    tournament_id: &str,
+) -> Result<bool> {
+    let stub = matches.get_by_name(code)?;
+    let mut init = RequestInit::new();
+    init.with_method(Method::Post)
+        .with_body(Some(wasm_bindgen::JsValue::from_str(tournament_id)));
+    let resp = stub
+        .fetch_with_request(Request::new_with_init("https://match/tournament", &init)?)
+        .await?;
+    Ok(resp.status_code() == 200)
+}
+
+/// Choose when the match `code` fills an empty slot with the AI.
+/// Returns false if the match already has players (code in use).
+pub async fn set_match_bot_fill(
+    matches: &ObjectNamespace,
+    code: &str,
+    bot_fill: bot::BotFill,
+) -> Result<bool> {
+    let stub = matches.get_by_name(code)?;
+    let body = serde_json::to_string(&bot_fill)?;
+    let mut init = RequestInit::new();
+    init.with_method(Method::Post)
+        .with_body(Some(wasm_bindgen::JsValue::from_str(&body)));
+    let resp = stub
+        .fetch_with_request(Request::new_with_init("https://match/bot", &init)?)
+        .await?;
+    Ok(resp.status_code() == 200)
+}
+
+/// Store settings in the match `code` before anyone joins.
+/// Returns false if the match already has players (code in use).
+pub async fn apply_match_settings(
+    matches: &ObjectNamespace,
+    code: &str,
+    settings: &game_core::MatchSettings,
+) -> Result<bool> {
+    let stub = matches.get_by_name(code)?;
+    let body = serde_json::to_string(settings)?;
+    let mut init = RequestInit::new();
+    init.with_method(Method::Post)
+        .with_body(Some(wasm_bindgen::JsValue::from_str(&body)));
+    let resp = stub
+        .fetch_with_request(Request::new_with_init("https://match/settings", &init)?)
+        .await?;
+    match resp.status_code() {
+        200 => Ok(true),
+        409 => Ok(false),
+        status => Err(Error::RustError(format!(
+            "Match {code} rejected settings with status {status}"
+        ))),
+    }
+}
+
 #[durable_object]
 pub struct MatchDO {
     state: State,
-    #[allow(dead_code)]
     env: Env,
     game_state: RefCell<GameState>,
+    restored: Cell<bool>, // Storage checked for a saved match since (re)start
+    result_retry_at_ms: Cell<u64>, // No resending a failed result before this
+    limiters: RefCell<Vec<(WebSocket, ConnectionLimiter)>>, // Per socket; hibernation starts them afresh
+    rate_limits: RateLimits,                                // From the RATE_LIMIT_* vars
 }
 
 impl DurableObject for MatchDO {
     fn new(state: State, env: Env) -> Self {
+        let rate_limits = RateLimits::from_vars(|name| env.var(name).ok().map(|v| v.to_string()))
+            .unwrap_or_else(|e| {
+                console_error!("DO: Ignoring rate limit settings: {e}");
+                RateLimits::default()
+            });
         Self {
             state,
             env,
-            game_state: RefCell::new(GameState::new(Box::new(WasmEnv))),
+            game_state: RefCell::new(GameState::new(Box::new(WasmEnv::new()))),
+            restored: Cell::new(false),
+            result_retry_at_ms: Cell::new(0),
+            limiters: RefCell::new(Vec::new()),
+            rate_limits,
         }
     }
 
-    async fn fetch(&self, req: Request) -> Result<Response> {
+    async fn fetch(&self, mut req: Request) -> Result<Response> {
+        self.restore_from_storage().await?;
         console_log!("DO: Received request, method: {:?}", req.method());
         if let Ok(url) = req.url() {
             console_log!("DO: Request URL: {}", url);
         }
 
+        // Settings from /create, stored before anyone joins
+        if req.method() == Method::Post && req.path() == "/settings" {
+            let settings: game_core::MatchSettings = req.json().await?;
+            let applied = self.game_state.borrow_mut().apply_settings(settings);
+            return match applied {
+                Ok(()) => {
+                    self.persist().await?;
+                    Response::ok("Settings applied")
+                }
+                Err(e) => Response::error(format!("Invalid settings: {e}"), 409),
+            };
+        }
+
+        // Tournament that this match reports its result to, linked before anyone joins
+        if req.method() == Method::Post && req.path() == "/tournament" {
+            let tournament_id = req.text().await?;
+            let linked = self.game_state.borrow_mut().link_tournament(tournament_id);
+            return match linked {
+                Ok(()) => {
+                    self.persist().await?;
+                    Response::ok("Linked to tournament")
+                }
+                Err(e) => Response::error(format!("Cannot link tournament: {e}"), 409),
+            };
+        }
+
+        // Whether an AI fills the slot if nobody joins, chosen before anyone joins
+        if req.method() == Method::Post && req.path() == "/bot" {
+            let bot_fill: bot::BotFill = req.json().await?;
+            let chosen = self.game_state.borrow_mut().set_bot_fill(bot_fill);
+            return match chosen {
+                Ok(()) => {
+                    self.persist().await?;
+                    Response::ok("Bot fill chosen")
+                }
+                Err(e) => Response::error(format!("Cannot choose bot fill: {e}"), 409),
+            };
+        }
+
+        // Introspection and admin actions (the lobby authenticates admin requests)
+        match (req.method(), req.path().as_str()) {
+            (Method::Get, "/status") => {
+                let status = self.game_state.borrow().status();
+                return Response::from_json(&status);
+            }
+            (Method::Post, path) if path.starts_with("/admin/") => {
+                let url = req.url()?;
+                return self
+                    .handle_admin(path.trim_start_matches("/admin/"), &url)
+                    .await;
+            }
+            _ => {}
+        }
+
         // Check for WebSocket upgrade
         let upgrade_header = req.headers().get("Upgrade");
         console_log!("DO: Upgrade header result: {:?}", upgrade_header);
@@ -85,27 +249,42 @@ impl DurableObject for MatchDO {
         ws: WebSocket,
         message: durable::WebSocketIncomingMessage,
     ) -> Result<()> {
+        self.restore_from_storage().await?;
         match message {
             durable::WebSocketIncomingMessage::String(_text) => {
                 // Ignore text messages
             }
-            durable::WebSocketIncomingMessage::Binary(bytes) => match C2S::from_bytes(&bytes) {
-                Ok(c2s_msg) => {
-                    if let Err(e) = Self::handle_c2s_message(self, ws, c2s_msg).await {
-                        console_error!("Error handling C2S message: {e:?}");
-                    }
-                }
-                Err(e) => {
-                    console_error!("Failed to parse C2S message: {e:?}");
+            durable::WebSocketIncomingMessage::Binary(bytes) => {
+                let role = Self::socket_role(&ws);
+                let handled = {
+                    let mut limiters = self.limiters.borrow_mut();
+                    let index = match limiters.iter().position(|(socket, _)| *socket == ws) {
+                        Some(index) => index,
+                        None => {
+                            limiters.push((ws.clone(), ConnectionLimiter::new(self.rate_limits)));
+                            limiters.len() - 1
+                        }
+                    };
+                    host::handle_bytes(
+                        &mut self.game_state.borrow_mut(),
+                        &ws,
+                        role,
+                        &mut limiters[index].1,
+                        &bytes,
+                    )
+                };
+                if let Err(e) = self.apply_handled(&ws, handled).await {
+                    console_error!("Error handling C2S message: {e:?}");
                 }
-            },
+            }
         }
-        Ok(())
+        self.record_finished_match().await;
+        self.persist().await
     }
 
     async fn websocket_close(
         &self,
-        _ws: WebSocket,
+        ws: WebSocket,
         code: usize,
         reason: String,
         _was_clean: bool,
@@ -115,198 +294,300 @@ impl DurableObject for MatchDO {
             code,
             reason
         );
+        self.restore_from_storage().await?;
+        self.forget_limiter(&ws);
 
-        let mut gs = self.game_state.borrow_mut();
-
-        // workaround for missing WS ID
-        let player_id_to_remove = gs.clients.keys().next().copied();
-
-        if let Some(player_id) = player_id_to_remove {
-            gs.env
-                .log(format!("DO: Removing player {player_id} after close event"));
-            gs.remove_player(player_id);
+        {
+            let mut gs = self.game_state.borrow_mut();
+            host::handle_close(&mut gs, Self::socket_role(&ws), "close event");
+            gs.env.log(format!(
+                "DO: Remaining clients after cleanup: {}",
+                gs.clients.len()
+            ));
         }
-
-        gs.env.log(format!(
-            "DO: Remaining clients after cleanup: {}",
-            gs.clients.len()
-        ));
-        Ok(())
+        self.record_finished_match().await;
+        self.persist().await
     }
 
-    async fn websocket_error(&self, _ws: WebSocket, error: Error) -> Result<()> {
+    async fn websocket_error(&self, ws: WebSocket, error: Error) -> Result<()> {
         console_error!("DO: WebSocket error: {:?}", error);
-        Ok(())
+        self.restore_from_storage().await?;
+        self.forget_limiter(&ws);
+
+        // An errored socket is unusable; free its slot the same way a close would
+        host::handle_close(
+            &mut self.game_state.borrow_mut(),
+            Self::socket_role(&ws),
+            "socket error",
+        );
+        self.record_finished_match().await;
+        self.persist().await
     }
 
-    #[allow(clippy::await_holding_refcell_ref)] // We drop the RefCell borrow before await
     async fn alarm(&self) -> Result<Response> {
-        use game_state::MatchState;
-
-        let mut gs = self.game_state.borrow_mut();
+        self.restore_from_storage().await?;
+        let next_alarm_ms = host::run_alarm(&mut self.game_state.borrow_mut());
+        // Even on the run that stops the loop: kicking the last players can end the match
+        self.record_finished_match().await;
+        self.persist().await?;
+        let next_alarm_ms = match next_alarm_ms {
+            Some(next_alarm_ms) => next_alarm_ms,
+            // Keep waking up until the result is delivered
+            None if self.game_state.borrow().finished_result.is_some() => RESULT_RETRY_MS,
+            None => return Response::ok("No clients, stopping alarm loop"),
+        };
 
-        // Check for idle clients and disconnect them (1 minute timeout)
-        let now_ms = gs.env.now();
-        let now_seconds = now_ms / 1000;
-        let idle_timeout_seconds = 120; // 2 minutes
-        let mut clients_to_remove = Vec::new();
-
-        for (player_id, client_info) in gs.clients.iter() {
-            let elapsed = now_seconds.saturating_sub(client_info.last_activity);
-            if elapsed > idle_timeout_seconds {
-                gs.env.log(format!(
-                    "DO: Client {} idle for {}s (now: {}, last: {}), disconnecting",
-                    player_id, elapsed, now_seconds, client_info.last_activity
-                ));
-                clients_to_remove.push(*player_id);
-            }
-        }
+        // Schedule next alarm
+        self.state
+            .storage()
+            .set_alarm(Duration::from_millis(next_alarm_ms))
+            .await?;
 
-        // Remove idle clients
-        for player_id in clients_to_remove {
-            gs.remove_player(player_id);
-        }
+        Response::ok("Alarm processed")
+    }
+}
 
-        // Check if we still have clients after cleanup
-        let has_clients = !gs.clients.is_empty();
-        if !has_clients {
-            gs.env
-                .log("DO: No clients remaining, stopping alarm loop".to_string());
-            drop(gs);
-            return Response::ok("No clients, stopping alarm loop");
+impl MatchDO {
+    /// Admin action: `end` closes every connection and discards the match,
+    /// `reset` restarts it from 0-0, `kick?player=N` removes a player
+    async fn handle_admin(&self, action: &str, url: &Url) -> Result<Response> {
+        match action {
+            "end" => {
+                {
+                    let mut gs = self.game_state.borrow_mut();
+                    gs.env.log("DO: Match ended by admin".to_string());
+                    gs.close_all("Match ended by admin");
+                    *gs = GameState::new(Box::new(WasmEnv::new()));
+                }
+                let storage = self.state.storage();
+                storage.delete(MATCH_STORAGE_KEY).await?;
+                storage.delete_alarm().await?;
+                Response::ok("Match ended")
+            }
+            "reset" => {
+                let reset = self.game_state.borrow_mut().reset_match();
+                if !reset {
+                    return Response::error("No players to reset the match for", 409);
+                }
+                self.persist().await?;
+                self.state
+                    .storage()
+                    .set_alarm(Duration::from_millis(PLAY_ALARM_MS))
+                    .await?;
+                Response::ok("Match reset")
+            }
+            "kick" => {
+                let Some(player_id) = url
+                    .query_pairs()
+                    .find(|(key, _)| key == "player")
+                    .and_then(|(_, value)| value.parse::<u8>().ok())
+                else {
+                    return Response::error("Missing or invalid player", 400);
+                };
+                let kicked = self.game_state.borrow_mut().admin_kick(player_id);
+                if !kicked {
+                    return Response::error(format!("Player {player_id} is not connected"), 404);
+                }
+                self.record_finished_match().await;
+                self.persist().await?;
+                Response::ok(format!("Player {player_id} kicked"))
+            }
+            _ => Response::error("Unknown admin action", 404),
         }
+    }
 
-        // Handle based on current match state
-        let current_state = gs.match_state;
-        let next_alarm_ms = match current_state {
-            MatchState::Waiting => {
-                // Just keep alarm running at low frequency for idle checks
-                500
-            }
-            MatchState::Countdown => {
-                // Tick countdown every second
-                gs.tick_countdown();
-                1000
+    /// Point every player socket at the other slot after sides were swapped
+    fn rebind_swapped_players(&self) {
+        for ws in self.state.get_websockets() {
+            if let Some(role @ SocketRole::Player { .. }) = Self::socket_role(&ws) {
+                if let Err(e) = ws.serialize_attachment(role.swapped()) {
+                    console_error!("DO: Failed to rebind player socket: {e:?}");
+                }
             }
-            MatchState::Playing => {
-                // Run game simulation at 60 Hz
-                let tick_interval_ms = 16;
+        }
+    }
 
-                let elapsed_ms = now_ms.saturating_sub(gs.last_tick_time);
-                gs.last_tick_time = now_ms;
+    /// Drop the rate limiting state of a socket that is gone
+    fn forget_limiter(&self, ws: &WebSocket) {
+        self.limiters
+            .borrow_mut()
+            .retain(|(socket, _)| socket != ws);
+    }
 
-                // Add to accumulator, capped to avoid large jumps
-                gs.accumulator += elapsed_ms.min(100) as f32;
+    /// Resolve who is on the other end of a socket from its attachment
+    fn socket_role(ws: &WebSocket) -> Option<SocketRole> {
+        ws.deserialize_attachment::<SocketRole>().ok().flatten()
+    }
 
-                let mut steps_run = 0;
-                const MAX_STEPS: u32 = 10;
+    /// Rebuild the match from storage the first time this instance handles
+    /// an event, rebinding sockets that survived hibernation. A failed read
+    /// is returned and retried by the next event.
+    async fn restore_from_storage(&self) -> Result<()> {
+        if self.restored.get() {
+            return Ok(());
+        }
 
-                while gs.accumulator >= tick_interval_ms as f32 && steps_run < MAX_STEPS {
-                    gs.step();
-                    gs.accumulator -= tick_interval_ms as f32;
-                    steps_run += 1;
-                }
+        let saved =
+            storage::get_optional::<Vec<u8>>(&self.state.storage(), MATCH_STORAGE_KEY).await?;
+        self.restored.set(true);
+        let Some(bytes) = saved else {
+            return Ok(()); // Fresh object, nothing saved yet
+        };
+        let saved: PersistedMatch = match postcard::from_bytes(&bytes) {
+            Ok(saved) => saved,
+            Err(e) => {
+                console_error!("DO: Discarding unreadable saved match: {e:?}");
+                return Ok(());
+            }
+        };
 
-                if steps_run > 1 && gs.tick % 60 == 0 {
-                    gs.env.log(format!(
-                        "DO: Catching up, ran {steps_run} steps in one alarm"
-                    ));
+        let mut gs = self.game_state.borrow_mut();
+        let mut players: Vec<(u8, Box<dyn GameClient>)> = Vec::new();
+        for ws in self.state.get_websockets() {
+            match Self::socket_role(&ws) {
+                Some(SocketRole::Player { player_id, .. }) => {
+                    players.push((player_id, Box::new(ws)))
                 }
-
-                // Broadcast state regularly
-                if gs.tick == 1 || gs.tick % 3 == 0 {
-                    gs.broadcast_state();
+                Some(SocketRole::Spectator {
+                    spectator_id,
+                    delayed,
+                }) => {
+                    gs.spectators.insert(
+                        spectator_id,
+                        SpectatorInfo {
+                            client: Box::new(ws),
+                            delayed,
+                            joined_ms: 0, // The queue did not survive eviction
+                            catch_up: Vec::new(),
+                        },
+                    );
+                    gs.next_spectator_id = gs.next_spectator_id.max(spectator_id + 1);
                 }
+                None => {}
+            }
+        }
+        gs.restore(saved, players);
+        Ok(())
+    }
 
-                tick_interval_ms
+    /// Hand a just-finished match to its tournament, if it has one, and to
+    /// the history object. Failures are logged rather than returned so an
+    /// outage elsewhere never stalls the match; the result is kept and offered
+    /// again after RESULT_RETRY_MS. The tournament goes first as it ignores a
+    /// result it already has, while history would count the ratings twice.
+    async fn record_finished_match(&self) {
+        let (result, tournament_id) = {
+            let mut gs = self.game_state.borrow_mut();
+            if gs.env.now() < self.result_retry_at_ms.get() {
+                return;
             }
-            MatchState::GameOver => {
-                // Low frequency, just for cleanup
-                500
+            let Some(result) = gs.take_finished_result() else {
+                return;
+            };
+            (result, gs.tournament_id.clone())
+        };
+        let body = match serde_json::to_string(&result) {
+            Ok(body) => body,
+            Err(e) => {
+                console_error!("DO: Failed to encode result {}: {e}", result.id);
+                return;
             }
         };
 
-        drop(gs);
+        if let Some(tournament_id) = tournament_id {
+            let report = Self::post_json(
+                &self.env,
+                "TOURNAMENT",
+                &tournament_id,
+                "https://tournament/result",
+                &body,
+            );
+            if let Err(e) = Self::check_delivered(report.await) {
+                console_error!("DO: Failed to report result to tournament {tournament_id}: {e:?}");
+                self.retry_result(result);
+                return;
+            }
+        }
 
-        // Schedule next alarm
-        self.state
-            .storage()
-            .set_alarm(Duration::from_millis(next_alarm_ms))
-            .await?;
+        let history = Self::post_json(
+            &self.env,
+            "HISTORY",
+            match_history::HISTORY_OBJECT_NAME,
+            "https://history/record",
+            &body,
+        );
+        match Self::check_delivered(history.await) {
+            Ok(()) => console_log!("DO: Recorded result {}", result.id),
+            Err(e) => {
+                console_error!("DO: Failed to record result {}: {e:?}", result.id);
+                self.retry_result(result);
+            }
+        }
+    }
 
-        Response::ok("Alarm processed")
+    /// Treat an error status from another object like a failed request
+    fn check_delivered(response: Result<Response>) -> Result<()> {
+        let status = response?.status_code();
+        if (200..300).contains(&status) {
+            Ok(())
+        } else {
+            Err(Error::RustError(format!("status {status}")))
+        }
     }
-}
 
-impl MatchDO {
-    /// Handle incoming C2S message
-    async fn handle_c2s_message(&self, ws: WebSocket, msg: C2S) -> Result<()> {
-        let should_start_alarm = {
-            let mut gs = self.game_state.borrow_mut();
-            match msg {
-                C2S::Join { code: _, .. } => {
-                    // We need to clone WS here because add_player takes ownership
-                    if let Some((player_id, was_empty)) = gs.add_player(Box::new(ws.clone())) {
-                        gs.env.log(format!(
-                            "DO: Player {player_id} joining (clients was empty: {was_empty})"
-                        ));
-                        // Send Welcome message
-                        let welcome = S2C::Welcome { player_id };
-                        if let Ok(bytes) = welcome.to_bytes() {
-                            let _ = ws.send_with_bytes(&bytes);
-                        }
+    /// Keep an undelivered result to offer again later
+    fn retry_result(&self, result: match_history::MatchResult) {
+        let mut gs = self.game_state.borrow_mut();
+        self.result_retry_at_ms.set(gs.env.now() + RESULT_RETRY_MS);
+        gs.finished_result = Some(result);
+    }
 
-                        // Send initial state
-                        let state_msg = gs.generate_state_message();
-                        if let Ok(bytes) = state_msg.to_bytes() {
-                            // Broadcast to all
-                            for client_info in gs.clients.values() {
-                                let _ = client_info.client.send_bytes(&bytes);
-                            }
-                        }
-                        Some(was_empty)
-                    } else {
-                        gs.env
-                            .log("DO: Match full, rejecting new player".to_string());
-                        None
-                    }
-                }
-                C2S::Input {
-                    player_id,
-                    y,
-                    seq: _,
-                } => {
-                    gs.handle_input(player_id, y);
-                    None
-                }
-                C2S::Restart => {
-                    gs.restart_match();
-                    None
-                }
-                C2S::Ping { t_ms } => {
-                    // Update activity for all (workaround)
-                    let now = gs.env.now() / 1000;
-                    for client_info in gs.clients.values_mut() {
-                        client_info.last_activity = now;
-                    }
+    /// POST a JSON body to the named Durable Object in a namespace
+    async fn post_json(
+        env: &Env,
+        binding: &str,
+        name: &str,
+        url: &str,
+        body: &str,
+    ) -> Result<Response> {
+        let stub = env.durable_object(binding)?.get_by_name(name)?;
+        let mut init = RequestInit::new();
+        init.with_method(Method::Post)
+            .with_body(Some(wasm_bindgen::JsValue::from_str(body)));
+        stub.fetch_with_request(Request::new_with_init(url, &init)?)
+            .await
+    }
 
-                    let pong = S2C::Pong { t_ms };
-                    if let Ok(bytes) = pong.to_bytes() {
-                        let _ = ws.send_with_bytes(&bytes);
-                    }
-                    None
-                }
-            }
-        };
+    /// Save essential match state if it changed since the last save
+    async fn persist(&self) -> Result<()> {
+        let saved = self.game_state.borrow_mut().take_persist();
+        if let Some(saved) = saved {
+            let bytes = postcard::to_allocvec(&saved)
+                .map_err(|e| Error::RustError(format!("Failed to encode match: {e}")))?;
+            self.state.storage().put(MATCH_STORAGE_KEY, bytes).await?;
+        }
+        Ok(())
+    }
 
-        // Start game loop if this was the first player
-        if let Some(true) = should_start_alarm {
+    /// Carry out what handling a message asked of the host: bind the socket,
+    /// follow a side swap and start the alarm loop
+    async fn apply_handled(&self, ws: &WebSocket, handled: host::Handled) -> Result<()> {
+        // Bind the socket to its role so later messages and close events
+        // resolve the right slot
+        if let Some(role) = handled.bind {
+            if let Err(e) = ws.serialize_attachment(role) {
+                console_error!("DO: Failed to tag socket: {e:?}");
+            }
+        }
+        if handled.sides_swapped {
+            self.rebind_swapped_players();
+        }
+        if handled.start_loop {
             self.state
                 .storage()
-                .set_alarm(Duration::from_millis(16)) // 60 Hz
+                .set_alarm(Duration::from_millis(PLAY_ALARM_MS))
                 .await?;
         }
-
         Ok(())
     }
 }
@@ -0,0 +1,213 @@
+//! Server match lifecycle as an explicit transition table
+//!
+//! `MatchState::next` is the only place that says which lifecycle changes
+//! are allowed. `GameState::transition` applies it and logs anything the
+//! table rejects, so a bug shows up as a log line instead of a match stuck
+//! in a state nothing else expects.
+
+/// Server-side match lifecycle state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum MatchState {
+    /// Waiting for players to join
+    Waiting,
+    /// Both players connected, counting down
+    Countdown,
+    /// Game in progress
+    Playing,
+    /// A player dropped mid-match; play is frozen until they rejoin or time runs out
+    AwaitingReconnect,
+    /// A player paused; play is frozen until everyone is ready or time runs out
+    Paused,
+    /// Game ended
+    GameOver,
+    /// Everyone left before the game ended; the next join starts afresh
+    Abandoned,
+}
+
+/// Things that move a match between states
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchAction {
+    /// Second player joined
+    PlayersReady,
+    CountdownFinished,
+    PauseRequested,
+    /// Pause over and its resume countdown done
+    Resumed,
+    /// A player's connection dropped and their slot is held
+    PlayerDropped,
+    /// The held slot was reclaimed
+    PlayerRejoined,
+    /// A player left and someone is still connected
+    PlayerLeft,
+    /// The last connected player left
+    AllLeft,
+    /// Someone reached the winning score
+    WinnerDecided,
+    /// The match was awarded to the player still there
+    Forfeited,
+    RematchAgreed,
+    /// Admin reset; play starts again from Waiting
+    Reset,
+    /// Someone joined an abandoned match
+    Reopened,
+}
+
+impl MatchState {
+    /// State after `action`, or None if the action is not valid here
+    pub fn next(self, action: MatchAction) -> Option<MatchState> {
+        use MatchAction as A;
+        use MatchState as S;
+
+        match (self, action) {
+            // Admin reset is allowed from anywhere
+            (_, A::Reset) => Some(S::Waiting),
+
+            // From Waiting
+            (S::Waiting, A::PlayersReady) => Some(S::Countdown),
+            (S::Waiting, A::PlayerLeft | A::AllLeft) => Some(S::Waiting),
+
+            // From Countdown
+            (S::Countdown, A::CountdownFinished) => Some(S::Playing),
+            (S::Countdown, A::PlayerLeft | A::AllLeft) => Some(S::Waiting),
+
+            // From Playing
+            (S::Playing, A::PauseRequested) => Some(S::Paused),
+            (S::Playing, A::PlayerDropped) => Some(S::AwaitingReconnect),
+            (S::Playing, A::WinnerDecided | A::Forfeited) => Some(S::GameOver),
+            (S::Playing, A::AllLeft) => Some(S::Abandoned),
+
+            // From Paused
+            (S::Paused, A::Resumed) => Some(S::Playing),
+            (S::Paused, A::PlayerDropped) => Some(S::AwaitingReconnect),
+            (S::Paused, A::Forfeited) => Some(S::GameOver),
+            (S::Paused, A::AllLeft) => Some(S::Abandoned),
+
+            // From AwaitingReconnect
+            (S::AwaitingReconnect, A::PlayerRejoined) => Some(S::Playing),
+            (S::AwaitingReconnect, A::Forfeited) => Some(S::GameOver),
+            (S::AwaitingReconnect, A::AllLeft) => Some(S::Abandoned),
+
+            // From GameOver
+            (S::GameOver, A::RematchAgreed) => Some(S::Countdown),
+            (S::GameOver, A::PlayerLeft | A::AllLeft) => Some(S::Waiting),
+
+            // From Abandoned
+            (S::Abandoned, A::Reopened) => Some(S::Waiting),
+
+            _ => None,
+        }
+    }
+
+    /// True while a game is underway (including frozen for a pause or reconnect)
+    pub fn in_progress(self) -> bool {
+        matches!(
+            self,
+            MatchState::Playing | MatchState::Paused | MatchState::AwaitingReconnect
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MatchAction as A;
+    use super::MatchState as S;
+    use super::*;
+
+    const STATES: [MatchState; 7] = [
+        S::Waiting,
+        S::Countdown,
+        S::Playing,
+        S::AwaitingReconnect,
+        S::Paused,
+        S::GameOver,
+        S::Abandoned,
+    ];
+
+    const ACTIONS: [MatchAction; 13] = [
+        A::PlayersReady,
+        A::CountdownFinished,
+        A::PauseRequested,
+        A::Resumed,
+        A::PlayerDropped,
+        A::PlayerRejoined,
+        A::PlayerLeft,
+        A::AllLeft,
+        A::WinnerDecided,
+        A::Forfeited,
+        A::RematchAgreed,
+        A::Reset,
+        A::Reopened,
+    ];
+
+    /// Every legal transition; any pair not listed must be rejected
+    const LEGAL: &[(MatchState, MatchAction, MatchState)] = &[
+        (S::Waiting, A::PlayersReady, S::Countdown),
+        (S::Waiting, A::PlayerLeft, S::Waiting),
+        (S::Waiting, A::AllLeft, S::Waiting),
+        (S::Waiting, A::Reset, S::Waiting),
+        (S::Countdown, A::CountdownFinished, S::Playing),
+        (S::Countdown, A::PlayerLeft, S::Waiting),
+        (S::Countdown, A::AllLeft, S::Waiting),
+        (S::Countdown, A::Reset, S::Waiting),
+        (S::Playing, A::PauseRequested, S::Paused),
+        (S::Playing, A::PlayerDropped, S::AwaitingReconnect),
+        (S::Playing, A::WinnerDecided, S::GameOver),
+        (S::Playing, A::Forfeited, S::GameOver),
+        (S::Playing, A::AllLeft, S::Abandoned),
+        (S::Playing, A::Reset, S::Waiting),
+        (S::Paused, A::Resumed, S::Playing),
+        (S::Paused, A::PlayerDropped, S::AwaitingReconnect),
+        (S::Paused, A::Forfeited, S::GameOver),
+        (S::Paused, A::AllLeft, S::Abandoned),
+        (S::Paused, A::Reset, S::Waiting),
+        (S::AwaitingReconnect, A::PlayerRejoined, S::Playing),
+        (S::AwaitingReconnect, A::Forfeited, S::GameOver),
+        (S::AwaitingReconnect, A::AllLeft, S::Abandoned),
+        (S::AwaitingReconnect, A::Reset, S::Waiting),
+        (S::GameOver, A::RematchAgreed, S::Countdown),
+        (S::GameOver, A::PlayerLeft, S::Waiting),
+        (S::GameOver, A::AllLeft, S::Waiting),
+        (S::GameOver, A::Reset, S::Waiting),
+        (S::Abandoned, A::Reopened, S::Waiting),
+        (S::Abandoned, A::Reset, S::Waiting),
+    ];
+
+    #[test]
+    fn test_every_state_action_pair() {
+        for state in STATES {
+            for action in ACTIONS {
+                let expected = LEGAL
+                    .iter()
+                    .find(|(from, on, _)| *from == state && *on == action)
+                    .map(|(_, _, to)| *to);
+                assert_eq!(state.next(action), expected, "{state:?} + {action:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_state_is_reachable_and_left() {
+        for state in STATES {
+            assert!(
+                state == S::Waiting || LEGAL.iter().any(|(_, _, to)| *to == state),
+                "{state:?} is unreachable"
+            );
+            assert!(
+                LEGAL
+                    .iter()
+                    .any(|(from, _, to)| *from == state && *to != state),
+                "{state:?} is a dead end"
+            );
+        }
+    }
+
+    #[test]
+    fn test_frozen_play_cannot_score() {
+        for state in [S::Paused, S::AwaitingReconnect] {
+            assert!(state.in_progress());
+            assert_eq!(state.next(A::WinnerDecided), None);
+            assert_eq!(state.next(A::PauseRequested), None);
+        }
+        assert!(!S::GameOver.in_progress());
+    }
+}
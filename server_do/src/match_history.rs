@@ -0,0 +1,340 @@
+//! Finished match results and where they are kept
+//!
+//! `MatchDO` builds a `MatchResult` when a match ends and hands it to the
+//! `HistoryDO` singleton, which stores it through the `MatchHistory` trait.
+//! The lobby worker queries the same object for recent matches.
+
+use crate::params;
+use crate::rating;
+use crate::storage::get_optional;
+use game_core::Config;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use worker::*;
+
+/// Most results returned by one listing
+pub const MAX_RECENT: usize = 50;
+
+/// Name of the single `HistoryDO` instance
+pub const HISTORY_OBJECT_NAME: &str = "global";
+
+/// Storage key prefix for results
+const RESULT_PREFIX: &str = "result:";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerResult {
+    pub player_id: u8, // 0 = left, 1 = right
+    pub score: u8,
+    pub profile_id: Option<String>, // Persistent id for ratings (None = unrated)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RallyStats {
+    pub points: u32,        // Rallies played (one per point)
+    pub paddle_hits: u32,   // Returns across the whole match
+    pub longest_rally: u32, // Most returns in a single rally
+}
+
+/// Rules the match was played under
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ruleset {
+    pub win_score: u8,
+    pub ball_speed_initial: f32,
+    pub ball_speed_max: f32,
+    pub paddle_speed: f32,
+}
+
+impl From<&Config> for Ruleset {
+    fn from(config: &Config) -> Self {
+        Self {
+            win_score: config.win_score,
+            ball_speed_initial: config.ball_speed_initial,
+            ball_speed_max: config.ball_speed_max,
+            paddle_speed: config.paddle_speed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchResult {
+    pub id: String, // "<ended_ms>-<code>", sorts chronologically
+    pub code: String,
+    pub players: Vec<PlayerResult>,
+    pub winner: u8,
+    pub forfeit: bool, // Ended because the loser did not come back
+    pub started_ms: u64,
+    pub ended_ms: u64,
+    pub duration_ms: u64,
+    pub rallies: RallyStats,
+    pub ruleset: Ruleset,
+}
+
+impl MatchResult {
+    pub fn make_id(ended_ms: u64, code: &str) -> String {
+        format!("{ended_ms:013}-{code}")
+    }
+
+    /// (winner, loser) profile ids if this match counts towards ratings:
+    /// both players rated and not the same profile
+    pub fn rated_profiles(&self) -> Option<(&str, &str)> {
+        let profile = |player_id: u8| {
+            self.players
+                .iter()
+                .find(|player| player.player_id == player_id)?
+                .profile_id
+                .as_deref()
+        };
+        let winner = profile(self.winner)?;
+        let loser = profile(1 - self.winner.min(1))?;
+        (winner != loser).then_some((winner, loser))
+    }
+}
+
+/// Where finished matches are kept
+// Only used on the single-threaded Workers runtime, so no Send bounds are needed
+#[allow(async_fn_in_trait)]
+pub trait MatchHistory {
+    async fn record(&self, result: &MatchResult) -> Result<()>;
+
+    /// Newest first, at most `limit`
+    async fn recent(&self, limit: usize) -> Result<Vec<MatchResult>>;
+
+    async fn get(&self, id: &str) -> Result<Option<MatchResult>>;
+}
+
+/// History in Durable Object storage
+pub struct DurableHistory {
+    storage: Storage,
+}
+
+impl DurableHistory {
+    pub fn new(storage: Storage) -> Self {
+        Self { storage }
+    }
+}
+
+impl MatchHistory for DurableHistory {
+    async fn record(&self, result: &MatchResult) -> Result<()> {
+        self.storage
+            .put(&format!("{RESULT_PREFIX}{}", result.id), result)
+            .await
+    }
+
+    async fn recent(&self, limit: usize) -> Result<Vec<MatchResult>> {
+        let options = ListOptions::new()
+            .prefix(RESULT_PREFIX)
+            .reverse(true)
+            .limit(limit.min(MAX_RECENT));
+        let map = self.storage.list_with_options(options).await?;
+
+        let mut results = Vec::new();
+        for value in map.values() {
+            let value = value?;
+            results.push(serde_wasm_bindgen::from_value(value)?);
+        }
+        Ok(results)
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<MatchResult>> {
+        get_optional(&self.storage, &format!("{RESULT_PREFIX}{id}")).await
+    }
+}
+
+/// History kept in memory (tests and local runs)
+#[derive(Default)]
+pub struct InMemoryHistory {
+    results: RefCell<BTreeMap<String, MatchResult>>,
+}
+
+impl InMemoryHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MatchHistory for InMemoryHistory {
+    async fn record(&self, result: &MatchResult) -> Result<()> {
+        self.results
+            .borrow_mut()
+            .insert(result.id.clone(), result.clone());
+        Ok(())
+    }
+
+    async fn recent(&self, limit: usize) -> Result<Vec<MatchResult>> {
+        Ok(self
+            .results
+            .borrow()
+            .values()
+            .rev()
+            .take(limit.min(MAX_RECENT))
+            .cloned()
+            .collect())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<MatchResult>> {
+        Ok(self.results.borrow().get(id).cloned())
+    }
+}
+
+/// Singleton object holding every finished match
+#[durable_object]
+pub struct HistoryDO {
+    state: State,
+    #[allow(dead_code)]
+    env: Env,
+}
+
+impl DurableObject for HistoryDO {
+    fn new(state: State, env: Env) -> Self {
+        Self { state, env }
+    }
+
+    async fn fetch(&self, mut req: Request) -> Result<Response> {
+        let history = DurableHistory::new(self.state.storage());
+        let path = req.path();
+
+        match (req.method(), path.as_str()) {
+            (Method::Post, "/record") => {
+                let result: MatchResult = req.json().await?;
+                history.record(&result).await?;
+                self.update_ratings(&result).await?;
+                Response::ok("Recorded")
+            }
+            (Method::Get, "/ratings") => {
+                let query: Vec<(String, String)> = req.url()?.query_pairs().into_owned().collect();
+                let limit = params::limit(&query);
+                let storage = self.state.storage();
+                Response::from_json(&rating::leaderboard(&storage, limit).await?)
+            }
+            (Method::Get, path) if path.starts_with("/ratings/") => {
+                let profile_id = path.trim_start_matches("/ratings/");
+                if !rating::is_valid_profile_id(profile_id) {
+                    return Response::error("Invalid profile id", 400);
+                }
+                let storage = self.state.storage();
+                Response::from_json(&rating::load(&storage, profile_id).await?)
+            }
+            (Method::Get, "/recent") => {
+                let query: Vec<(String, String)> = req.url()?.query_pairs().into_owned().collect();
+                let limit = params::limit(&query);
+                Response::from_json(&history.recent(limit).await?)
+            }
+            (Method::Get, path) if path.starts_with("/result/") => {
+                let id = path.trim_start_matches("/result/");
+                match history.get(id).await? {
+                    Some(result) => Response::from_json(&result),
+                    None => Response::error("Match not found", 404),
+                }
+            }
+            _ => Response::error("Not found", 404),
+        }
+    }
+}
+
+impl HistoryDO {
+    /// Apply a finished match to both players' ratings
+    async fn update_ratings(&self, result: &MatchResult) -> Result<()> {
+        let Some((winner_id, loser_id)) = result.rated_profiles() else {
+            return Ok(());
+        };
+        let storage = self.state.storage();
+        let mut winner = rating::load(&storage, winner_id).await?;
+        let mut loser = rating::load(&storage, loser_id).await?;
+
+        (winner.rating, loser.rating) =
+            rating::rate_match(winner.rating, loser.rating, result.forfeit);
+        for player in [&mut winner, &mut loser] {
+            player.games += 1;
+            player.updated_ms = result.ended_ms;
+            rating::save(&storage, player).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    /// The in-memory store never suspends, so one poll completes it
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut cx = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("In-memory history should not suspend"),
+        }
+    }
+
+    fn result(ended_ms: u64, code: &str) -> MatchResult {
+        MatchResult {
+            id: MatchResult::make_id(ended_ms, code),
+            code: code.to_string(),
+            players: vec![
+                PlayerResult {
+                    player_id: 0,
+                    score: 5,
+                    profile_id: None,
+                },
+                PlayerResult {
+                    player_id: 1,
+                    score: 3,
+                    profile_id: None,
+                },
+            ],
+            winner: 0,
+            forfeit: false,
+            started_ms: ended_ms - 60_000,
+            ended_ms,
+            duration_ms: 60_000,
+            rallies: RallyStats::default(),
+            ruleset: Ruleset::from(&Config::new()),
+        }
+    }
+
+    #[test]
+    fn test_ids_sort_chronologically() {
+        assert!(MatchResult::make_id(9_999, "ZZZZZ") < MatchResult::make_id(10_000, "AAAAA"));
+    }
+
+    #[test]
+    fn test_recent_is_newest_first_and_limited() {
+        let history = InMemoryHistory::new();
+        for (ended_ms, code) in [(200_000, "BBBBB"), (100_000, "AAAAA"), (300_000, "CCCCC")] {
+            block_on(history.record(&result(ended_ms, code))).unwrap();
+        }
+
+        let recent = block_on(history.recent(2)).unwrap();
+        let codes: Vec<&str> = recent.iter().map(|r| r.code.as_str()).collect();
+        assert_eq!(codes, ["CCCCC", "BBBBB"]);
+    }
+
+    #[test]
+    fn test_rated_profiles() {
+        let mut result = result(100_000, "ABCDE");
+        assert_eq!(result.rated_profiles(), None, "Unrated players");
+
+        result.players[0].profile_id = Some("left".into());
+        assert_eq!(result.rated_profiles(), None, "Both players need a profile");
+
+        result.players[1].profile_id = Some("right".into());
+        result.winner = 1;
+        assert_eq!(result.rated_profiles(), Some(("right", "left")));
+
+        result.players[1].profile_id = Some("left".into());
+        assert_eq!(result.rated_profiles(), None, "Playing yourself is unrated");
+    }
+
+    #[test]
+    fn test_get_by_id() {
+        let history = InMemoryHistory::new();
+        let saved = result(100_000, "ABCDE");
+        block_on(history.record(&saved)).unwrap();
+
+        assert_eq!(block_on(history.get(&saved.id)).unwrap(), Some(saved));
+        assert_eq!(block_on(history.get("missing")).unwrap(), None);
+    }
+}
@@ -0,0 +1,451 @@
+//! Quick-play matchmaking
+//!
+//! Players who press "quick play" get a ticket from the `MatchmakerDO`
+//! singleton and poll it until they are paired. Pairing prefers opponents
+//! with similar rating and latency, widening the tolerance the longer a
+//! player waits. Each pair gets a fresh `MatchDO` code to join as usual.
+
+use game_core::MatchSettings;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use worker::*;
+
+/// Name of the single `MatchmakerDO` instance
+pub const MATCHMAKER_OBJECT_NAME: &str = "global";
+
+/// Longest a ticket waits for an opponent before timing out
+pub const QUEUE_TIMEOUT_MS: u64 = 60_000;
+
+/// Waiting tickets not polled for this long are dropped (tab closed)
+pub const TICKET_IDLE_MS: u64 = 10_000;
+
+/// How long a matched or timed out ticket's result stays available to poll
+const RESULT_TTL_MS: u64 = 30_000;
+
+/// Rating difference that counts as one unit of mismatch
+const RATING_SPREAD: f64 = 200.0;
+
+/// Latency difference (ms) that counts as one unit of mismatch
+const LATENCY_SPREAD_MS: f64 = 100.0;
+
+/// Mismatch accepted immediately, and how much more is accepted per second waited
+const BASE_TOLERANCE: f64 = 1.0;
+const TOLERANCE_PER_SECOND: f64 = 0.1;
+
+/// Attempts at finding an unused match code for a pair
+const CODE_ATTEMPTS: usize = 3;
+
+/// What a player tells the queue about themselves (both optional)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueueRequest {
+    #[serde(default)]
+    pub rating: Option<f64>,
+    #[serde(default)]
+    pub latency_ms: Option<u16>,
+}
+
+/// Answer to a poll
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TicketStatus {
+    Waiting { waited_ms: u64 },
+    Matched { code: String },
+    TimedOut,
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueEntry {
+    pub ticket: String,
+    pub request: QueueRequest,
+    pub joined_ms: u64,
+    pub last_seen_ms: u64,
+}
+
+impl QueueEntry {
+    /// How badly two players fit; missing data counts as a perfect fit
+    fn mismatch(&self, other: &QueueEntry) -> f64 {
+        let rating = match (self.request.rating, other.request.rating) {
+            (Some(a), Some(b)) => (a - b).abs() / RATING_SPREAD,
+            _ => 0.0,
+        };
+        let latency = match (self.request.latency_ms, other.request.latency_ms) {
+            (Some(a), Some(b)) => f64::from(a.abs_diff(b)) / LATENCY_SPREAD_MS,
+            _ => 0.0,
+        };
+        rating + latency
+    }
+
+    fn tolerance(&self, now_ms: u64) -> f64 {
+        let waited_secs = now_ms.saturating_sub(self.joined_ms) as f64 / 1000.0;
+        BASE_TOLERANCE + waited_secs * TOLERANCE_PER_SECOND
+    }
+}
+
+/// Queue state, independent of the Workers runtime
+#[derive(Debug, Default)]
+pub struct MatchQueue {
+    waiting: Vec<QueueEntry>,                       // Oldest first
+    pairing: Vec<QueueEntry>, // Taken by `take_pairs`, match not yet assigned or requeued
+    finished: HashMap<String, (TicketStatus, u64)>, // ticket -> (result, when it was decided)
+}
+
+impl MatchQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn join(&mut self, ticket: String, request: QueueRequest, now_ms: u64) {
+        self.waiting.push(QueueEntry {
+            ticket,
+            request,
+            joined_ms: now_ms,
+            last_seen_ms: now_ms,
+        });
+    }
+
+    /// Put back a pair that could not be given a match, keeping their place
+    pub fn requeue(&mut self, entries: [QueueEntry; 2]) {
+        self.stop_pairing(&entries);
+        self.waiting.extend(entries);
+        self.waiting.sort_by_key(|entry| entry.joined_ms);
+    }
+
+    pub fn leave(&mut self, ticket: &str) {
+        self.waiting.retain(|entry| entry.ticket != ticket);
+        self.pairing.retain(|entry| entry.ticket != ticket);
+        self.finished.remove(ticket);
+    }
+
+    /// A ticket being paired still reads as waiting until its match is known
+    pub fn poll(&mut self, ticket: &str, now_ms: u64) -> TicketStatus {
+        let mut queued = self.waiting.iter_mut().chain(self.pairing.iter_mut());
+        if let Some(entry) = queued.find(|entry| entry.ticket == ticket) {
+            entry.last_seen_ms = now_ms;
+            return TicketStatus::Waiting {
+                waited_ms: now_ms.saturating_sub(entry.joined_ms),
+            };
+        }
+        self.finished
+            .get(ticket)
+            .map_or(TicketStatus::Unknown, |(status, _)| status.clone())
+    }
+
+    /// Time out long waits, drop abandoned tickets and forget old results
+    pub fn expire(&mut self, now_ms: u64) {
+        let mut still_waiting = Vec::with_capacity(self.waiting.len());
+        for entry in self.waiting.drain(..) {
+            if now_ms.saturating_sub(entry.last_seen_ms) >= TICKET_IDLE_MS {
+                continue;
+            }
+            if now_ms.saturating_sub(entry.joined_ms) >= QUEUE_TIMEOUT_MS {
+                self.finished
+                    .insert(entry.ticket, (TicketStatus::TimedOut, now_ms));
+                continue;
+            }
+            still_waiting.push(entry);
+        }
+        self.waiting = still_waiting;
+        self.finished
+            .retain(|_, (_, decided_ms)| now_ms.saturating_sub(*decided_ms) < RESULT_TTL_MS);
+    }
+
+    /// Remove and return every pair that fits. The oldest ticket picks first,
+    /// taking the closest opponent within either player's tolerance. The pairs
+    /// stay pollable until they are given to `assign` or `requeue`.
+    pub fn take_pairs(&mut self, now_ms: u64) -> Vec<[QueueEntry; 2]> {
+        let mut paired = vec![false; self.waiting.len()];
+        let mut pairs = Vec::new();
+
+        for i in 0..self.waiting.len() {
+            if paired[i] {
+                continue;
+            }
+            let entry = &self.waiting[i];
+            let best = (i + 1..self.waiting.len())
+                .filter(|&j| !paired[j])
+                .map(|j| (j, entry.mismatch(&self.waiting[j])))
+                .filter(|&(j, mismatch)| {
+                    let tolerance = entry
+                        .tolerance(now_ms)
+                        .max(self.waiting[j].tolerance(now_ms));
+                    mismatch <= tolerance
+                })
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((j, _)) = best {
+                paired[i] = true;
+                paired[j] = true;
+                pairs.push((i, j));
+            }
+        }
+
+        let mut taken: Vec<Option<QueueEntry>> = self.waiting.drain(..).map(Some).collect();
+        let result = pairs
+            .into_iter()
+            .filter_map(|(i, j)| Some([taken[i].take()?, taken[j].take()?]))
+            .collect::<Vec<_>>();
+        self.waiting = taken.into_iter().flatten().collect();
+        self.pairing.extend(result.iter().flatten().cloned());
+        result
+    }
+
+    fn stop_pairing(&mut self, entries: &[QueueEntry; 2]) {
+        self.pairing
+            .retain(|entry| entries.iter().all(|paired| paired.ticket != entry.ticket));
+    }
+
+    /// Record the match a pair was given so their next poll picks it up
+    pub fn assign(&mut self, entries: &[QueueEntry; 2], code: &str, now_ms: u64) {
+        self.stop_pairing(entries);
+        for entry in entries {
+            self.finished.insert(
+                entry.ticket.clone(),
+                (
+                    TicketStatus::Matched {
+                        code: code.to_string(),
+                    },
+                    now_ms,
+                ),
+            );
+        }
+    }
+
+    pub fn waiting_count(&self) -> usize {
+        self.waiting.len()
+    }
+}
+
+/// Random ticket id (16 hex characters)
+fn new_ticket() -> String {
+    let mut bytes = [0u8; 8];
+    if let Err(e) = getrandom::getrandom(&mut bytes) {
+        console_error!("Matchmaker: No secure randomness for ticket: {e}");
+        bytes = (Date::now().as_millis()).to_le_bytes();
+    }
+    format!("{:016x}", u64::from_le_bytes(bytes))
+}
+
+/// Singleton object pairing quick-play players
+#[durable_object]
+pub struct MatchmakerDO {
+    #[allow(dead_code)]
+    state: State,
+    env: Env,
+    queue: RefCell<MatchQueue>,
+}
+
+impl DurableObject for MatchmakerDO {
+    fn new(state: State, env: Env) -> Self {
+        Self {
+            state,
+            env,
+            queue: RefCell::new(MatchQueue::new()),
+        }
+    }
+
+    async fn fetch(&self, mut req: Request) -> Result<Response> {
+        let now_ms = Date::now().as_millis();
+        let path = req.path();
+        self.queue.borrow_mut().expire(now_ms);
+
+        match (req.method(), path.as_str()) {
+            (Method::Post, "/join") => {
+                let request: QueueRequest = req.json().await.unwrap_or_default();
+                let ticket = new_ticket();
+                self.queue
+                    .borrow_mut()
+                    .join(ticket.clone(), request, now_ms);
+                self.pair_waiting(now_ms).await;
+                let status = self.queue.borrow_mut().poll(&ticket, now_ms);
+                Response::from_json(&serde_json::json!({ "ticket": ticket, "result": status }))
+            }
+            (Method::Get, path) if path.starts_with("/poll/") => {
+                let ticket = path.trim_start_matches("/poll/");
+                self.pair_waiting(now_ms).await;
+                let status = self.queue.borrow_mut().poll(ticket, now_ms);
+                Response::from_json(&status)
+            }
+            (Method::Post, path) if path.starts_with("/leave/") => {
+                let ticket = path.trim_start_matches("/leave/");
+                self.queue.borrow_mut().leave(ticket);
+                Response::ok("Left queue")
+            }
+            _ => Response::error("Not found", 404),
+        }
+    }
+}
+
+impl MatchmakerDO {
+    /// Pair everyone who fits and give each pair a fresh match
+    async fn pair_waiting(&self, now_ms: u64) {
+        let pairs = self.queue.borrow_mut().take_pairs(now_ms);
+        for pair in pairs {
+            match self.create_match().await {
+                Some(code) => {
+                    console_log!(
+                        "Matchmaker: Paired {} and {} in {code}",
+                        pair[0].ticket,
+                        pair[1].ticket
+                    );
+                    self.queue.borrow_mut().assign(&pair, &code, now_ms);
+                }
+                None => {
+                    console_error!("Matchmaker: Could not create a match, requeueing pair");
+                    self.queue.borrow_mut().requeue(pair);
+                }
+            }
+        }
+    }
+
+    /// Claim an unused match code with default settings
+    async fn create_match(&self) -> Option<String> {
+        let matches = self.env.durable_object("MATCH").ok()?;
+        for _ in 0..CODE_ATTEMPTS {
+            let code = crate::generate_match_code();
+            match crate::apply_match_settings(&matches, &code, &MatchSettings::default()).await {
+                Ok(true) => return Some(code),
+                Ok(false) => continue, // Code already in use
+                Err(e) => {
+                    console_error!("Matchmaker: Failed to set up match {code}: {e:?}");
+                    return None;
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(rating: f64, latency_ms: u16) -> QueueRequest {
+        QueueRequest {
+            rating: Some(rating),
+            latency_ms: Some(latency_ms),
+        }
+    }
+
+    fn tickets(pairs: &[[QueueEntry; 2]]) -> Vec<[&str; 2]> {
+        pairs
+            .iter()
+            .map(|[a, b]| [a.ticket.as_str(), b.ticket.as_str()])
+            .collect()
+    }
+
+    #[test]
+    fn test_pairs_without_data() {
+        let mut queue = MatchQueue::new();
+        queue.join("a".into(), QueueRequest::default(), 0);
+        assert!(queue.take_pairs(0).is_empty());
+
+        queue.join("b".into(), QueueRequest::default(), 100);
+        let pairs = queue.take_pairs(100);
+        assert_eq!(tickets(&pairs), [["a", "b"]]);
+        assert_eq!(queue.waiting_count(), 0);
+    }
+
+    #[test]
+    fn test_prefers_closest_rating_and_latency() {
+        let mut queue = MatchQueue::new();
+        queue.join("a".into(), request(1500.0, 40), 0);
+        queue.join("far".into(), request(1650.0, 120), 0);
+        queue.join("near".into(), request(1520.0, 50), 0);
+
+        let pairs = queue.take_pairs(0);
+        assert_eq!(tickets(&pairs), [["a", "near"]]);
+        assert_eq!(queue.waiting_count(), 1);
+    }
+
+    #[test]
+    fn test_tolerance_widens_with_wait() {
+        let mut queue = MatchQueue::new();
+        queue.join("a".into(), request(1200.0, 40), 0);
+        queue.join("b".into(), request(1800.0, 40), 0);
+        assert!(queue.take_pairs(0).is_empty(), "Too far apart at first");
+
+        // Mismatch 3.0 fits once tolerance has grown by 2.0 (20s)
+        assert_eq!(tickets(&queue.take_pairs(20_000)), [["a", "b"]]);
+    }
+
+    #[test]
+    fn test_assigned_code_is_polled() {
+        let mut queue = MatchQueue::new();
+        queue.join("a".into(), QueueRequest::default(), 0);
+        queue.join("b".into(), QueueRequest::default(), 0);
+        assert_eq!(
+            queue.poll("a", 500),
+            TicketStatus::Waiting { waited_ms: 500 }
+        );
+
+        let pairs = queue.take_pairs(1000);
+        queue.assign(&pairs[0], "ABCDE", 1000);
+        let matched = TicketStatus::Matched {
+            code: "ABCDE".into(),
+        };
+        assert_eq!(queue.poll("a", 1500), matched);
+        assert_eq!(queue.poll("b", 1500), matched);
+
+        queue.expire(1000 + RESULT_TTL_MS);
+        assert_eq!(queue.poll("a", 1000 + RESULT_TTL_MS), TicketStatus::Unknown);
+    }
+
+    #[test]
+    fn test_tickets_being_paired_stay_pollable() {
+        let mut queue = MatchQueue::new();
+        queue.join("a".into(), QueueRequest::default(), 0);
+        queue.join("b".into(), QueueRequest::default(), 0);
+        let pairs = queue.take_pairs(1000);
+
+        // Polled while the match is still being created
+        queue.expire(1200);
+        assert_eq!(
+            queue.poll("a", 1200),
+            TicketStatus::Waiting { waited_ms: 1200 }
+        );
+        assert!(queue.take_pairs(1200).is_empty(), "Not paired twice");
+
+        queue.requeue(pairs.into_iter().next().unwrap());
+        assert_eq!(queue.waiting_count(), 2);
+        let pairs = queue.take_pairs(1500);
+        queue.assign(&pairs[0], "ABCDE", 1500);
+        let matched = TicketStatus::Matched {
+            code: "ABCDE".into(),
+        };
+        assert_eq!(queue.poll("b", 1600), matched);
+    }
+
+    #[test]
+    fn test_timeout_and_abandoned_tickets() {
+        let mut queue = MatchQueue::new();
+        queue.join("patient".into(), QueueRequest::default(), 0);
+        queue.join("gone".into(), QueueRequest::default(), 0);
+        queue.leave("gone");
+        queue.join("idle".into(), QueueRequest::default(), 0);
+
+        let mut now = 0;
+        while now < QUEUE_TIMEOUT_MS {
+            now += 5_000;
+            queue.poll("patient", now);
+            queue.expire(now);
+        }
+
+        assert_eq!(queue.poll("patient", now), TicketStatus::TimedOut);
+        assert_eq!(queue.poll("idle", now), TicketStatus::Unknown);
+        assert_eq!(queue.waiting_count(), 0);
+    }
+
+    #[test]
+    fn test_requeue_keeps_order() {
+        let mut queue = MatchQueue::new();
+        queue.join("a".into(), QueueRequest::default(), 0);
+        queue.join("b".into(), QueueRequest::default(), 10);
+        let pair = queue.take_pairs(10).pop().unwrap();
+        queue.join("c".into(), QueueRequest::default(), 20);
+        queue.requeue(pair);
+
+        assert_eq!(tickets(&queue.take_pairs(20)), [["a", "b"]]);
+        assert_eq!(queue.waiting_count(), 1);
+    }
+}
@@ -0,0 +1,289 @@
+//! Counters, histograms and structured events recorded through `Environment`
+//!
+//! Game code records metrics by name and never formats them itself; the
+//! sink decides what happens to them. `NoopMetrics` drops everything,
+//! `ConsoleMetrics` aggregates and logs a periodic JSON summary, and
+//! `InMemoryMetrics` keeps every value so tests can assert on them.
+//!
+//! `tick.duration_ms` needs a clock that moves while code runs
+//! (`Environment::precise_ms`), so only the native server records it.
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+/// How often `ConsoleMetrics` logs its aggregated summary
+pub const CONSOLE_FLUSH_INTERVAL_MS: u64 = 10_000;
+
+/// Metric names used by the match server
+pub mod names {
+    pub const TICKS: &str = "ticks";
+    pub const TICK_DURATION_MS: &str = "tick.duration_ms"; // Native server only
+    pub const TICK_INTERVAL_MS: &str = "tick.interval_ms";
+    pub const ALARM_STEPS: &str = "alarm.steps";
+    pub const CATCH_UP: &str = "alarm.catch_up";
+    pub const MESSAGES_IN: &str = "messages.in";
+    pub const MESSAGES_OUT: &str = "messages.out";
+    pub const BYTES_IN: &str = "bytes.in";
+    pub const BYTES_OUT: &str = "bytes.out";
+    pub const MESSAGES_DROPPED: &str = "messages.dropped";
+    pub const RATE_LIMIT_DISCONNECTS: &str = "rate_limit.disconnects";
+}
+
+pub trait Metrics {
+    /// Add `value` to a counter
+    fn count(&self, name: &'static str, value: u64);
+
+    /// Record one sample of a distribution
+    fn observe(&self, name: &'static str, value: f64);
+
+    /// Record a named event with numeric fields
+    fn event(&self, name: &'static str, fields: &[(&'static str, f64)]);
+
+    /// Give buffering sinks a chance to emit what they have aggregated
+    fn flush(&self, _now_ms: u64) {}
+}
+
+/// Discards everything
+pub struct NoopMetrics;
+
+impl Metrics for NoopMetrics {
+    fn count(&self, _name: &'static str, _value: u64) {}
+    fn observe(&self, _name: &'static str, _value: f64) {}
+    fn event(&self, _name: &'static str, _fields: &[(&'static str, f64)]) {}
+}
+
+/// Running summary of a histogram between flushes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub count: u64,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Summary {
+    fn new(value: f64) -> Self {
+        Self {
+            count: 1,
+            sum: value,
+            min: value,
+            max: value,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+}
+
+fn json_fields(fields: &[(&'static str, f64)]) -> serde_json::Map<String, serde_json::Value> {
+    fields
+        .iter()
+        .map(|(name, value)| (name.to_string(), serde_json::json!(value)))
+        .collect()
+}
+
+/// Logs events as they happen and a JSON summary of counters and
+/// histograms every `CONSOLE_FLUSH_INTERVAL_MS`
+pub struct ConsoleMetrics {
+    log: fn(&str),
+    counters: RefCell<BTreeMap<&'static str, u64>>,
+    histograms: RefCell<BTreeMap<&'static str, Summary>>,
+    last_flush_ms: Cell<u64>,
+}
+
+impl ConsoleMetrics {
+    pub fn new(log: fn(&str)) -> Self {
+        Self {
+            log,
+            counters: RefCell::new(BTreeMap::new()),
+            histograms: RefCell::new(BTreeMap::new()),
+            last_flush_ms: Cell::new(0),
+        }
+    }
+
+    /// Aggregated values since the last flush as a JSON object, clearing them
+    pub fn take_summary(&self) -> serde_json::Value {
+        let counters = std::mem::take(&mut *self.counters.borrow_mut());
+        let histograms = std::mem::take(&mut *self.histograms.borrow_mut());
+        let histograms: serde_json::Map<String, serde_json::Value> = histograms
+            .into_iter()
+            .map(|(name, summary)| {
+                (
+                    name.to_string(),
+                    serde_json::json!({
+                        "count": summary.count,
+                        "mean": summary.mean(),
+                        "min": summary.min,
+                        "max": summary.max,
+                    }),
+                )
+            })
+            .collect();
+        serde_json::json!({ "counters": counters, "histograms": histograms })
+    }
+}
+
+impl Metrics for ConsoleMetrics {
+    fn count(&self, name: &'static str, value: u64) {
+        *self.counters.borrow_mut().entry(name).or_default() += value;
+    }
+
+    fn observe(&self, name: &'static str, value: f64) {
+        self.histograms
+            .borrow_mut()
+            .entry(name)
+            .and_modify(|summary| summary.add(value))
+            .or_insert_with(|| Summary::new(value));
+    }
+
+    fn event(&self, name: &'static str, fields: &[(&'static str, f64)]) {
+        let line = serde_json::json!({ "event": name, "fields": json_fields(fields) });
+        (self.log)(&line.to_string());
+    }
+
+    fn flush(&self, now_ms: u64) {
+        if now_ms.saturating_sub(self.last_flush_ms.get()) < CONSOLE_FLUSH_INTERVAL_MS {
+            return;
+        }
+        self.last_flush_ms.set(now_ms);
+        if self.counters.borrow().is_empty() && self.histograms.borrow().is_empty() {
+            return;
+        }
+        let line = serde_json::json!({ "metrics": self.take_summary() });
+        (self.log)(&line.to_string());
+    }
+}
+
+/// A recorded event
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedEvent {
+    pub name: &'static str,
+    pub fields: Vec<(&'static str, f64)>,
+}
+
+impl RecordedEvent {
+    pub fn field(&self, name: &str) -> Option<f64> {
+        self.fields
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+/// Keeps every value for tests to inspect
+#[derive(Default)]
+pub struct InMemoryMetrics {
+    counters: RefCell<BTreeMap<&'static str, u64>>,
+    samples: RefCell<BTreeMap<&'static str, Vec<f64>>>,
+    events: RefCell<Vec<RecordedEvent>>,
+}
+
+impl InMemoryMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counter total (0 if never counted)
+    pub fn counter(&self, name: &str) -> u64 {
+        self.counters.borrow().get(name).copied().unwrap_or(0)
+    }
+
+    /// Every sample observed for a histogram, oldest first
+    pub fn samples(&self, name: &str) -> Vec<f64> {
+        self.samples.borrow().get(name).cloned().unwrap_or_default()
+    }
+
+    /// Events recorded under `name`, oldest first
+    pub fn events(&self, name: &str) -> Vec<RecordedEvent> {
+        self.events
+            .borrow()
+            .iter()
+            .filter(|event| event.name == name)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Metrics for InMemoryMetrics {
+    fn count(&self, name: &'static str, value: u64) {
+        *self.counters.borrow_mut().entry(name).or_default() += value;
+    }
+
+    fn observe(&self, name: &'static str, value: f64) {
+        self.samples
+            .borrow_mut()
+            .entry(name)
+            .or_default()
+            .push(value);
+    }
+
+    fn event(&self, name: &'static str, fields: &[(&'static str, f64)]) {
+        self.events.borrow_mut().push(RecordedEvent {
+            name,
+            fields: fields.to_vec(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    thread_local! {
+        static LINES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn capture(line: &str) {
+        LINES.with(|lines| lines.borrow_mut().push(line.to_string()));
+    }
+
+    #[test]
+    fn test_in_memory_records_everything() {
+        let metrics = InMemoryMetrics::new();
+        metrics.count(names::MESSAGES_OUT, 2);
+        metrics.count(names::MESSAGES_OUT, 3);
+        metrics.observe(names::ALARM_STEPS, 1.0);
+        metrics.observe(names::ALARM_STEPS, 4.0);
+        metrics.event("catch_up", &[("steps", 4.0)]);
+
+        assert_eq!(metrics.counter(names::MESSAGES_OUT), 5);
+        assert_eq!(metrics.counter(names::BYTES_OUT), 0);
+        assert_eq!(metrics.samples(names::ALARM_STEPS), vec![1.0, 4.0]);
+        assert_eq!(metrics.events("catch_up")[0].field("steps"), Some(4.0));
+    }
+
+    #[test]
+    fn test_console_aggregates_until_flush_interval() {
+        let metrics = ConsoleMetrics::new(capture);
+        metrics.count(names::TICKS, 60);
+        metrics.observe(names::ALARM_STEPS, 1.0);
+        metrics.observe(names::ALARM_STEPS, 3.0);
+        metrics.event("catch_up", &[("steps", 3.0)]);
+        assert_eq!(LINES.with(|lines| lines.borrow().len()), 1);
+
+        // Not due yet
+        metrics.flush(CONSOLE_FLUSH_INTERVAL_MS - 1);
+        assert_eq!(LINES.with(|lines| lines.borrow().len()), 1);
+
+        metrics.flush(CONSOLE_FLUSH_INTERVAL_MS);
+        let summary: serde_json::Value =
+            serde_json::from_str(&LINES.with(|lines| lines.borrow()[1].clone())).unwrap();
+        assert_eq!(summary["metrics"]["counters"]["ticks"], 60);
+        assert_eq!(summary["metrics"]["histograms"]["alarm.steps"]["mean"], 2.0);
+        assert_eq!(summary["metrics"]["histograms"]["alarm.steps"]["max"], 3.0);
+
+        // Values were cleared by the flush
+        assert_eq!(
+            metrics.take_summary(),
+            serde_json::json!({ "counters": {}, "histograms": {} })
+        );
+    }
+}
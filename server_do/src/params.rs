@@ -0,0 +1,98 @@
+//! Request parameters shared by the hosted lobby and the native server
+//!
+//! Both front ends accept the same query options and admin header, so the
+//! parsing and checks live here and each host only maps errors to its own
+//! response type.
+
+use crate::bot::BotFill;
+use crate::match_history::MAX_RECENT;
+use game_core::MatchSettings;
+
+/// Listing size when the request does not give a `limit`
+pub const DEFAULT_LIMIT: usize = 20;
+
+/// Settings and bot fill from the `/create` query options:
+/// win_score=1..21, speed=slow|normal|fast, paddle=small|normal|large,
+/// layout=classic|wide, bot=now|wait
+pub fn create_options(options: &[(String, String)]) -> Result<(MatchSettings, BotFill), String> {
+    let settings = MatchSettings::from_options(
+        options
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str())),
+    )
+    .map_err(|e| format!("Invalid match settings: {e}"))?;
+    let bot_fill = match options.iter().find(|(name, _)| name == "bot") {
+        Some((_, value)) => {
+            BotFill::from_option(value).ok_or_else(|| format!("Invalid bot option '{value}'"))?
+        }
+        None => BotFill::Never,
+    };
+    Ok((settings, bot_fill))
+}
+
+/// `limit` query parameter, capped at what the history object lists
+pub fn limit(params: &[(String, String)]) -> usize {
+    params
+        .iter()
+        .find(|(key, _)| key == "limit")
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_LIMIT)
+        .min(MAX_RECENT)
+}
+
+/// Whether `id` could name a finished match
+pub fn is_valid_result_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Compare secrets without leaking how many leading bytes matched
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_create_options() {
+        let (settings, bot_fill) = create_options(&params(&[])).unwrap();
+        assert_eq!(settings, MatchSettings::default());
+        assert_eq!(bot_fill, BotFill::Never);
+
+        let (settings, bot_fill) =
+            create_options(&params(&[("win_score", "3"), ("bot", "now")])).unwrap();
+        assert_eq!(settings.win_score, 3);
+        assert_eq!(bot_fill, BotFill::Immediate);
+
+        assert!(create_options(&params(&[("win_score", "0")]))
+            .unwrap_err()
+            .starts_with("Invalid match settings"));
+        assert_eq!(
+            create_options(&params(&[("bot", "maybe")])).unwrap_err(),
+            "Invalid bot option 'maybe'"
+        );
+    }
+
+    #[test]
+    fn test_limit() {
+        assert_eq!(limit(&params(&[])), DEFAULT_LIMIT);
+        assert_eq!(limit(&params(&[("limit", "5")])), 5);
+        assert_eq!(limit(&params(&[("limit", "5000")])), MAX_RECENT);
+        assert_eq!(limit(&params(&[("limit", "lots")])), DEFAULT_LIMIT);
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"Bearer abc", b"Bearer abc"));
+        assert!(!constant_time_eq(b"Bearer abd", b"Bearer abc"));
+        assert!(!constant_time_eq(b"Bearer ab", b"Bearer abc"));
+    }
+}
@@ -0,0 +1,398 @@
+//! Per-connection message rate limiting
+//!
+//! Each socket gets a token bucket per kind of message and one shared by
+//! every message, checked before a message does any work on the match.
+//! Messages over budget are dropped. A socket that keeps sending them is
+//! disconnected; one quiet stretch is enough to be forgiven.
+//!
+//! Hosts read the limits from their environment with `RateLimits::from_vars`.
+//! Each bucket is set as `burst/per_second`:
+//!
+//! | Variable                 | Default  |
+//! | ------------------------ | -------- |
+//! | `RATE_LIMIT_INPUT`       | `30/90`  |
+//! | `RATE_LIMIT_PING`        | `5/2`    |
+//! | `RATE_LIMIT_CHAT`        | `5/2`    |
+//! | `RATE_LIMIT_CONTROL`     | `10/4`   |
+//! | `RATE_LIMIT_TOTAL`       | `60/120` |
+//! | `RATE_LIMIT_MAX_DROPPED` | `100`    |
+//! | `RATE_LIMIT_FORGIVE_MS`  | `10000`  |
+
+use proto::C2S;
+use std::fmt;
+
+/// Kinds of client message, each with its own budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    Input,
+    Ping,
+    Chat,
+    /// Joining, pausing, rematches and mutes
+    Control,
+}
+
+impl MessageKind {
+    pub fn of(msg: &C2S) -> Self {
+        match msg {
+            C2S::Input { .. } => MessageKind::Input,
+            C2S::Ping { .. } | C2S::PongEcho { .. } => MessageKind::Ping,
+            C2S::Chat { .. } => MessageKind::Chat,
+            C2S::Join { .. }
+            | C2S::Rejoin { .. }
+            | C2S::Spectate { .. }
+            | C2S::Rematch { .. }
+            | C2S::Pause
+            | C2S::Resume
+            | C2S::SetMute { .. } => MessageKind::Control,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Budget for one token bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BucketLimit {
+    /// Messages accepted back to back from a full bucket
+    pub burst: u32,
+    /// Rate the bucket refills at
+    pub per_second: u32,
+}
+
+/// Limits applied to every connection of a match
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimits {
+    /// Clients send inputs at ~30Hz, plus one on each key change
+    pub input: BucketLimit,
+    /// Clients ping every 2s
+    pub ping: BucketLimit,
+    pub chat: BucketLimit,
+    pub control: BucketLimit,
+    /// All messages together
+    pub total: BucketLimit,
+    /// Dropped messages tolerated before the socket is disconnected
+    pub max_dropped: u32,
+    /// A drop this long after the previous one starts the count afresh (ms)
+    pub forgive_after_ms: u64,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            input: BucketLimit {
+                burst: 30,
+                per_second: 90,
+            },
+            ping: BucketLimit {
+                burst: 5,
+                per_second: 2,
+            },
+            chat: BucketLimit {
+                burst: 5,
+                per_second: 2,
+            },
+            control: BucketLimit {
+                burst: 10,
+                per_second: 4,
+            },
+            total: BucketLimit {
+                burst: 60,
+                per_second: 120,
+            },
+            max_dropped: 100,
+            forgive_after_ms: 10_000,
+        }
+    }
+}
+
+impl BucketLimit {
+    /// Parse `burst/per_second`
+    fn parse(value: &str) -> Option<Self> {
+        let (burst, per_second) = value.trim().split_once('/')?;
+        Some(Self {
+            burst: burst.trim().parse().ok()?,
+            per_second: per_second.trim().parse().ok()?,
+        })
+    }
+}
+
+impl RateLimits {
+    /// Defaults overridden by whichever `RATE_LIMIT_*` variables `var` finds.
+    /// An unreadable value is an error naming the variable.
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let mut limits = Self::default();
+        let buckets = [
+            ("RATE_LIMIT_INPUT", &mut limits.input),
+            ("RATE_LIMIT_PING", &mut limits.ping),
+            ("RATE_LIMIT_CHAT", &mut limits.chat),
+            ("RATE_LIMIT_CONTROL", &mut limits.control),
+            ("RATE_LIMIT_TOTAL", &mut limits.total),
+        ];
+        for (name, bucket) in buckets {
+            if let Some(value) = var(name) {
+                *bucket = BucketLimit::parse(&value)
+                    .ok_or_else(|| format!("{name} must be burst/per_second, got {value:?}"))?;
+            }
+        }
+        if let Some(value) = var("RATE_LIMIT_MAX_DROPPED") {
+            limits.max_dropped = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("RATE_LIMIT_MAX_DROPPED must be a count, got {value:?}"))?;
+        }
+        if let Some(value) = var("RATE_LIMIT_FORGIVE_MS") {
+            limits.forgive_after_ms = value.trim().parse().map_err(|_| {
+                format!("RATE_LIMIT_FORGIVE_MS must be milliseconds, got {value:?}")
+            })?;
+        }
+        Ok(limits)
+    }
+
+    fn for_kind(&self, kind: MessageKind) -> BucketLimit {
+        match kind {
+            MessageKind::Input => self.input,
+            MessageKind::Ping => self.ping,
+            MessageKind::Chat => self.chat,
+            MessageKind::Control => self.control,
+        }
+    }
+}
+
+/// Why a message was not accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitError {
+    /// Over budget; the message was dropped
+    Dropped { kind: MessageKind },
+    /// Too many messages dropped; the socket must be disconnected
+    TooManyDropped { count: u32 },
+}
+
+impl fmt::Display for RateLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateLimitError::Dropped { kind } => write!(f, "{kind:?} rate limit exceeded"),
+            RateLimitError::TooManyDropped { count } => {
+                write!(f, "disconnected after {count} dropped messages")
+            }
+        }
+    }
+}
+
+/// Tokens refilled continuously up to the burst size
+#[derive(Debug, Clone, Copy, Default)]
+struct TokenBucket {
+    tokens: f64,
+    last_ms: Option<u64>, // None until first used; a new bucket starts full
+}
+
+impl TokenBucket {
+    fn refill(&mut self, limit: BucketLimit, now_ms: u64) {
+        let burst = f64::from(limit.burst);
+        self.tokens = match self.last_ms {
+            None => burst,
+            Some(last_ms) => {
+                let elapsed = now_ms.saturating_sub(last_ms) as f64 / 1000.0;
+                (self.tokens + elapsed * f64::from(limit.per_second)).min(burst)
+            }
+        };
+        self.last_ms = Some(now_ms);
+    }
+}
+
+/// Rate limiting state for one socket
+#[derive(Debug, Clone)]
+pub struct ConnectionLimiter {
+    limits: RateLimits,
+    kinds: [TokenBucket; 4], // Indexed by MessageKind
+    total: TokenBucket,
+    dropped: u32, // Drops since the socket was last forgiven
+    last_drop_ms: u64,
+}
+
+impl Default for ConnectionLimiter {
+    fn default() -> Self {
+        Self::new(RateLimits::default())
+    }
+}
+
+impl ConnectionLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            kinds: [TokenBucket::default(); 4],
+            total: TokenBucket::default(),
+            dropped: 0,
+            last_drop_ms: 0,
+        }
+    }
+
+    /// Spend a token for one message of `kind`. A message over either its
+    /// own budget or the shared one is dropped and spends nothing.
+    pub fn check(&mut self, now_ms: u64, kind: MessageKind) -> Result<(), RateLimitError> {
+        let kind_limit = self.limits.for_kind(kind);
+        let bucket = &mut self.kinds[kind.index()];
+        bucket.refill(kind_limit, now_ms);
+        self.total.refill(self.limits.total, now_ms);
+
+        if bucket.tokens >= 1.0 && self.total.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            self.total.tokens -= 1.0;
+            return Ok(());
+        }
+
+        if now_ms.saturating_sub(self.last_drop_ms) >= self.limits.forgive_after_ms {
+            self.dropped = 0;
+        }
+        self.dropped += 1;
+        self.last_drop_ms = now_ms;
+        if self.dropped > self.limits.max_dropped {
+            Err(RateLimitError::TooManyDropped {
+                count: self.dropped,
+            })
+        } else {
+            Err(RateLimitError::Dropped { kind })
+        }
+    }
+
+    /// Messages dropped since the socket was last forgiven
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(per_kind: BucketLimit) -> RateLimits {
+        RateLimits {
+            input: per_kind,
+            ping: per_kind,
+            chat: per_kind,
+            control: per_kind,
+            total: BucketLimit {
+                burst: 100,
+                per_second: 100,
+            },
+            max_dropped: 3,
+            forgive_after_ms: 1000,
+        }
+    }
+
+    const TWO_PER_SECOND: BucketLimit = BucketLimit {
+        burst: 2,
+        per_second: 2,
+    };
+
+    #[test]
+    fn test_limits_from_vars() {
+        let vars = |name: &str| match name {
+            "RATE_LIMIT_PING" => Some("3/1".to_string()),
+            "RATE_LIMIT_MAX_DROPPED" => Some(" 7 ".to_string()),
+            _ => None,
+        };
+        let limits = RateLimits::from_vars(vars).unwrap();
+        assert_eq!(
+            limits.ping,
+            BucketLimit {
+                burst: 3,
+                per_second: 1
+            }
+        );
+        assert_eq!(limits.max_dropped, 7);
+        assert_eq!(limits.input, RateLimits::default().input);
+
+        assert_eq!(RateLimits::from_vars(|_| None), Ok(RateLimits::default()));
+        let bad =
+            RateLimits::from_vars(|name| (name == "RATE_LIMIT_CHAT").then(|| "5".to_string()));
+        assert!(bad.unwrap_err().contains("RATE_LIMIT_CHAT"));
+    }
+
+    #[test]
+    fn test_burst_then_refill() {
+        let mut limiter = ConnectionLimiter::new(limits(TWO_PER_SECOND));
+        assert_eq!(limiter.check(0, MessageKind::Input), Ok(()));
+        assert_eq!(limiter.check(0, MessageKind::Input), Ok(()));
+        assert_eq!(
+            limiter.check(0, MessageKind::Input),
+            Err(RateLimitError::Dropped {
+                kind: MessageKind::Input
+            })
+        );
+
+        // Half a second earns one token back
+        assert_eq!(limiter.check(500, MessageKind::Input), Ok(()));
+        assert!(limiter.check(500, MessageKind::Input).is_err());
+    }
+
+    #[test]
+    fn test_kinds_have_separate_budgets() {
+        let mut limiter = ConnectionLimiter::new(limits(TWO_PER_SECOND));
+        for _ in 0..2 {
+            assert_eq!(limiter.check(0, MessageKind::Input), Ok(()));
+        }
+        assert!(limiter.check(0, MessageKind::Input).is_err());
+        assert_eq!(limiter.check(0, MessageKind::Ping), Ok(()));
+        assert_eq!(limiter.check(0, MessageKind::Control), Ok(()));
+    }
+
+    #[test]
+    fn test_total_budget_is_shared() {
+        let mut config = limits(TWO_PER_SECOND);
+        config.total = BucketLimit {
+            burst: 3,
+            per_second: 3,
+        };
+        let mut limiter = ConnectionLimiter::new(config);
+        assert_eq!(limiter.check(0, MessageKind::Input), Ok(()));
+        assert_eq!(limiter.check(0, MessageKind::Ping), Ok(()));
+        assert_eq!(limiter.check(0, MessageKind::Chat), Ok(()));
+        assert_eq!(
+            limiter.check(0, MessageKind::Control),
+            Err(RateLimitError::Dropped {
+                kind: MessageKind::Control
+            })
+        );
+    }
+
+    #[test]
+    fn test_persistent_offender_is_disconnected() {
+        let mut limiter = ConnectionLimiter::new(limits(TWO_PER_SECOND));
+        limiter.check(0, MessageKind::Ping).unwrap();
+        limiter.check(0, MessageKind::Ping).unwrap();
+        for _ in 0..3 {
+            assert!(matches!(
+                limiter.check(0, MessageKind::Ping),
+                Err(RateLimitError::Dropped { .. })
+            ));
+        }
+        assert_eq!(
+            limiter.check(0, MessageKind::Ping),
+            Err(RateLimitError::TooManyDropped { count: 4 })
+        );
+    }
+
+    #[test]
+    fn test_quiet_stretch_forgives_drops() {
+        let mut limiter = ConnectionLimiter::new(limits(TWO_PER_SECOND));
+        limiter.check(0, MessageKind::Ping).unwrap();
+        limiter.check(0, MessageKind::Ping).unwrap();
+        for _ in 0..3 {
+            assert!(limiter.check(0, MessageKind::Ping).is_err());
+        }
+        assert_eq!(limiter.dropped(), 3);
+
+        // Drain the refilled bucket a second later; the count starts again
+        limiter.check(1000, MessageKind::Ping).unwrap();
+        limiter.check(1000, MessageKind::Ping).unwrap();
+        assert_eq!(
+            limiter.check(1000, MessageKind::Ping),
+            Err(RateLimitError::Dropped {
+                kind: MessageKind::Ping
+            })
+        );
+        assert_eq!(limiter.dropped(), 1);
+    }
+}
@@ -0,0 +1,351 @@
+//! Glicko-2 player ratings
+//!
+//! Ratings follow Glickman's "Example of the Glicko-2 system" with each
+//! finished match treated as a one-game rating period. Glicko-2 has no K
+//! factor, so forfeits are damped by scaling the rating change instead.
+//! Ratings are kept per persistent profile id by `HistoryDO`.
+
+use crate::storage::get_optional;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use worker::*;
+
+/// Glicko-2 scale factor between displayed ratings and the internal scale
+const SCALE: f64 = 173.7178;
+
+/// System constant constraining volatility change over time
+const TAU: f64 = 0.5;
+
+/// Convergence tolerance for the volatility iteration
+const EPSILON: f64 = 0.000_001;
+
+/// Share of a normal rating change applied when a match ends by forfeit
+pub const FORFEIT_WEIGHT: f64 = 0.5;
+
+/// Longest accepted profile id
+pub const MAX_PROFILE_ID_LEN: usize = 32;
+
+/// Storage key prefix for ratings
+const RATING_PREFIX: &str = "rating:";
+
+/// Storage key of the top rated profiles, kept sorted as ratings change
+const LEADERBOARD_KEY: &str = "leaderboard";
+
+/// Profiles kept on the leaderboard
+pub const LEADERBOARD_SIZE: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self {
+            rating: 1500.0,
+            deviation: 350.0,
+            volatility: 0.06,
+        }
+    }
+}
+
+fn g(phi: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * phi * phi / (PI * PI)).sqrt()
+}
+
+fn expected(mu: f64, mu_j: f64, phi_j: f64) -> f64 {
+    1.0 / (1.0 + (-g(phi_j) * (mu - mu_j)).exp())
+}
+
+/// New volatility (step 5 of the Glicko-2 algorithm, Illinois method)
+fn new_volatility(phi: f64, sigma: f64, v: f64, delta: f64) -> f64 {
+    let a = (sigma * sigma).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        let denom = phi * phi + v + ex;
+        ex * (delta * delta - phi * phi - v - ex) / (2.0 * denom * denom) - (x - a) / (TAU * TAU)
+    };
+
+    let mut big_a = a;
+    let mut big_b = if delta * delta > phi * phi + v {
+        (delta * delta - phi * phi - v).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * TAU) < 0.0 {
+            k += 1.0;
+        }
+        a - k * TAU
+    };
+
+    let mut f_a = f(big_a);
+    let mut f_b = f(big_b);
+    while (big_b - big_a).abs() > EPSILON {
+        let big_c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+        let f_c = f(big_c);
+        if f_c * f_b <= 0.0 {
+            big_a = big_b;
+            f_a = f_b;
+        } else {
+            f_a /= 2.0;
+        }
+        big_b = big_c;
+        f_b = f_c;
+    }
+    (big_a / 2.0).exp()
+}
+
+impl Rating {
+    /// Rating after one period of `results` (opponent, score: 1 win, 0 loss, 0.5 draw).
+    /// `weight` scales the rating change (1.0 normally, `FORFEIT_WEIGHT` for forfeits).
+    pub fn update(&self, results: &[(Rating, f64)], weight: f64) -> Rating {
+        let mu = (self.rating - 1500.0) / SCALE;
+        let phi = self.deviation / SCALE;
+
+        if results.is_empty() {
+            // Only the deviation grows while a player is inactive
+            let phi_star = (phi * phi + self.volatility * self.volatility).sqrt();
+            return Rating {
+                deviation: phi_star * SCALE,
+                ..*self
+            };
+        }
+
+        let mut v_inv = 0.0;
+        let mut improvement = 0.0;
+        for (opponent, score) in results {
+            let mu_j = (opponent.rating - 1500.0) / SCALE;
+            let phi_j = opponent.deviation / SCALE;
+            let e = expected(mu, mu_j, phi_j);
+            v_inv += g(phi_j) * g(phi_j) * e * (1.0 - e);
+            improvement += g(phi_j) * (score - e);
+        }
+        let v = 1.0 / v_inv;
+        let delta = v * improvement;
+
+        let sigma = new_volatility(phi, self.volatility, v, delta);
+        let phi_star = (phi * phi + sigma * sigma).sqrt();
+        let new_phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / v).sqrt();
+        let new_mu = mu + weight * new_phi * new_phi * improvement;
+
+        Rating {
+            rating: new_mu * SCALE + 1500.0,
+            deviation: new_phi * SCALE,
+            volatility: sigma,
+        }
+    }
+}
+
+/// Updated (winner, loser) ratings after one match
+pub fn rate_match(winner: Rating, loser: Rating, forfeit: bool) -> (Rating, Rating) {
+    let weight = if forfeit { FORFEIT_WEIGHT } else { 1.0 };
+    (
+        winner.update(&[(loser, 1.0)], weight),
+        loser.update(&[(winner, 0.0)], weight),
+    )
+}
+
+/// True for ids clients may use as a persistent profile
+pub fn is_valid_profile_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_PROFILE_ID_LEN
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// A profile's stored rating
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerRating {
+    pub profile_id: String,
+    pub rating: Rating,
+    pub games: u32,
+    pub updated_ms: u64,
+}
+
+impl PlayerRating {
+    pub fn new(profile_id: &str) -> Self {
+        Self {
+            profile_id: profile_id.to_string(),
+            rating: Rating::default(),
+            games: 0,
+            updated_ms: 0,
+        }
+    }
+}
+
+/// Stored rating for a profile. Only a profile with no stored rating starts
+/// at the default; a failed read is an error, so it is never saved over.
+pub async fn load(storage: &Storage, profile_id: &str) -> Result<PlayerRating> {
+    let stored = get_optional(storage, &format!("{RATING_PREFIX}{profile_id}")).await?;
+    Ok(stored.unwrap_or_else(|| PlayerRating::new(profile_id)))
+}
+
+/// Store a rating and move the profile to its place on the leaderboard
+pub async fn save(storage: &Storage, rating: &PlayerRating) -> Result<()> {
+    storage
+        .put(&format!("{RATING_PREFIX}{}", rating.profile_id), rating)
+        .await?;
+
+    let mut top: Option<Vec<PlayerRating>> = get_optional(storage, LEADERBOARD_KEY).await?;
+    let stale = match top.as_mut() {
+        Some(top) => rank(top, rating),
+        None => true,
+    };
+    let top = match top {
+        Some(top) if !stale => top,
+        // No board yet, or it may have missed someone: rebuild from every rating
+        _ => rank_all(storage).await?,
+    };
+    storage.put(LEADERBOARD_KEY, &top).await
+}
+
+/// Highest rated profiles first, at most `limit`
+pub async fn leaderboard(storage: &Storage, limit: usize) -> Result<Vec<PlayerRating>> {
+    let mut top: Vec<PlayerRating> = match get_optional(storage, LEADERBOARD_KEY).await? {
+        Some(top) => top,
+        None => rank_all(storage).await?,
+    };
+    top.truncate(limit);
+    Ok(top)
+}
+
+/// The leaderboard built from every stored rating
+async fn rank_all(storage: &Storage) -> Result<Vec<PlayerRating>> {
+    let options = ListOptions::new().prefix(RATING_PREFIX);
+    let map = storage.list_with_options(options).await?;
+
+    let mut ratings: Vec<PlayerRating> = Vec::new();
+    for value in map.values() {
+        ratings.push(serde_wasm_bindgen::from_value(value?)?);
+    }
+    ratings.sort_by(|a, b| b.rating.rating.total_cmp(&a.rating.rating));
+    ratings.truncate(LEADERBOARD_SIZE);
+    Ok(ratings)
+}
+
+/// Put `rating` in its place on a sorted leaderboard. Returns true if the
+/// board may now be wrong: a listed profile fell to the bottom of a full
+/// board, where an unlisted profile might outrank it.
+fn rank(top: &mut Vec<PlayerRating>, rating: &PlayerRating) -> bool {
+    let before = top.len();
+    top.retain(|listed| listed.profile_id != rating.profile_id);
+    let was_listed = top.len() < before;
+    let was_full = before >= LEADERBOARD_SIZE;
+
+    let at = top.partition_point(|listed| listed.rating.rating >= rating.rating.rating);
+    top.insert(at, rating.clone());
+    top.truncate(LEADERBOARD_SIZE);
+    was_listed && was_full && at >= LEADERBOARD_SIZE - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rating(rating: f64, deviation: f64) -> Rating {
+        Rating {
+            rating,
+            deviation,
+            volatility: 0.06,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() < tolerance,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn test_glickman_reference_example() {
+        // Worked example from Glickman's Glicko-2 paper (tau = 0.5)
+        let player = rating(1500.0, 200.0);
+        let results = [
+            (rating(1400.0, 30.0), 1.0),
+            (rating(1550.0, 100.0), 0.0),
+            (rating(1700.0, 300.0), 0.0),
+        ];
+
+        let updated = player.update(&results, 1.0);
+        assert_close(updated.rating, 1464.06, 0.01);
+        assert_close(updated.deviation, 151.52, 0.01);
+        assert_close(updated.volatility, 0.05999, 0.00001);
+    }
+
+    #[test]
+    fn test_inactive_period_grows_deviation() {
+        let player = rating(1500.0, 200.0);
+        let updated = player.update(&[], 1.0);
+        assert_eq!(updated.rating, 1500.0);
+        assert_close(updated.deviation, 200.27, 0.01);
+    }
+
+    #[test]
+    fn test_match_between_new_players() {
+        let (winner, loser) = rate_match(Rating::default(), Rating::default(), false);
+        assert_close(winner.rating, 1662.31, 0.01);
+        assert_close(loser.rating, 1337.69, 0.01);
+        assert_close(winner.deviation, 290.32, 0.01);
+        assert_eq!(winner.deviation, loser.deviation);
+    }
+
+    #[test]
+    fn test_forfeit_moves_ratings_less() {
+        let (winner, loser) = rate_match(Rating::default(), Rating::default(), true);
+        let (full_winner, _) = rate_match(Rating::default(), Rating::default(), false);
+        assert_close(
+            winner.rating - 1500.0,
+            (full_winner.rating - 1500.0) * FORFEIT_WEIGHT,
+            1e-9,
+        );
+        assert!(loser.rating < 1500.0);
+    }
+
+    fn player(profile_id: &str, value: f64) -> PlayerRating {
+        PlayerRating {
+            rating: rating(value, 100.0),
+            ..PlayerRating::new(profile_id)
+        }
+    }
+
+    #[test]
+    fn test_rank_keeps_board_sorted_and_unique() {
+        let mut top = Vec::new();
+        for (id, value) in [("a", 1500.0), ("b", 1700.0), ("c", 1600.0)] {
+            assert!(!rank(&mut top, &player(id, value)));
+        }
+        assert!(!rank(&mut top, &player("a", 1800.0)));
+        let ids: Vec<&str> = top.iter().map(|p| p.profile_id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_rank_on_full_board() {
+        let mut top: Vec<PlayerRating> = (0..LEADERBOARD_SIZE)
+            .map(|n| player(&format!("p{n}"), 2000.0 - n as f64))
+            .collect();
+
+        // Below the board: not listed, nothing unknown above it
+        assert!(!rank(&mut top, &player("low", 1000.0)));
+        assert_eq!(top.len(), LEADERBOARD_SIZE);
+        assert!(top.iter().all(|p| p.profile_id != "low"));
+
+        // A newcomer near the top pushes the last profile off
+        assert!(!rank(&mut top, &player("new", 1999.5)));
+        assert_eq!(top[1].profile_id, "new");
+        assert_eq!(top.len(), LEADERBOARD_SIZE);
+
+        // A listed profile sinking to the bottom may be outranked by someone
+        // no longer listed, so the board asks to be rebuilt
+        assert!(rank(&mut top, &player("p0", 1200.0)));
+    }
+
+    #[test]
+    fn test_profile_id_validation() {
+        assert!(is_valid_profile_id("3f2b9c0e1d4a4b7f8e6c5d4b3a291807"));
+        assert!(!is_valid_profile_id(""));
+        assert!(!is_valid_profile_id("has space"));
+        assert!(!is_valid_profile_id(&"a".repeat(MAX_PROFILE_ID_LEN + 1)));
+    }
+}
@@ -0,0 +1,155 @@
+//! Per-client snapshot rate
+//!
+//! Each player gets snapshots at a cadence chosen from the RTT they report
+//! and how many snapshots are still unacknowledged. Fast, clean links get
+//! more updates; slow or backed up links get fewer so they can drain.
+
+use std::collections::VecDeque;
+
+/// Snapshot interval before a client has reported anything
+pub const DEFAULT_INTERVAL_TICKS: u32 = game_core::TickClock::SNAPSHOT_INTERVAL_TICKS;
+
+/// Longest interval a congested client is throttled to (5 Hz)
+pub const MAX_INTERVAL_TICKS: u32 = 12;
+
+/// Unacknowledged snapshots above which a client counts as backed up
+const BACKLOG_LIMIT: usize = 4;
+
+/// Sent ticks remembered for backlog estimation
+const MAX_TRACKED: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct SnapshotRate {
+    interval_ticks: u32,
+    last_sent_tick: Option<u32>,
+    unacked_ticks: VecDeque<u32>, // Ticks of snapshots sent but not yet seen by the client
+}
+
+impl Default for SnapshotRate {
+    fn default() -> Self {
+        Self {
+            interval_ticks: DEFAULT_INTERVAL_TICKS,
+            last_sent_tick: None,
+            unacked_ticks: VecDeque::new(),
+        }
+    }
+}
+
+impl SnapshotRate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update the cadence from a client report
+    /// rtt_ms: client's smoothed round trip, acked_tick: newest snapshot tick it has
+    pub fn on_report(&mut self, rtt_ms: u16, acked_tick: u32) {
+        while self
+            .unacked_ticks
+            .front()
+            .is_some_and(|&tick| tick <= acked_tick)
+        {
+            self.unacked_ticks.pop_front();
+        }
+
+        let base = match rtt_ms {
+            0..=59 => 2,
+            60..=149 => 3,
+            150..=299 => 4,
+            _ => 6,
+        };
+        self.interval_ticks = if self.backlog() > BACKLOG_LIMIT {
+            (base * 2).min(MAX_INTERVAL_TICKS)
+        } else {
+            base
+        };
+    }
+
+    /// True if a snapshot for `tick` should go to this client. A tick before
+    /// the last one sent means a new game started, so one is due at once.
+    pub fn is_due(&self, tick: u32) -> bool {
+        match self.last_sent_tick {
+            Some(last) if tick >= last => tick - last >= self.interval_ticks,
+            _ => true,
+        }
+    }
+
+    /// Forget what was sent in the previous game; the cadence is kept
+    pub fn restart(&mut self) {
+        self.last_sent_tick = None;
+        self.unacked_ticks.clear();
+    }
+
+    /// Record that the snapshot for `tick` was sent
+    pub fn record_sent(&mut self, tick: u32) {
+        self.last_sent_tick = Some(tick);
+        if self.unacked_ticks.len() == MAX_TRACKED {
+            self.unacked_ticks.pop_front();
+        }
+        self.unacked_ticks.push_back(tick);
+    }
+
+    /// Snapshots sent that the client has not reported receiving
+    pub fn backlog(&self) -> usize {
+        self.unacked_ticks.len()
+    }
+
+    pub fn interval_ticks(&self) -> u32 {
+        self.interval_ticks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_cadence() {
+        let mut rate = SnapshotRate::new();
+        assert!(rate.is_due(1));
+        rate.record_sent(1);
+        assert!(!rate.is_due(3));
+        assert!(rate.is_due(4));
+    }
+
+    #[test]
+    fn test_new_game_restarts_cadence() {
+        let mut rate = SnapshotRate::new();
+        rate.record_sent(900);
+        assert!(rate.is_due(0));
+
+        rate.restart();
+        assert_eq!(rate.backlog(), 0);
+        assert!(rate.is_due(0));
+        rate.record_sent(0);
+        assert!(rate.is_due(3));
+    }
+
+    #[test]
+    fn test_interval_follows_rtt() {
+        let mut rate = SnapshotRate::new();
+        rate.on_report(30, 0);
+        assert_eq!(rate.interval_ticks(), 2);
+        rate.on_report(200, 0);
+        assert_eq!(rate.interval_ticks(), 4);
+        rate.on_report(500, 0);
+        assert_eq!(rate.interval_ticks(), 6);
+    }
+
+    #[test]
+    fn test_backlog_throttles_until_acked() {
+        let mut rate = SnapshotRate::new();
+        for tick in (3..=30).step_by(3) {
+            rate.record_sent(tick);
+        }
+
+        // Client has only seen up to tick 9: seven snapshots in flight
+        rate.on_report(30, 9);
+        assert_eq!(rate.backlog(), 7);
+        assert_eq!(rate.interval_ticks(), 4);
+
+        // Caught up
+        rate.on_report(30, 30);
+        assert_eq!(rate.backlog(), 0);
+        assert_eq!(rate.interval_ticks(), 2);
+    }
+}
@@ -0,0 +1,19 @@
+//! Durable Object storage helpers
+
+use serde::de::DeserializeOwned;
+use worker::wasm_bindgen::JsValue;
+use worker::*;
+
+/// Value stored under `key`, or None if there is none. Unlike `Storage::get`,
+/// which reports a missing key as an error like any other, storage and
+/// decoding failures stay errors.
+pub async fn get_optional<T: DeserializeOwned>(storage: &Storage, key: &str) -> Result<Option<T>> {
+    let found = storage
+        .get_multiple(vec![key])
+        .await?
+        .get(&JsValue::from_str(key));
+    if found.is_undefined() {
+        return Ok(None);
+    }
+    Ok(Some(serde_wasm_bindgen::from_value(found)?))
+}
@@ -1,8 +1,21 @@
-use crate::game_state::{Environment, GameClient, GameState, MatchState};
-use proto::S2C;
+use crate::bot::{BotFill, BOT_FILL_WAIT_MS};
+use crate::game_state::{
+    Environment, GameClient, GameState, MatchAction, MatchState, PersistedMatch, CHAT_COOLDOWN_MS,
+    PAUSES_PER_PLAYER, PAUSE_MAX_SECONDS, RECONNECT_GRACE_SECONDS, REMATCH_OFFER_SECONDS,
+    RESUME_COUNTDOWN_SECONDS, SPECTATOR_DELAY_MS,
+};
+use crate::host::{self, SocketRole};
+use crate::input_validator::{InputError, MAX_INPUT_VIOLATIONS};
+use crate::metrics::{names, InMemoryMetrics, Metrics};
+use crate::rate_limit::{ConnectionLimiter, RateLimits};
+use game_core::{Ball, MapLayout, MatchSettings, PaddleSize, SpeedPreset};
+use proto::{ChatItem, Emote, GameEvent, QuickChat, C2S, S2C};
 use std::cell::RefCell;
+use std::rc::Rc;
 use worker::*;
 
+mod harness;
+
 struct MockGameClient {
     pub sent_messages: RefCell<Vec<Vec<u8>>>,
 }
@@ -38,30 +51,106 @@ impl GameClient for MockGameClient {
     }
 }
 
+/// Client that shares its outbox with the test so messages can be inspected
+/// after the client has been moved into `GameState`
+#[derive(Clone)]
+struct SharedMockClient {
+    msgs: Rc<RefCell<Vec<Vec<u8>>>>,
+}
+
+impl SharedMockClient {
+    fn new() -> (Self, Rc<RefCell<Vec<Vec<u8>>>>) {
+        let msgs = Rc::new(RefCell::new(Vec::new()));
+        (Self { msgs: msgs.clone() }, msgs)
+    }
+}
+
+impl GameClient for SharedMockClient {
+    fn send_bytes(&self, bytes: &[u8]) -> Result<()> {
+        self.msgs.borrow_mut().push(bytes.to_vec());
+        Ok(())
+    }
+}
+
+/// Client that records whether the server closed it
+struct ClosableMockClient {
+    closed: Rc<RefCell<Option<String>>>,
+}
+
+impl ClosableMockClient {
+    fn new() -> (Self, Rc<RefCell<Option<String>>>) {
+        let closed = Rc::new(RefCell::new(None));
+        (
+            Self {
+                closed: closed.clone(),
+            },
+            closed,
+        )
+    }
+}
+
+impl GameClient for ClosableMockClient {
+    fn send_bytes(&self, _bytes: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    fn close(&self, reason: &str) -> Result<()> {
+        *self.closed.borrow_mut() = Some(reason.to_string());
+        Ok(())
+    }
+}
+
+fn decode_all(msgs: &Rc<RefCell<Vec<Vec<u8>>>>) -> Vec<S2C> {
+    msgs.borrow()
+        .iter()
+        .map(|bytes| S2C::from_bytes(bytes).expect("Valid S2C message"))
+        .collect()
+}
+
 struct MockEnv {
-    time_ms: u64,
+    time_ms: Rc<RefCell<u64>>,
+    metrics: Rc<InMemoryMetrics>,
 }
 
 impl MockEnv {
     fn new() -> Self {
-        Self { time_ms: 1000 }
+        Self {
+            time_ms: Rc::new(RefCell::new(1000)),
+            metrics: Rc::new(InMemoryMetrics::new()),
+        }
+    }
+
+    /// Env plus a handle the test can use to inspect recorded metrics
+    fn with_metrics() -> (Self, Rc<InMemoryMetrics>) {
+        let env = Self::new();
+        let metrics = env.metrics.clone();
+        (env, metrics)
+    }
+
+    /// Env plus a handle the test can use to advance the clock
+    fn with_clock() -> (Self, Rc<RefCell<u64>>) {
+        let env = Self::new();
+        let clock = env.time_ms.clone();
+        (env, clock)
     }
 }
 
 impl Environment for MockEnv {
     fn now(&self) -> u64 {
-        self.time_ms
+        *self.time_ms.borrow()
     }
     fn log(&self, _msg: String) {
         // No-op for tests or println!(_msg)
     }
+    fn metrics(&self) -> &dyn Metrics {
+        &*self.metrics
+    }
 }
 
 #[test]
 fn test_game_initialization() {
     let gs = GameState::new(Box::new(MockEnv::new()));
     assert_eq!(gs.clients.len(), 0);
-    assert_eq!(gs.next_player_id, 0);
     assert_eq!(gs.match_state, MatchState::Waiting);
 }
 
@@ -117,7 +206,7 @@ fn test_handle_input() {
     gs.add_player(client0);
 
     // Send input for player 0
-    gs.handle_input(0, 1.0); // Move down
+    gs.handle_input(0, 0, 1.0, 0).unwrap(); // Move down
 
     // Check if input queue has it
     let inputs = gs.net_queue.pop_inputs();
@@ -149,7 +238,7 @@ fn test_broadcast_state() {
 
     gs.add_player(client);
 
-    gs.broadcast_state();
+    gs.send_snapshots(true);
 
     assert_eq!(messages.borrow().len(), 1);
 
@@ -161,3 +250,1703 @@ fn test_broadcast_state() {
         _ => panic!("Expected GameState message"),
     }
 }
+
+#[test]
+fn test_paddle_hit_broadcasts_event() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (client0, msgs0) = SharedMockClient::new();
+    gs.add_player(Box::new(client0));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.match_state = MatchState::Playing;
+
+    // Ball just in front of the left paddle, moving into it
+    let paddle_x = gs.config.paddle_x(0);
+    for (_e, ball) in gs.world.query_mut::<&mut Ball>() {
+        ball.pos = glam::Vec2::new(paddle_x + 0.6, 12.0);
+        ball.vel = glam::Vec2::new(-12.0, 0.0);
+    }
+
+    gs.step();
+    gs.broadcast_events();
+
+    let events = decode_all(&msgs0)
+        .into_iter()
+        .find_map(|msg| match msg {
+            S2C::Events { tick, events } => Some((tick, events)),
+            _ => None,
+        })
+        .expect("Should broadcast an Events message");
+    assert_eq!(events.0, 1);
+    assert!(matches!(
+        events.1.as_slice(),
+        [GameEvent::PaddleHit { player_id: 0, .. }]
+    ));
+
+    // Nothing pending means nothing sent
+    let count = msgs0.borrow().len();
+    gs.broadcast_events();
+    assert_eq!(msgs0.borrow().len(), count);
+}
+
+#[test]
+fn test_final_point_event_precedes_game_over() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (client0, msgs0) = SharedMockClient::new();
+    gs.add_player(Box::new(client0));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.match_state = MatchState::Playing;
+    gs.score.left = gs.config.win_score - 1;
+
+    let width = gs.map.width;
+    for (_e, ball) in gs.world.query_mut::<&mut Ball>() {
+        ball.pos = glam::Vec2::new(width - 0.1, 12.0);
+        ball.vel = glam::Vec2::new(12.0, 0.0);
+    }
+    msgs0.borrow_mut().clear();
+
+    assert_eq!(gs.step(), Some(0));
+
+    let msgs = decode_all(&msgs0);
+    assert_eq!(msgs.len(), 2);
+    match &msgs[0] {
+        S2C::Events { events, .. } => {
+            assert_eq!(events.as_slice(), [GameEvent::PointScored { scorer: 0 }]);
+        }
+        other => panic!("Expected Events, got {other:?}"),
+    }
+    assert!(matches!(msgs[1], S2C::GameOver { winner: 0 }));
+}
+
+#[test]
+fn test_ping_reports_server_times() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    gs.add_player(Box::new(MockGameClient::new()));
+
+    match gs.handle_ping(Some(0), 1234, 0, 0) {
+        S2C::Pong {
+            t_ms,
+            server_recv_ms,
+            server_send_ms,
+        } => {
+            assert_eq!(t_ms, 1234);
+            assert_eq!(server_recv_ms, 1000);
+            assert!(server_send_ms >= server_recv_ms);
+        }
+        other => panic!("Expected Pong, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_snapshots_and_game_start_carry_server_time() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (client0, msgs0) = SharedMockClient::new();
+    gs.add_player(Box::new(client0));
+    gs.add_player(Box::new(MockGameClient::new()));
+
+    // Run the countdown to completion
+    while !gs.tick_countdown() {}
+    gs.send_snapshots(true);
+
+    let msgs = decode_all(&msgs0);
+    assert!(msgs.iter().any(|m| matches!(
+        m,
+        S2C::GameStart {
+            server_time_ms: 1000
+        }
+    )));
+    match msgs.last() {
+        Some(S2C::GameState(snapshot)) => assert_eq!(snapshot.server_time_ms, 1000),
+        other => panic!("Expected GameState, got {other:?}"),
+    }
+}
+
+fn chat_items(msgs: &Rc<RefCell<Vec<Vec<u8>>>>) -> Vec<(u8, ChatItem)> {
+    decode_all(msgs)
+        .into_iter()
+        .filter_map(|msg| match msg {
+            S2C::Chat { player_id, item } => Some((player_id, item)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_chat_relayed_to_both_players() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (client0, msgs0) = SharedMockClient::new();
+    let (client1, msgs1) = SharedMockClient::new();
+    gs.add_player(Box::new(client0));
+    gs.add_player(Box::new(client1));
+
+    let wave = ChatItem::Emote(Emote::Wave);
+    assert!(gs.handle_chat(0, wave));
+
+    assert_eq!(chat_items(&msgs0), vec![(0, wave)]);
+    assert_eq!(chat_items(&msgs1), vec![(0, wave)]);
+
+    // Unknown senders are ignored
+    assert!(!gs.handle_chat(5, wave));
+}
+
+#[test]
+fn test_chat_cooldown() {
+    let (env, clock) = MockEnv::with_clock();
+    let mut gs = GameState::new(Box::new(env));
+    let (client1, msgs1) = SharedMockClient::new();
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.add_player(Box::new(client1));
+
+    let nice = ChatItem::Phrase(QuickChat::NiceShot);
+    assert!(gs.handle_chat(0, nice));
+    assert!(
+        !gs.handle_chat(0, nice),
+        "Second item within cooldown is dropped"
+    );
+
+    // Cooldown is per player
+    assert!(gs.handle_chat(1, nice));
+
+    *clock.borrow_mut() += CHAT_COOLDOWN_MS;
+    assert!(gs.handle_chat(0, nice));
+
+    assert_eq!(chat_items(&msgs1), vec![(0, nice), (1, nice), (0, nice)]);
+}
+
+#[test]
+fn test_muted_player_chat_not_delivered() {
+    let (env, clock) = MockEnv::with_clock();
+    let mut gs = GameState::new(Box::new(env));
+    let (client0, msgs0) = SharedMockClient::new();
+    let (client1, msgs1) = SharedMockClient::new();
+    gs.add_player(Box::new(client0));
+    gs.add_player(Box::new(client1));
+
+    gs.set_muted(1, 0, true);
+    let angry = ChatItem::Emote(Emote::Angry);
+    assert!(gs.handle_chat(0, angry));
+
+    assert!(chat_items(&msgs1).is_empty(), "Muted chat must not arrive");
+    assert_eq!(
+        chat_items(&msgs0),
+        vec![(0, angry)],
+        "Sender still sees own chat"
+    );
+
+    gs.set_muted(1, 0, false);
+    *clock.borrow_mut() += CHAT_COOLDOWN_MS;
+    assert!(gs.handle_chat(0, angry));
+    assert_eq!(chat_items(&msgs1), vec![(0, angry)]);
+}
+
+#[test]
+fn test_spectators_do_not_take_player_slots() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    gs.add_spectator(Box::new(MockGameClient::new()), false);
+    gs.add_spectator(Box::new(MockGameClient::new()), false);
+
+    assert!(gs.add_player(Box::new(MockGameClient::new())).is_some());
+    assert!(gs.add_player(Box::new(MockGameClient::new())).is_some());
+    assert_eq!(gs.spectators.len(), 2);
+    assert_eq!(gs.match_state, MatchState::Countdown);
+}
+
+#[test]
+fn test_spectator_receives_lifecycle_and_count() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (client0, msgs0) = SharedMockClient::new();
+    gs.add_player(Box::new(client0));
+    gs.add_player(Box::new(MockGameClient::new()));
+
+    let (spectator, spec_msgs) = SharedMockClient::new();
+    let spectator_id = gs.add_spectator(Box::new(spectator), false);
+
+    // Joined mid-countdown: caught up, then told how many are watching
+    let msgs = decode_all(&spec_msgs);
+    assert!(matches!(msgs[0], S2C::MatchFound));
+    assert!(matches!(msgs[1], S2C::GameState(_)));
+    assert!(matches!(msgs[2], S2C::SpectatorCount { count: 1 }));
+    assert!(decode_all(&msgs0)
+        .iter()
+        .any(|m| matches!(m, S2C::SpectatorCount { count: 1 })));
+
+    while !gs.tick_countdown() {}
+    gs.send_snapshots(true);
+    let msgs = decode_all(&spec_msgs);
+    assert!(msgs.iter().any(|m| matches!(m, S2C::GameStart { .. })));
+    assert!(matches!(msgs.last(), Some(S2C::GameState(_))));
+
+    gs.remove_spectator(spectator_id);
+    assert!(matches!(
+        decode_all(&msgs0).last(),
+        Some(S2C::SpectatorCount { count: 0 })
+    ));
+}
+
+#[test]
+fn test_delayed_spectator_snapshots() {
+    let (env, clock) = MockEnv::with_clock();
+    let mut gs = GameState::new(Box::new(env));
+    let (client0, msgs0) = SharedMockClient::new();
+    gs.add_player(Box::new(client0));
+    let (spectator, spec_msgs) = SharedMockClient::new();
+    gs.add_spectator(Box::new(spectator), true);
+
+    let snapshots = |msgs: &Rc<RefCell<Vec<Vec<u8>>>>| {
+        decode_all(msgs)
+            .into_iter()
+            .filter(|m| matches!(m, S2C::GameState(_)))
+            .count()
+    };
+
+    gs.send_snapshots(true);
+    assert_eq!(snapshots(&msgs0), 1, "Players get snapshots immediately");
+    assert_eq!(snapshots(&spec_msgs), 0, "Delayed spectator waits");
+
+    *clock.borrow_mut() += SPECTATOR_DELAY_MS;
+    gs.send_snapshots(true);
+    assert_eq!(
+        snapshots(&spec_msgs),
+        2,
+        "Catch-up and first snapshot released after delay"
+    );
+    assert_eq!(gs.delayed_messages.len(), 1);
+}
+
+#[test]
+fn test_ping_refreshes_only_sender_activity() {
+    let (env, clock) = MockEnv::with_clock();
+    let mut gs = GameState::new(Box::new(env));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.add_player(Box::new(MockGameClient::new()));
+
+    *clock.borrow_mut() += 60_000;
+    gs.handle_ping(Some(1), 0, 0, 0);
+
+    assert_eq!(gs.clients[&0].last_activity, 1);
+    assert_eq!(gs.clients[&1].last_activity, 61);
+
+    // Pings from non-players (spectators, unjoined sockets) refresh nobody
+    *clock.borrow_mut() += 60_000;
+    gs.handle_ping(None, 0, 0, 0);
+    assert_eq!(gs.clients[&1].last_activity, 61);
+}
+
+#[test]
+fn test_remove_player_is_idempotent() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (client0, msgs0) = SharedMockClient::new();
+    gs.add_player(Box::new(client0));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.match_state = MatchState::Playing;
+
+    // Close and error both fire for the same socket
+    gs.remove_player(1);
+    gs.remove_player(1);
+
+    let notices = decode_all(&msgs0)
+        .iter()
+        .filter(|m| matches!(m, S2C::OpponentReconnecting { .. }))
+        .count();
+    assert_eq!(notices, 1);
+    assert_eq!(gs.clients.len(), 1);
+    assert!(gs.clients.contains_key(&0), "Remaining player must be kept");
+}
+
+#[test]
+fn test_kick_player_closes_socket() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (client0, closed0) = ClosableMockClient::new();
+    gs.add_player(Box::new(client0));
+
+    gs.kick_player(0, "Idle timeout");
+
+    assert_eq!(closed0.borrow().as_deref(), Some("Idle timeout"));
+    assert!(gs.clients.is_empty());
+}
+
+/// Two players mid-match with player 1 dropped; returns player 0's outbox
+/// and player 1's session token
+fn drop_player_mid_match(gs: &mut GameState) -> (Rc<RefCell<Vec<Vec<u8>>>>, u64) {
+    let (client0, msgs0) = SharedMockClient::new();
+    gs.add_player(Box::new(client0));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.match_state = MatchState::Playing;
+    let token = gs.clients[&1].session_token;
+
+    gs.remove_player(1);
+    (msgs0, token)
+}
+
+#[test]
+fn test_drop_mid_match_holds_slot() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (msgs0, _token) = drop_player_mid_match(&mut gs);
+
+    assert_eq!(gs.match_state, MatchState::AwaitingReconnect);
+    assert!(matches!(
+        decode_all(&msgs0).last(),
+        Some(S2C::OpponentReconnecting {
+            player_id: 1,
+            seconds: RECONNECT_GRACE_SECONDS
+        })
+    ));
+
+    // Paddle stays in the world while the slot is held
+    let paddles = gs.world.query::<&game_core::Paddle>().iter().count();
+    assert_eq!(paddles, 2);
+
+    // Strangers cannot take the held slot
+    assert_eq!(gs.add_player(Box::new(MockGameClient::new())), None);
+}
+
+#[test]
+fn test_rejoin_with_token_resumes_match() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (msgs0, token) = drop_player_mid_match(&mut gs);
+
+    // Wrong token is rejected and the slot stays held
+    assert_eq!(
+        gs.rejoin_player(Box::new(MockGameClient::new()), token ^ 1),
+        None
+    );
+    assert_eq!(gs.match_state, MatchState::AwaitingReconnect);
+
+    let (client1, msgs1) = SharedMockClient::new();
+    assert_eq!(gs.rejoin_player(Box::new(client1), token), Some(1));
+    assert_eq!(gs.match_state, MatchState::Playing);
+    assert!(gs.pending_reconnect.is_none());
+
+    let rejoined = decode_all(&msgs1);
+    assert!(matches!(
+        rejoined[0],
+        S2C::Welcome {
+            player_id: 1,
+            session_token,
+            ..
+        } if session_token == token
+    ));
+    assert!(matches!(rejoined[1], S2C::GameStart { .. }));
+    assert!(matches!(
+        decode_all(&msgs0).last(),
+        Some(S2C::OpponentReconnected { player_id: 1 })
+    ));
+
+    // The token is single use
+    assert_eq!(
+        gs.rejoin_player(Box::new(MockGameClient::new()), token),
+        None
+    );
+}
+
+#[test]
+fn test_reconnect_grace_expiry_forfeits() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (msgs0, token) = drop_player_mid_match(&mut gs);
+
+    for _ in 1..RECONNECT_GRACE_SECONDS {
+        gs.tick_reconnect_grace();
+    }
+    assert_eq!(gs.match_state, MatchState::AwaitingReconnect);
+    assert!(matches!(
+        decode_all(&msgs0).last(),
+        Some(S2C::OpponentReconnecting { seconds: 1, .. })
+    ));
+
+    gs.tick_reconnect_grace();
+    assert_eq!(gs.match_state, MatchState::GameOver);
+    assert!(matches!(
+        decode_all(&msgs0).last(),
+        Some(S2C::GameOver { winner: 0 })
+    ));
+    assert_eq!(
+        gs.rejoin_player(Box::new(MockGameClient::new()), token),
+        None
+    );
+}
+
+#[test]
+fn test_both_players_gone_abandons_held_slot() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    drop_player_mid_match(&mut gs);
+
+    gs.score.left = 3;
+    gs.remove_player(0);
+
+    assert_eq!(gs.match_state, MatchState::Abandoned);
+    assert!(gs.pending_reconnect.is_none());
+    let paddles = gs.world.query::<&game_core::Paddle>().iter().count();
+    assert_eq!(paddles, 0);
+
+    // The next player to arrive starts a new game
+    gs.add_player(Box::new(MockGameClient::new()));
+    assert_eq!(gs.match_state, MatchState::Waiting);
+    assert_eq!(gs.score.left, 0);
+}
+
+#[test]
+fn test_illegal_transition_is_refused() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    playing_match(&mut gs);
+
+    assert!(!gs.transition(MatchAction::RematchAgreed));
+    assert_eq!(gs.match_state, MatchState::Playing);
+    // A rematch vote mid-game goes nowhere
+    assert!(!gs.request_rematch(0, false));
+    assert_eq!(gs.match_state, MatchState::Playing);
+}
+
+#[test]
+fn test_invalid_inputs_rejected_then_disconnected() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (client0, closed0) = ClosableMockClient::new();
+    gs.add_player(Box::new(client0));
+    gs.add_player(Box::new(MockGameClient::new()));
+
+    // Spoofed ids and NaN never reach the simulation
+    assert!(matches!(
+        gs.handle_input(0, 1, 5.0, 0),
+        Err(InputError::SpoofedPlayerId {
+            claimed: 1,
+            actual: 0
+        })
+    ));
+    assert_eq!(
+        gs.handle_input(0, 0, f32::NAN, 0),
+        Err(InputError::NonFiniteY)
+    );
+    assert!(gs.net_queue.pop_inputs().is_empty());
+
+    for _ in 2..MAX_INPUT_VIOLATIONS - 1 {
+        let _ = gs.handle_input(0, 0, f32::NAN, 0);
+    }
+    assert!(closed0.borrow().is_none());
+
+    assert_eq!(
+        gs.handle_input(0, 0, f32::NAN, 0),
+        Err(InputError::TooManyViolations {
+            count: MAX_INPUT_VIOLATIONS
+        })
+    );
+    assert_eq!(closed0.borrow().as_deref(), Some("Too many invalid inputs"));
+    assert!(!gs.clients.contains_key(&0));
+}
+
+#[test]
+fn test_input_abuse_mid_match_forfeits_without_holding_slot() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    gs.add_player(Box::new(MockGameClient::new()));
+    let (client1, closed1) = ClosableMockClient::new();
+    gs.add_player(Box::new(client1));
+    let token = gs.clients[&1].session_token;
+    gs.match_state = MatchState::Playing;
+
+    for _ in 0..MAX_INPUT_VIOLATIONS {
+        let _ = gs.handle_input(1, 1, f32::NAN, 0);
+    }
+    assert_eq!(closed1.borrow().as_deref(), Some("Too many invalid inputs"));
+    assert!(gs.pending_reconnect.is_none());
+    assert_eq!(gs.match_state, MatchState::GameOver);
+
+    // The token died with the slot
+    assert_eq!(
+        gs.rejoin_player(Box::new(MockGameClient::new()), token),
+        None
+    );
+}
+
+#[test]
+fn test_take_persist_only_at_key_moments() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    assert!(gs.take_persist().is_none());
+
+    gs.add_player(Box::new(MockGameClient::new()));
+    assert!(gs.take_persist().is_some());
+    assert!(
+        gs.take_persist().is_none(),
+        "Nothing changed since last save"
+    );
+
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.match_state = MatchState::Playing;
+    let _ = gs.take_persist();
+
+    // Ordinary ticks are not worth a storage write
+    gs.step();
+    assert!(gs.take_persist().is_none());
+}
+
+/// Save a mid-match state with the given score and round-trip it through storage encoding
+fn saved_mid_match(left: u8, right: u8) -> (PersistedMatch, u64, u64) {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.match_state = MatchState::Playing;
+    gs.score.left = left;
+    gs.score.right = right;
+    gs.tick = 900;
+    let tokens = (gs.clients[&0].session_token, gs.clients[&1].session_token);
+
+    let bytes = postcard::to_allocvec(&gs.persisted()).unwrap();
+    (postcard::from_bytes(&bytes).unwrap(), tokens.0, tokens.1)
+}
+
+#[test]
+fn test_restore_resumes_match_with_surviving_sockets() {
+    let (saved, token0, token1) = saved_mid_match(3, 2);
+
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    gs.restore(
+        saved,
+        vec![
+            (0, Box::new(MockGameClient::new()) as Box<dyn GameClient>),
+            (1, Box::new(MockGameClient::new())),
+        ],
+    );
+
+    assert_eq!(gs.match_state, MatchState::Playing);
+    assert_eq!((gs.score.left, gs.score.right), (3, 2));
+    assert_eq!(gs.tick, 900);
+    assert_eq!(gs.clients[&0].session_token, token0);
+    assert_eq!(gs.clients[&1].session_token, token1);
+    let paddles = gs.world.query::<&game_core::Paddle>().iter().count();
+    assert_eq!(paddles, 2);
+}
+
+#[test]
+fn test_restore_holds_slot_for_lost_socket() {
+    let (saved, _token0, token1) = saved_mid_match(1, 4);
+
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    gs.restore(saved, vec![(0, Box::new(MockGameClient::new()))]);
+
+    assert_eq!(gs.match_state, MatchState::AwaitingReconnect);
+    assert_eq!(
+        gs.rejoin_player(Box::new(MockGameClient::new()), token1),
+        Some(1)
+    );
+    assert_eq!(gs.match_state, MatchState::Playing);
+    assert_eq!((gs.score.left, gs.score.right), (1, 4));
+}
+
+#[test]
+fn test_restore_without_players_starts_fresh() {
+    let (saved, _, _) = saved_mid_match(2, 2);
+
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    gs.restore(saved, Vec::new());
+
+    assert_eq!(gs.match_state, MatchState::Waiting);
+    assert!(gs.clients.is_empty());
+    assert_eq!((gs.score.left, gs.score.right), (0, 0));
+    assert_eq!(gs.tick, 0);
+}
+
+fn count_snapshots(msgs: &Rc<RefCell<Vec<Vec<u8>>>>) -> usize {
+    decode_all(msgs)
+        .iter()
+        .filter(|m| matches!(m, S2C::GameState(_)))
+        .count()
+}
+
+#[test]
+fn test_snapshot_rate_follows_reported_rtt() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (fast, fast_msgs) = SharedMockClient::new();
+    let (slow, slow_msgs) = SharedMockClient::new();
+    gs.add_player(Box::new(fast));
+    gs.add_player(Box::new(slow));
+    gs.handle_ping(Some(0), 0, 20, 0);
+    gs.handle_ping(Some(1), 0, 400, 0);
+    fast_msgs.borrow_mut().clear();
+    slow_msgs.borrow_mut().clear();
+
+    for tick in 1..=24 {
+        gs.tick = tick;
+        gs.send_snapshots(false);
+    }
+
+    assert_eq!(count_snapshots(&fast_msgs), 12);
+    assert_eq!(count_snapshots(&slow_msgs), 4);
+}
+
+#[test]
+fn test_paddle_hit_sends_snapshot_immediately() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (client0, msgs0) = SharedMockClient::new();
+    gs.add_player(Box::new(client0));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.match_state = MatchState::Playing;
+    gs.tick = 1;
+    gs.send_snapshots(false);
+    msgs0.borrow_mut().clear();
+
+    let paddle_x = gs.config.paddle_x(0);
+    for (_e, ball) in gs.world.query_mut::<&mut Ball>() {
+        ball.pos = glam::Vec2::new(paddle_x + 0.6, 12.0);
+        ball.vel = glam::Vec2::new(-12.0, 0.0);
+    }
+    gs.step();
+
+    // Tick 2 is not due on the default cadence, but a hit forces it
+    assert!(gs.has_priority_events());
+    gs.send_snapshots(gs.has_priority_events());
+    assert_eq!(count_snapshots(&msgs0), 1);
+}
+
+/// Two players mid-match, player 0's round trip timed at `rtt_ms`, with paddle
+/// history recorded; then player 0's paddle moves away from where its player
+/// saw it
+fn lag_comp_setup(clock_ms_later: u64, rtt_ms: u64) -> (GameState, Rc<RefCell<u64>>, f32) {
+    let (env, clock) = MockEnv::with_clock();
+    let mut gs = GameState::new(Box::new(env));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.match_state = MatchState::Playing;
+    let pong_sent_ms = *clock.borrow();
+    gs.handle_ping(Some(0), 0, 0, 0);
+    *clock.borrow_mut() += rtt_ms;
+    gs.handle_pong_echo(0, pong_sent_ms);
+    let seen_y = gs.map.paddle_spawn(0).y;
+
+    // History: paddle at spawn when the player made their input
+    gs.step();
+
+    for (_e, (paddle, intent)) in gs
+        .world
+        .query_mut::<(&mut game_core::Paddle, &mut game_core::PaddleIntent)>()
+    {
+        if paddle.player_id == 0 {
+            paddle.y = seen_y + 8.0;
+            intent.target_y = seen_y + 8.0;
+        }
+    }
+    for _ in 0..clock_ms_later / 50 {
+        *clock.borrow_mut() += 50;
+        gs.step();
+    }
+    (gs, clock, seen_y)
+}
+
+fn aim_ball_at_left_paddle(gs: &mut GameState, y: f32) {
+    let paddle_x = gs.config.paddle_x(0);
+    for (_e, ball) in gs.world.query_mut::<&mut Ball>() {
+        ball.pos = glam::Vec2::new(paddle_x + 0.6, y);
+        ball.vel = glam::Vec2::new(-12.0, 0.0);
+    }
+    gs.pending_events.clear();
+}
+
+fn left_paddle_hit(gs: &GameState) -> bool {
+    gs.pending_events
+        .iter()
+        .any(|e| matches!(e, GameEvent::PaddleHit { player_id: 0, .. }))
+}
+
+#[test]
+fn test_lag_compensated_hit_uses_paddle_at_input_time() {
+    let (mut gs, clock, seen_y) = lag_comp_setup(100, 120);
+    let input_ms = *clock.borrow() - 100;
+    gs.handle_input(0, 0, seen_y + 8.0, input_ms).unwrap();
+
+    aim_ball_at_left_paddle(&mut gs, seen_y);
+    gs.step();
+
+    assert!(
+        left_paddle_hit(&gs),
+        "Hit where the player saw their paddle"
+    );
+}
+
+#[test]
+fn test_rewind_is_limited_to_the_players_rtt() {
+    let (mut gs, clock, seen_y) = lag_comp_setup(100, 40);
+    // Stamped 100ms ago, but a 40ms round trip cannot have seen that far back
+    let input_ms = *clock.borrow() - 100;
+    gs.handle_input(0, 0, seen_y + 8.0, input_ms).unwrap();
+
+    aim_ball_at_left_paddle(&mut gs, seen_y);
+    gs.step();
+
+    assert!(!left_paddle_hit(&gs));
+}
+
+#[test]
+fn test_rewind_needs_a_server_timed_rtt() {
+    let (mut gs, clock, seen_y) = lag_comp_setup(100, 0);
+    // The client claims a long round trip and echoes a Pong it never got
+    gs.handle_ping(Some(0), 0, 500, 0);
+    gs.handle_pong_echo(0, 1);
+    let input_ms = *clock.borrow() - 100;
+    gs.handle_input(0, 0, seen_y + 8.0, input_ms).unwrap();
+
+    aim_ball_at_left_paddle(&mut gs, seen_y);
+    gs.step();
+
+    assert!(!left_paddle_hit(&gs));
+}
+
+#[test]
+fn test_rewound_paddle_replaces_live_one() {
+    let (mut gs, clock, seen_y) = lag_comp_setup(100, 120);
+    let input_ms = *clock.borrow() - 100;
+    gs.handle_input(0, 0, seen_y + 8.0, input_ms).unwrap();
+
+    // The live paddle is at the ball, but the player saw it elsewhere
+    aim_ball_at_left_paddle(&mut gs, seen_y + 8.0);
+    gs.step();
+
+    assert!(!left_paddle_hit(&gs));
+}
+
+#[test]
+fn test_rewind_applies_to_one_step() {
+    let (mut gs, clock, seen_y) = lag_comp_setup(100, 120);
+    let input_ms = *clock.borrow() - 100;
+    gs.handle_input(0, 0, seen_y + 8.0, input_ms).unwrap();
+    *clock.borrow_mut() += 50;
+    gs.step();
+
+    aim_ball_at_left_paddle(&mut gs, seen_y);
+    gs.step();
+
+    assert!(!left_paddle_hit(&gs), "No rewind without a fresh input");
+}
+
+#[test]
+fn test_unstamped_input_gets_no_compensation() {
+    let (mut gs, _clock, seen_y) = lag_comp_setup(100, 120);
+    gs.handle_input(0, 0, seen_y + 8.0, 0).unwrap();
+
+    aim_ball_at_left_paddle(&mut gs, seen_y);
+    gs.step();
+
+    assert!(!left_paddle_hit(&gs));
+}
+
+#[test]
+fn test_rewind_window_is_bounded() {
+    let (mut gs, clock, seen_y) = lag_comp_setup(400, 400);
+    // Claims to be from 400ms ago, but only MAX_REWIND_MS is honoured
+    let input_ms = *clock.borrow() - 400;
+    gs.handle_input(0, 0, seen_y + 8.0, input_ms).unwrap();
+
+    aim_ball_at_left_paddle(&mut gs, seen_y);
+    gs.step();
+
+    assert!(!left_paddle_hit(&gs));
+}
+
+#[test]
+fn test_finished_match_produces_result() {
+    let (env, clock) = MockEnv::with_clock();
+    let mut gs = GameState::new(Box::new(env));
+    gs.set_code(b"ABCDE");
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.match_state = MatchState::Countdown;
+    gs.countdown_remaining = 0;
+    gs.tick_countdown();
+    assert!(gs.take_finished_result().is_none());
+
+    // One return, then the winning point
+    let paddle_y = gs.map.paddle_spawn(0).y;
+    aim_ball_at_left_paddle(&mut gs, paddle_y);
+    gs.step();
+    gs.score.left = gs.config.win_score - 1;
+    let width = gs.map.width;
+    for (_e, ball) in gs.world.query_mut::<&mut Ball>() {
+        ball.pos = glam::Vec2::new(width - 0.1, 12.0);
+        ball.vel = glam::Vec2::new(12.0, 0.0);
+    }
+    *clock.borrow_mut() += 90_000;
+    assert_eq!(gs.step(), Some(0));
+
+    let result = gs
+        .take_finished_result()
+        .expect("Result for finished match");
+    assert_eq!(result.code, "ABCDE");
+    assert_eq!(result.winner, 0);
+    assert!(!result.forfeit);
+    assert_eq!(result.players[0].score, gs.config.win_score);
+    assert_eq!(result.duration_ms, 90_000);
+    assert_eq!(result.rallies.paddle_hits, 1);
+    assert_eq!(result.rallies.longest_rally, 1);
+    assert_eq!(result.ruleset.win_score, gs.config.win_score);
+    assert!(
+        gs.take_finished_result().is_none(),
+        "Result is handed over once"
+    );
+}
+
+#[test]
+fn test_reconnect_forfeit_is_recorded() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    drop_player_mid_match(&mut gs);
+    for _ in 0..RECONNECT_GRACE_SECONDS {
+        gs.tick_reconnect_grace();
+    }
+
+    let result = gs.take_finished_result().expect("Forfeit is recorded");
+    assert_eq!(result.winner, 0);
+    assert!(result.forfeit);
+}
+
+#[test]
+fn test_settings_apply_before_join_only() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let settings = MatchSettings {
+        win_score: 11,
+        speed: SpeedPreset::Fast,
+        paddle_size: PaddleSize::Large,
+        layout: MapLayout::Wide,
+    };
+    assert!(gs.apply_settings(settings).is_ok());
+    assert_eq!(gs.config.win_score, 11);
+    assert_eq!(gs.map.width, 40.0);
+    assert_eq!(gs.persisted().settings, settings);
+
+    gs.add_player(Box::new(MockGameClient::new()));
+    let ball_x = gs
+        .world
+        .query::<&Ball>()
+        .iter()
+        .next()
+        .map(|(_, ball)| ball.pos.x);
+    assert_eq!(
+        ball_x,
+        Some(20.0),
+        "Ball spawns in the middle of the wide arena"
+    );
+
+    assert!(
+        gs.apply_settings(MatchSettings::default()).is_err(),
+        "Settings are fixed once a player has joined"
+    );
+    assert_eq!(gs.config.win_score, 11);
+}
+
+#[test]
+fn test_invalid_settings_rejected() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let settings = MatchSettings {
+        win_score: 0,
+        ..MatchSettings::default()
+    };
+    assert!(gs.apply_settings(settings).is_err());
+    assert_eq!(gs.settings, MatchSettings::default());
+}
+
+#[test]
+fn test_rejoin_welcome_carries_settings() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let settings = MatchSettings {
+        win_score: 3,
+        ..MatchSettings::default()
+    };
+    gs.apply_settings(settings).unwrap();
+    let (_msgs0, token) = drop_player_mid_match(&mut gs);
+
+    let (client1, msgs1) = SharedMockClient::new();
+    gs.rejoin_player(Box::new(client1), token).unwrap();
+    assert!(matches!(
+        decode_all(&msgs1)[0],
+        S2C::Welcome { settings: sent, .. } if sent == settings
+    ));
+}
+
+#[test]
+fn test_result_carries_profile_ids() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    drop_player_mid_match(&mut gs);
+    gs.set_profile(0, Some("alice".to_string()));
+    gs.set_profile(1, Some("not a valid id!".to_string()));
+    for _ in 0..RECONNECT_GRACE_SECONDS {
+        gs.tick_reconnect_grace();
+    }
+
+    let result = gs.take_finished_result().unwrap();
+    assert_eq!(result.players[0].profile_id.as_deref(), Some("alice"));
+    assert_eq!(
+        result.players[1].profile_id, None,
+        "Invalid ids play unrated"
+    );
+    assert!(result.rated_profiles().is_none());
+    assert_eq!(
+        gs.persisted().profiles.get(&0).map(String::as_str),
+        Some("alice")
+    );
+}
+
+#[test]
+fn test_tournament_link_before_join_only() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    assert!(gs.link_tournament("not-hex".to_string()).is_err());
+    assert!(gs.link_tournament("abcdef012345".to_string()).is_ok());
+    assert_eq!(
+        gs.persisted().tournament_id.as_deref(),
+        Some("abcdef012345")
+    );
+
+    gs.add_player(Box::new(MockGameClient::new()));
+    assert!(gs.link_tournament("0123456789ab".to_string()).is_err());
+    assert_eq!(gs.tournament_id.as_deref(), Some("abcdef012345"));
+}
+
+#[test]
+fn test_status_reports_players_and_rtt() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.handle_ping(Some(1), 0, 84, 0);
+    gs.score.left = 2;
+
+    let status = gs.status();
+    assert_eq!(status.match_state, gs.match_state);
+    assert_eq!(status.players.len(), 2);
+    assert_eq!(status.players[1].player_id, 1);
+    assert_eq!(status.players[1].rtt_ms, 84);
+    assert!(status.players.iter().all(|player| player.connected));
+    assert_eq!(status.score.left, 2);
+    assert_eq!(status.config.win_score, gs.config.win_score);
+
+    // A held slot is listed as disconnected
+    gs.match_state = MatchState::Playing;
+    gs.remove_player(1);
+    let status = gs.status();
+    assert_eq!(status.players.len(), 2);
+    assert!(!status.players[1].connected);
+    assert!(status.pending_reconnect.is_some());
+}
+
+#[test]
+fn test_status_never_reveals_session_tokens() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.match_state = MatchState::Playing;
+    let token = gs.clients[&1].session_token;
+    gs.remove_player(1);
+    assert_eq!(gs.pending_reconnect.unwrap().session_token, token);
+
+    let json = serde_json::to_string(&gs.status()).unwrap();
+    assert!(json.contains("\"pending_reconnect\":{\"player_id\":1"));
+    assert!(!json.contains("session_token"));
+    assert!(!json.contains(&token.to_string()));
+}
+
+#[test]
+fn test_admin_kick_forfeits_without_holding_slot() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    gs.add_player(Box::new(MockGameClient::new()));
+    let (client1, closed) = ClosableMockClient::new();
+    gs.add_player(Box::new(client1));
+    gs.match_state = MatchState::Playing;
+
+    assert!(gs.admin_kick(1));
+    assert_eq!(closed.borrow().as_deref(), Some("Kicked by admin"));
+    assert!(gs.pending_reconnect.is_none());
+    assert_eq!(gs.match_state, MatchState::GameOver);
+    let result = gs.take_finished_result().expect("Kick is recorded");
+    assert_eq!(result.winner, 0);
+    assert!(result.forfeit);
+
+    assert!(!gs.admin_kick(1));
+}
+
+#[test]
+fn test_admin_reset_restarts_from_any_state() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    assert!(!gs.reset_match());
+
+    let (client0, msgs0) = SharedMockClient::new();
+    gs.add_player(Box::new(client0));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.match_state = MatchState::Playing;
+    gs.score.right = 3;
+    gs.tick = 500;
+
+    assert!(gs.reset_match());
+    assert_eq!(gs.match_state, MatchState::Countdown);
+    assert_eq!(gs.score.right, 0);
+    assert_eq!(gs.tick, 0);
+    assert!(matches!(
+        decode_all(&msgs0).last(),
+        Some(S2C::Countdown { seconds: 3 })
+    ));
+
+    // With the opponent's slot only held, the remaining player waits for a new one
+    gs.match_state = MatchState::Playing;
+    gs.remove_player(1);
+    assert!(gs.reset_match());
+    assert_eq!(gs.match_state, MatchState::Waiting);
+    assert!(gs.pending_reconnect.is_none());
+}
+
+#[test]
+fn test_close_all_disconnects_everyone() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (client0, closed0) = ClosableMockClient::new();
+    let (spectator, closed_spectator) = ClosableMockClient::new();
+    gs.add_player(Box::new(client0));
+    gs.add_spectator(Box::new(spectator), false);
+
+    gs.close_all("Match ended by admin");
+    assert!(gs.clients.is_empty());
+    assert!(gs.spectators.is_empty());
+    assert_eq!(closed0.borrow().as_deref(), Some("Match ended by admin"));
+    assert_eq!(
+        closed_spectator.borrow().as_deref(),
+        Some("Match ended by admin")
+    );
+}
+
+#[test]
+fn test_outgoing_messages_are_counted() {
+    let (env, metrics) = MockEnv::with_metrics();
+    let mut gs = GameState::new(Box::new(env));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.add_player(Box::new(MockGameClient::new()));
+    let before = metrics.counter(names::MESSAGES_OUT);
+
+    let bytes = S2C::Countdown { seconds: 3 }.to_bytes().unwrap();
+    gs.broadcast_to_all(&S2C::Countdown { seconds: 3 });
+    assert_eq!(metrics.counter(names::MESSAGES_OUT), before + 2);
+    assert!(metrics.counter(names::BYTES_OUT) >= 2 * bytes.len() as u64);
+}
+
+#[test]
+fn test_steps_are_counted() {
+    let (env, metrics) = MockEnv::with_metrics();
+    let mut gs = GameState::new(Box::new(env));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.match_state = MatchState::Playing;
+
+    for _ in 0..5 {
+        gs.step();
+    }
+    assert_eq!(metrics.counter(names::TICKS), 5);
+
+    // Nothing is simulated outside of play
+    gs.match_state = MatchState::GameOver;
+    gs.step();
+    assert_eq!(metrics.counter(names::TICKS), 5);
+}
+
+#[test]
+fn test_tick_duration_needs_a_running_clock() {
+    // Like Workers, MockEnv's clock stands still while the steps run
+    let (env, metrics) = MockEnv::with_metrics();
+    let clock = env.time_ms.clone();
+    let mut gs = GameState::new(Box::new(env));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.match_state = MatchState::Playing;
+    gs.last_tick_time = *clock.borrow();
+
+    *clock.borrow_mut() += 100;
+    host::run_alarm(&mut gs);
+    assert!(metrics.counter(names::TICKS) > 0);
+    assert_eq!(metrics.samples(names::TICK_INTERVAL_MS), [100.0]);
+    assert!(metrics.samples(names::TICK_DURATION_MS).is_empty());
+}
+
+/// A lone human in a match that seats the bot as soon as they join
+fn match_with_bot() -> (GameState, Rc<RefCell<Vec<Vec<u8>>>>) {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    gs.set_bot_fill(BotFill::Immediate).unwrap();
+    let (client0, msgs0) = SharedMockClient::new();
+    gs.add_player(Box::new(client0));
+    assert!(gs.tick_bot());
+    (gs, msgs0)
+}
+
+#[test]
+fn test_bot_fill_chosen_before_join_only() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    assert!(gs.set_bot_fill(BotFill::AfterWait).is_ok());
+    assert_eq!(gs.persisted().bot_fill, BotFill::AfterWait);
+
+    gs.add_player(Box::new(MockGameClient::new()));
+    assert!(gs.set_bot_fill(BotFill::Never).is_err());
+    assert_eq!(gs.bot_fill, BotFill::AfterWait);
+}
+
+#[test]
+fn test_bot_joins_immediately_and_starts_countdown() {
+    let (gs, msgs0) = match_with_bot();
+
+    assert!(gs.is_bot(1));
+    assert_eq!(gs.clients.len(), 2);
+    assert_eq!(gs.match_state, MatchState::Countdown);
+    assert!(matches!(decode_all(&msgs0).last(), Some(S2C::MatchFound)));
+    assert!(gs.status().players[1].bot);
+}
+
+#[test]
+fn test_bot_joins_after_wait() {
+    let (env, clock) = MockEnv::with_clock();
+    let mut gs = GameState::new(Box::new(env));
+    gs.set_bot_fill(BotFill::AfterWait).unwrap();
+    gs.add_player(Box::new(MockGameClient::new()));
+
+    assert!(!gs.tick_bot());
+    *clock.borrow_mut() += BOT_FILL_WAIT_MS - 1;
+    assert!(!gs.tick_bot());
+    *clock.borrow_mut() += 1;
+    assert!(gs.tick_bot());
+    assert_eq!(gs.match_state, MatchState::Countdown);
+}
+
+#[test]
+fn test_no_bot_without_fill_option() {
+    let (env, clock) = MockEnv::with_clock();
+    let mut gs = GameState::new(Box::new(env));
+    gs.add_player(Box::new(MockGameClient::new()));
+    *clock.borrow_mut() += BOT_FILL_WAIT_MS * 10;
+    assert!(!gs.tick_bot());
+    assert_eq!(gs.match_state, MatchState::Waiting);
+}
+
+#[test]
+fn test_bot_paddle_tracks_ball() {
+    let (mut gs, _msgs0) = match_with_bot();
+    gs.match_state = MatchState::Playing;
+
+    // Ball heading for the bot's side low in the arena
+    let arena_height = gs.map.height;
+    for (_e, ball) in gs.world.query_mut::<&mut Ball>() {
+        ball.pos = glam::Vec2::new(16.0, arena_height - 2.0);
+        ball.vel = glam::Vec2::new(8.0, 0.0);
+    }
+    let bot_y = |gs: &GameState| {
+        gs.world
+            .query::<&game_core::Paddle>()
+            .iter()
+            .find(|(_e, paddle)| paddle.player_id == 1)
+            .map(|(_e, paddle)| paddle.y)
+            .unwrap()
+    };
+
+    let start_y = bot_y(&gs);
+    for _ in 0..10 {
+        gs.step();
+    }
+    assert!(bot_y(&gs) > start_y);
+}
+
+#[test]
+fn test_bot_leaves_with_last_player() {
+    let (mut gs, _msgs0) = match_with_bot();
+    gs.remove_player(0);
+
+    assert!(gs.clients.is_empty());
+    assert_eq!(gs.bot, None);
+    assert_eq!(gs.match_state, MatchState::Waiting);
+}
+
+#[test]
+fn test_bot_wins_forfeit_then_leaves() {
+    let (mut gs, _msgs0) = match_with_bot();
+    gs.match_state = MatchState::Playing;
+
+    // The human's slot is held like any other drop
+    gs.remove_player(0);
+    assert_eq!(gs.match_state, MatchState::AwaitingReconnect);
+    assert!(gs.is_bot(1));
+
+    for _ in 0..RECONNECT_GRACE_SECONDS {
+        gs.tick_reconnect_grace();
+    }
+    let result = gs.take_finished_result().expect("Forfeit is recorded");
+    assert_eq!(result.winner, 1);
+    assert!(result.rated_profiles().is_none());
+    assert!(gs.clients.is_empty());
+}
+
+#[test]
+fn test_restore_reseats_bot() {
+    let (gs, _msgs0) = match_with_bot();
+    let bytes = postcard::to_allocvec(&gs.persisted()).unwrap();
+    let saved: PersistedMatch = postcard::from_bytes(&bytes).unwrap();
+
+    let mut restored = GameState::new(Box::new(MockEnv::new()));
+    restored.restore(saved, vec![(0, Box::new(MockGameClient::new()))]);
+    assert!(restored.is_bot(1));
+    assert_eq!(restored.clients.len(), 2);
+    assert_eq!(restored.match_state, MatchState::Countdown);
+}
+
+type Outbox = Rc<RefCell<Vec<Vec<u8>>>>;
+
+/// Two players mid-match; returns both outboxes
+fn playing_match(gs: &mut GameState) -> (Outbox, Outbox) {
+    let (client0, msgs0) = SharedMockClient::new();
+    let (client1, msgs1) = SharedMockClient::new();
+    gs.add_player(Box::new(client0));
+    gs.add_player(Box::new(client1));
+    gs.match_state = MatchState::Playing;
+    (msgs0, msgs1)
+}
+
+#[test]
+fn test_pause_freezes_play_and_uses_budget() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (msgs0, msgs1) = playing_match(&mut gs);
+    gs.tick = 100;
+
+    assert!(gs.request_pause(0).is_ok());
+    assert_eq!(gs.match_state, MatchState::Paused);
+    assert!(matches!(
+        decode_all(&msgs1).last(),
+        Some(S2C::Paused {
+            player_id: 0,
+            pauses_left,
+            seconds: PAUSE_MAX_SECONDS,
+        }) if *pauses_left == PAUSES_PER_PLAYER - 1
+    ));
+
+    // No simulation while paused
+    assert_eq!(gs.step(), None);
+    assert_eq!(gs.tick, 100);
+
+    // Already paused
+    assert!(gs.request_pause(1).is_err());
+
+    // Spend the rest of player 0's budget
+    for _ in 1..PAUSES_PER_PLAYER {
+        gs.match_state = MatchState::Playing;
+        assert!(gs.request_pause(0).is_ok());
+    }
+    gs.match_state = MatchState::Playing;
+    assert!(gs.request_pause(0).is_err());
+    assert_eq!(gs.match_state, MatchState::Playing);
+    assert!(matches!(
+        decode_all(&msgs0).last(),
+        Some(S2C::PauseDenied { pauses_left: 0 })
+    ));
+
+    // Player 1 still has their own pauses
+    assert!(gs.request_pause(1).is_ok());
+}
+
+#[test]
+fn test_mutual_resume_counts_down_then_resumes() {
+    let (env, clock) = MockEnv::with_clock();
+    let mut gs = GameState::new(Box::new(env));
+    let (msgs0, _msgs1) = playing_match(&mut gs);
+    gs.request_pause(1).unwrap();
+    gs.clock.advance(0.012);
+
+    // One player alone cannot resume
+    assert!(!gs.request_resume(0));
+    assert!(matches!(
+        decode_all(&msgs0).last(),
+        Some(S2C::ResumeRequested { player_id: 0 })
+    ));
+    assert!(!gs.tick_pause());
+    assert!(gs.pause.as_ref().unwrap().resume_countdown.is_none());
+
+    assert!(gs.request_resume(1));
+    for seconds in (1..=RESUME_COUNTDOWN_SECONDS).rev() {
+        assert!(!gs.tick_pause());
+        assert!(matches!(
+            decode_all(&msgs0).last(),
+            Some(S2C::ResumeCountdown { seconds: s }) if *s == seconds
+        ));
+    }
+
+    *clock.borrow_mut() = 50_000;
+    assert!(gs.tick_pause());
+    assert_eq!(gs.match_state, MatchState::Playing);
+    assert!(gs.pause.is_none());
+    // Time spent paused is not simulated on resume
+    assert_eq!(gs.last_tick_time, 50_000);
+    assert_eq!(gs.clock.accumulator(), 0.0);
+    assert!(matches!(
+        decode_all(&msgs0).last(),
+        Some(S2C::Resumed {
+            server_time_ms: 50_000
+        })
+    ));
+}
+
+#[test]
+fn test_pause_runs_out_and_resumes() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    playing_match(&mut gs);
+    gs.request_pause(0).unwrap();
+
+    for _ in 0..PAUSE_MAX_SECONDS {
+        assert!(!gs.tick_pause());
+    }
+    assert_eq!(
+        gs.pause.as_ref().unwrap().resume_countdown,
+        Some(RESUME_COUNTDOWN_SECONDS)
+    );
+    for _ in 0..RESUME_COUNTDOWN_SECONDS {
+        assert!(!gs.tick_pause());
+    }
+    assert!(gs.tick_pause());
+    assert_eq!(gs.match_state, MatchState::Playing);
+}
+
+#[test]
+fn test_bot_does_not_block_resume() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    gs.set_bot_fill(BotFill::Immediate).unwrap();
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.tick_bot();
+    gs.match_state = MatchState::Playing;
+
+    gs.request_pause(0).unwrap();
+    assert!(gs.request_resume(0));
+}
+
+#[test]
+fn test_drop_while_paused_holds_slot() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (msgs0, _msgs1) = playing_match(&mut gs);
+    let token = gs.clients[&1].session_token;
+    gs.request_pause(0).unwrap();
+
+    gs.remove_player(1);
+    assert_eq!(gs.match_state, MatchState::AwaitingReconnect);
+    assert!(gs.pause.is_none());
+    assert!(matches!(
+        decode_all(&msgs0).last(),
+        Some(S2C::OpponentReconnecting { player_id: 1, .. })
+    ));
+
+    // Rejoining goes straight back to play
+    gs.rejoin_player(Box::new(MockGameClient::new()), token);
+    assert_eq!(gs.match_state, MatchState::Playing);
+}
+
+#[test]
+fn test_restore_keeps_pause() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    playing_match(&mut gs);
+    gs.request_pause(1).unwrap();
+    gs.request_resume(0);
+    let bytes = postcard::to_allocvec(&gs.persisted()).unwrap();
+    let saved: PersistedMatch = postcard::from_bytes(&bytes).unwrap();
+
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    gs.restore(
+        saved,
+        vec![
+            (0, Box::new(MockGameClient::new()) as Box<dyn GameClient>),
+            (1, Box::new(MockGameClient::new())),
+        ],
+    );
+
+    assert_eq!(gs.match_state, MatchState::Paused);
+    assert_eq!(gs.pauses_used, [0, 1]);
+    assert_eq!(gs.pause.as_ref().unwrap().ready, [true, false]);
+}
+
+/// Two players after a finished game; returns both outboxes
+fn finished_match(gs: &mut GameState) -> (Outbox, Outbox) {
+    let outboxes = playing_match(gs);
+    gs.score.left = 5;
+    gs.match_state = MatchState::GameOver;
+    outboxes
+}
+
+#[test]
+fn test_rematch_needs_both_players() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (_msgs0, msgs1) = finished_match(&mut gs);
+
+    assert!(!gs.request_rematch(0, false));
+    assert_eq!(gs.match_state, MatchState::GameOver);
+    assert!(matches!(
+        decode_all(&msgs1).last(),
+        Some(S2C::RematchRequested {
+            player_id: 0,
+            swap_sides: false,
+            seconds: REMATCH_OFFER_SECONDS,
+        })
+    ));
+
+    // Asking again does not count as the opponent agreeing
+    assert!(!gs.request_rematch(0, false));
+
+    assert!(gs.request_rematch(1, false));
+    assert_eq!(gs.match_state, MatchState::Countdown);
+    assert_eq!(gs.score.left, 0);
+    assert!(gs.rematch.is_none());
+    assert!(!gs.take_sides_swapped());
+}
+
+#[test]
+fn test_rematch_with_different_terms_replaces_offer() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    finished_match(&mut gs);
+
+    gs.request_rematch(0, false);
+    assert!(!gs.request_rematch(1, true));
+    let offer = gs.rematch.unwrap();
+    assert_eq!(offer.votes, [false, true]);
+    assert!(offer.swap_sides);
+
+    assert!(gs.request_rematch(0, true));
+}
+
+#[test]
+fn test_rematch_offer_expires() {
+    let (env, clock) = MockEnv::with_clock();
+    let mut gs = GameState::new(Box::new(env));
+    let (msgs0, _msgs1) = finished_match(&mut gs);
+    let start = *clock.borrow();
+
+    gs.request_rematch(0, false);
+    *clock.borrow_mut() = start + u64::from(REMATCH_OFFER_SECONDS) * 1000 - 1;
+    gs.tick_rematch();
+    assert!(gs.rematch.is_some());
+
+    *clock.borrow_mut() += 1;
+    gs.tick_rematch();
+    assert!(gs.rematch.is_none());
+    assert!(matches!(
+        decode_all(&msgs0).last(),
+        Some(S2C::RematchExpired)
+    ));
+
+    // The old vote is gone: player 1 only opens a new offer
+    assert!(!gs.request_rematch(1, false));
+    assert_eq!(gs.rematch.unwrap().votes, [false, true]);
+}
+
+#[test]
+fn test_rematch_swaps_sides() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (msgs0, _msgs1) = finished_match(&mut gs);
+    gs.set_profile(0, Some("alice".to_string()));
+    let token0 = gs.clients[&0].session_token;
+    gs.clients.get_mut(&1).unwrap().muted.insert(0);
+
+    gs.request_rematch(0, true);
+    assert!(gs.request_rematch(1, true));
+
+    // The first player now plays on the right
+    assert_eq!(gs.clients[&1].session_token, token0);
+    assert_eq!(gs.profiles.get(&1).map(String::as_str), Some("alice"));
+    assert!(gs.clients[&0].muted.contains(&1));
+    assert!(decode_all(&msgs0).iter().any(|m| matches!(
+        m,
+        S2C::Welcome { player_id: 1, session_token, .. } if *session_token == token0
+    )));
+    assert!(gs.take_sides_swapped());
+    assert!(!gs.take_sides_swapped());
+}
+
+#[test]
+fn test_bot_accepts_rematch() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    gs.set_bot_fill(BotFill::Immediate).unwrap();
+    gs.add_player(Box::new(MockGameClient::new()));
+    gs.tick_bot();
+    gs.match_state = MatchState::GameOver;
+
+    assert!(gs.request_rematch(0, true));
+    assert_eq!(gs.bot, Some(0));
+    assert_eq!(gs.match_state, MatchState::Countdown);
+}
+
+#[test]
+fn test_host_binds_sockets_and_follows_side_swaps() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let join = || C2S::Join {
+        code: *b"ABCDE",
+        profile_id: None,
+    };
+
+    // The first player starts the loop; the second only takes a slot
+    let (client0, msgs0) = SharedMockClient::new();
+    let first = host::handle_message(&mut gs, &client0, None, join());
+    assert_eq!(first.bind.and_then(SocketRole::player_id), Some(0));
+    assert!(first.start_loop);
+    assert!(matches!(
+        decode_all(&msgs0).first(),
+        Some(S2C::Welcome { player_id: 0, .. })
+    ));
+
+    let (client1, _msgs1) = SharedMockClient::new();
+    let second = host::handle_message(&mut gs, &client1, None, join());
+    assert_eq!(second.bind.and_then(SocketRole::player_id), Some(1));
+    assert!(!second.start_loop);
+
+    // A bound socket cannot join again
+    let role0 = first.bind;
+    assert_eq!(
+        host::handle_message(&mut gs, &client0, role0, join()).bind,
+        None
+    );
+
+    // Agreeing to swap sides tells the host to rebind both sockets
+    gs.match_state = MatchState::GameOver;
+    let rematch = C2S::Rematch { swap_sides: true };
+    host::handle_message(&mut gs, &client0, role0, rematch.clone());
+    let agreed = host::handle_message(&mut gs, &client1, second.bind, rematch);
+    assert!(agreed.sides_swapped);
+    let role0 = role0.map(SocketRole::swapped);
+    assert_eq!(role0.and_then(SocketRole::player_id), Some(1));
+
+    // Closing a bound socket frees its slot
+    host::handle_close(&mut gs, role0, "test");
+    assert!(!gs.clients.contains_key(&1));
+}
+
+#[test]
+fn test_stale_socket_cannot_act_for_the_slots_new_owner() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let join = || C2S::Join {
+        code: *b"ABCDE",
+        profile_id: None,
+    };
+    let (client_a, _msgs_a) = SharedMockClient::new();
+    let (client_b, _msgs_b) = SharedMockClient::new();
+    let role_a = host::handle_message(&mut gs, &client_a, None, join()).bind;
+    let role_b = host::handle_message(&mut gs, &client_b, None, join()).bind;
+
+    // B leaves during the countdown; the newcomer gets B's free slot, not A's
+    assert_eq!(gs.match_state, MatchState::Countdown);
+    host::handle_close(&mut gs, role_b, "close event");
+    let (client_c, _msgs_c) = SharedMockClient::new();
+    let role_c = host::handle_message(&mut gs, &client_c, None, join()).bind;
+    assert_eq!(role_c.and_then(SocketRole::player_id), Some(1));
+    assert_eq!(gs.clients.len(), 2);
+
+    // B's late close and inputs no longer reach C
+    let c_token = gs.clients[&1].session_token;
+    host::handle_close(&mut gs, role_b, "socket error");
+    let input = C2S::Input {
+        player_id: 1,
+        y: 3.0,
+        seq: 0,
+        server_time_ms: 0,
+    };
+    host::handle_message(&mut gs, &client_b, role_b, input);
+    assert!(gs.owns_slot(1, c_token));
+    assert!(gs.net_queue.pop_inputs().is_empty());
+
+    // A still owns slot 0
+    host::handle_close(&mut gs, role_a, "close event");
+    assert!(!gs.clients.contains_key(&0));
+    assert!(gs.clients.contains_key(&1));
+}
+
+#[test]
+fn test_host_drops_floods_and_disconnects_offenders() {
+    let (env, metrics) = MockEnv::with_metrics();
+    let mut gs = GameState::new(Box::new(env));
+    let mut limiter = ConnectionLimiter::new(RateLimits {
+        max_dropped: 5,
+        ..RateLimits::default()
+    });
+    let ping = C2S::Ping {
+        t_ms: 0,
+        rtt_ms: 0,
+        snapshot_tick: 0,
+    }
+    .to_bytes()
+    .unwrap();
+
+    let (client, msgs) = SharedMockClient::new();
+    let join = C2S::Join {
+        code: *b"ABCDE",
+        profile_id: None,
+    }
+    .to_bytes()
+    .unwrap();
+    let role = host::handle_bytes(&mut gs, &client, None, &mut limiter, &join).bind;
+    assert_eq!(role.and_then(SocketRole::player_id), Some(0));
+
+    // The ping burst is answered, the rest of the flood is dropped unanswered
+    msgs.borrow_mut().clear();
+    let burst = RateLimits::default().ping.burst as usize;
+    for _ in 0..burst + 5 {
+        host::handle_bytes(&mut gs, &client, role, &mut limiter, &ping);
+    }
+    let pongs = decode_all(&msgs)
+        .iter()
+        .filter(|msg| matches!(msg, S2C::Pong { .. }))
+        .count();
+    assert_eq!(pongs, burst);
+    assert_eq!(metrics.counter(names::MESSAGES_DROPPED), 5);
+    assert!(gs.clients.contains_key(&0));
+
+    // One drop too many disconnects the socket and frees the slot
+    host::handle_bytes(&mut gs, &client, role, &mut limiter, &ping);
+    assert_eq!(metrics.counter(names::MESSAGES_DROPPED), 6);
+    assert_eq!(metrics.counter(names::RATE_LIMIT_DISCONNECTS), 1);
+    assert!(!gs.clients.contains_key(&0));
+}
+
+#[test]
+fn test_host_flooder_mid_match_forfeits_and_cannot_rejoin() {
+    let mut gs = GameState::new(Box::new(MockEnv::new()));
+    let (client0, _msgs0) = SharedMockClient::new();
+    gs.add_player(Box::new(client0));
+    let (client1, _msgs1) = SharedMockClient::new();
+    gs.add_player(Box::new(client1.clone()));
+    let token = gs.clients[&1].session_token;
+    gs.match_state = MatchState::Playing;
+
+    let mut limiter = ConnectionLimiter::new(RateLimits {
+        max_dropped: 0,
+        ..RateLimits::default()
+    });
+    let role = Some(SocketRole::Player {
+        player_id: 1,
+        session_token: token,
+    });
+    let pause = C2S::Pause.to_bytes().unwrap();
+    while gs.clients.contains_key(&1) {
+        host::handle_bytes(&mut gs, &client1, role, &mut limiter, &pause);
+    }
+
+    assert!(gs.pending_reconnect.is_none());
+    assert_eq!(gs.match_state, MatchState::GameOver);
+    assert_eq!(
+        gs.rejoin_player(Box::new(MockGameClient::new()), token),
+        None
+    );
+}
+
+#[test]
+fn test_steady_inputs_at_the_limit_are_never_dropped() {
+    let (env, metrics) = MockEnv::with_metrics();
+    let clock = env.time_ms.clone();
+    let mut gs = GameState::new(Box::new(env));
+    let (client, _msgs) = SharedMockClient::new();
+    gs.add_player(Box::new(client.clone()));
+    let role = Some(SocketRole::Player {
+        player_id: 0,
+        session_token: gs.clients[&0].session_token,
+    });
+    let mut limiter = ConnectionLimiter::default();
+
+    // A minute at the input refill rate, after spending the burst up front
+    let per_second = RateLimits::default().input.per_second as u64;
+    for n in 0..60 * per_second {
+        *clock.borrow_mut() = 1000 + n * 1000 / per_second;
+        let input = C2S::Input {
+            player_id: 0,
+            y: 12.0,
+            seq: n as u32,
+            server_time_ms: 0,
+        };
+        host::handle_bytes(
+            &mut gs,
+            &client,
+            role,
+            &mut limiter,
+            &input.to_bytes().unwrap(),
+        );
+    }
+    assert_eq!(metrics.counter(names::MESSAGES_DROPPED), 0);
+    assert_eq!(gs.clients[&0].input_validator.violations(), 0);
+}
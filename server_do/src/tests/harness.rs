@@ -0,0 +1,515 @@
+//! Whole matches on a virtual clock
+//!
+//! `Sim` drives a `GameState` the way a host does: messages go through
+//! `host::handle_message`, and `AlarmDriver` runs the alarm loop whenever
+//! the virtual clock reaches it, late by a repeating jitter pattern. Tests
+//! then compare what each client received against the exact sequence.
+
+use super::MockEnv;
+use crate::game_state::{
+    GameClient, GameState, MatchState, RECONNECT_GRACE_SECONDS, SPECTATOR_DELAY_MS,
+};
+use crate::host::{self, AlarmDriver, SocketRole, IDLE_TIMEOUT_SECONDS};
+use crate::rate_limit::ConnectionLimiter;
+use game_core::{MatchSettings, TickClock};
+use proto::{GameEvent, Role, C2S, S2C};
+use std::cell::RefCell;
+use std::rc::Rc;
+use worker::Result;
+
+/// Connection that records what the server sent and whether it closed it
+#[derive(Clone, Default)]
+struct RecordingClient {
+    sent: Rc<RefCell<Vec<Vec<u8>>>>,
+    closed: Rc<RefCell<Option<String>>>,
+}
+
+impl GameClient for RecordingClient {
+    fn send_bytes(&self, bytes: &[u8]) -> Result<()> {
+        self.sent.borrow_mut().push(bytes.to_vec());
+        Ok(())
+    }
+
+    fn close(&self, reason: &str) -> Result<()> {
+        *self.closed.borrow_mut() = Some(reason.to_string());
+        Ok(())
+    }
+}
+
+struct SimConn {
+    client: RecordingClient,
+    role: Option<SocketRole>,
+    limiter: ConnectionLimiter,
+    open: bool, // Close event not yet delivered to the match
+}
+
+pub(super) struct Sim {
+    pub gs: GameState,
+    alarm: AlarmDriver,
+    clock: Rc<RefCell<u64>>,
+    conns: Vec<SimConn>,
+    jitter_ms: Vec<u64>, // How late successive alarms fire, repeating
+    alarms_fired: usize,
+}
+
+impl Sim {
+    pub fn new(settings: MatchSettings) -> Self {
+        Self::with_jitter(settings, &[0])
+    }
+
+    /// Alarms fire late by each of `jitter_ms` in turn
+    pub fn with_jitter(settings: MatchSettings, jitter_ms: &[u64]) -> Self {
+        let (env, clock) = MockEnv::with_clock();
+        let mut gs = GameState::new(Box::new(env));
+        gs.apply_settings(settings).unwrap();
+        Self {
+            gs,
+            alarm: AlarmDriver::new(),
+            clock,
+            conns: Vec::new(),
+            jitter_ms: jitter_ms.to_vec(),
+            alarms_fired: 0,
+        }
+    }
+
+    pub fn now(&self) -> u64 {
+        *self.clock.borrow()
+    }
+
+    /// Open a socket; returns its index
+    pub fn connect(&mut self) -> usize {
+        self.conns.push(SimConn {
+            client: RecordingClient::default(),
+            role: None,
+            limiter: ConnectionLimiter::default(),
+            open: true,
+        });
+        self.conns.len() - 1
+    }
+
+    pub fn send(&mut self, conn: usize, msg: C2S) {
+        let sim_conn = &mut self.conns[conn];
+        assert!(sim_conn.open, "Connection {conn} is closed");
+        let bytes = msg.to_bytes().unwrap();
+        let handled = host::handle_bytes(
+            &mut self.gs,
+            &sim_conn.client,
+            sim_conn.role,
+            &mut sim_conn.limiter,
+            &bytes,
+        );
+        if let Some(role) = handled.bind {
+            sim_conn.role = Some(role);
+        }
+        if handled.sides_swapped {
+            for sim_conn in &mut self.conns {
+                sim_conn.role = sim_conn.role.map(SocketRole::swapped);
+            }
+        }
+        self.alarm.after_message(&self.gs, &handled);
+        self.deliver_closes();
+    }
+
+    /// Connect and join as a player
+    pub fn join(&mut self) -> usize {
+        let conn = self.connect();
+        self.send(
+            conn,
+            C2S::Join {
+                code: *b"ABCDE",
+                profile_id: None,
+            },
+        );
+        conn
+    }
+
+    /// Player holds their paddle at `y`, which also counts as activity
+    pub fn input(&mut self, conn: usize, y: f32) {
+        let Some(SocketRole::Player { player_id, .. }) = self.conns[conn].role else {
+            panic!("Connection {conn} is not a player");
+        };
+        let now = self.now();
+        self.send(
+            conn,
+            C2S::Input {
+                player_id,
+                y,
+                seq: 0,
+                server_time_ms: now,
+            },
+        );
+    }
+
+    /// The client side drops the socket
+    pub fn disconnect(&mut self, conn: usize) {
+        let sim_conn = &mut self.conns[conn];
+        if std::mem::replace(&mut sim_conn.open, false) {
+            host::handle_close(&mut self.gs, sim_conn.role, "close event");
+        }
+    }
+
+    /// Sockets the server closed get their close event, as the runtime
+    /// delivers one after `WebSocket::close`
+    fn deliver_closes(&mut self) {
+        for conn in 0..self.conns.len() {
+            if self.conns[conn].open && self.closed_reason(conn).is_some() {
+                self.disconnect(conn);
+            }
+        }
+    }
+
+    /// Advance the virtual clock by `ms`, firing every alarm that comes due
+    pub fn run_for(&mut self, ms: u64) {
+        let end = self.now() + ms;
+        self.run_until(end, |_| false);
+    }
+
+    /// Advance until `done` holds after an alarm, or the clock reaches
+    /// `end_ms`. Returns whether `done` was reached.
+    pub fn run_until(&mut self, end_ms: u64, done: impl Fn(&GameState) -> bool) -> bool {
+        loop {
+            let Some(due_ms) = self.alarm.next_ms() else {
+                *self.clock.borrow_mut() = end_ms;
+                return false;
+            };
+            let jitter = self.jitter_ms[self.alarms_fired % self.jitter_ms.len()];
+            let fire_ms = due_ms + jitter;
+            if fire_ms > end_ms {
+                *self.clock.borrow_mut() = end_ms;
+                return false;
+            }
+            *self.clock.borrow_mut() = fire_ms;
+            assert!(self.alarm.run_due(&mut self.gs));
+            self.alarms_fired += 1;
+            self.deliver_closes();
+            if done(&self.gs) {
+                return true;
+            }
+        }
+    }
+
+    /// Whether the alarm loop is still scheduled
+    pub fn alarm_running(&self) -> bool {
+        self.alarm.next_ms().is_some()
+    }
+
+    pub fn closed_reason(&self, conn: usize) -> Option<String> {
+        self.conns[conn].client.closed.borrow().clone()
+    }
+
+    pub fn role(&self, conn: usize) -> Option<SocketRole> {
+        self.conns[conn].role
+    }
+
+    /// Everything `conn` received, one entry per message. Runs of snapshots
+    /// become one `GameState` entry and, of gameplay events, only points are
+    /// listed, so the sequence does not hinge on every bounce. Session tokens
+    /// are random and left out.
+    pub fn transcript(&self, conn: usize) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        for bytes in self.conns[conn].client.sent.borrow().iter() {
+            let line = match S2C::from_bytes(bytes).unwrap() {
+                S2C::Welcome {
+                    player_id,
+                    role: Role::Player,
+                    ..
+                } => format!("Welcome {{ player_id: {player_id} }}"),
+                S2C::Welcome { .. } => "Welcome { spectator }".to_string(),
+                S2C::GameState(_) => "GameState".to_string(),
+                S2C::Events { events, .. } => {
+                    for event in events {
+                        if let GameEvent::PointScored { scorer } = event {
+                            lines.push(format!("PointScored {{ scorer: {scorer} }}"));
+                        }
+                    }
+                    continue;
+                }
+                msg => format!("{msg:?}"),
+            };
+            if line == "GameState" && lines.last().is_some_and(|last| last == "GameState") {
+                continue;
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Snapshots `conn` has received so far
+    pub fn snapshots(&self, conn: usize) -> usize {
+        self.conns[conn]
+            .client
+            .sent
+            .borrow()
+            .iter()
+            .filter(|bytes| matches!(S2C::from_bytes(bytes), Ok(S2C::GameState(_))))
+            .count()
+    }
+
+    pub fn state(&self) -> MatchState {
+        self.gs.match_state
+    }
+}
+
+fn first_to(win_score: u8) -> MatchSettings {
+    MatchSettings {
+        win_score,
+        ..MatchSettings::default()
+    }
+}
+
+/// Two players joined and the countdown run, at the moment play starts
+fn started(sim: &mut Sim) -> (usize, usize) {
+    let left = sim.join();
+    let right = sim.join();
+    assert!(sim.run_until(10_000, |gs| gs.match_state == MatchState::Playing));
+    (left, right)
+}
+
+/// What each player receives from joining to the start of play
+fn opening(first: bool, start_ms: u64) -> Vec<String> {
+    let mut lines = if first {
+        vec![
+            "Welcome { player_id: 0 }",
+            "GameState",
+            "MatchFound",
+            "GameState",
+        ]
+    } else {
+        vec!["MatchFound", "Welcome { player_id: 1 }", "GameState"]
+    };
+    lines.extend([
+        "Countdown { seconds: 3 }",
+        "Countdown { seconds: 2 }",
+        "Countdown { seconds: 1 }",
+    ]);
+    let mut lines: Vec<String> = lines.into_iter().map(String::from).collect();
+    lines.push(format!("GameStart {{ server_time_ms: {start_ms} }}"));
+    lines
+}
+
+fn with(mut lines: Vec<String>, more: &[&str]) -> Vec<String> {
+    lines.extend(more.iter().map(|line| line.to_string()));
+    lines
+}
+
+#[test]
+fn test_match_plays_to_game_over_through_alarm_jitter() {
+    let mut sim = Sim::with_jitter(first_to(2), &[0, 7, 2, 30, 1]);
+    let (left, right) = started(&mut sim);
+    assert_eq!(sim.gs.match_started_ms, 4055);
+
+    // Left parks their paddle at the top, so the serve gets past it
+    sim.input(left, 1.0);
+    assert!(sim.run_until(60_000, |gs| gs.match_state == MatchState::GameOver));
+
+    let play = [
+        "GameState",
+        "PointScored { scorer: 1 }",
+        "GameState",
+        "PointScored { scorer: 0 }",
+        "GameState",
+        "PointScored { scorer: 1 }",
+        "GameOver { winner: 1 }",
+    ];
+    assert_eq!(sim.transcript(left), with(opening(true, 4055), &play));
+    assert_eq!(sim.transcript(right), with(opening(false, 4055), &play));
+
+    // Late alarms were caught up: one tick per 1/60s of play
+    assert!(sim.gs.tick.abs_diff(ticks_played(&sim)) <= 1);
+
+    // The loop keeps running for the rematch offer window
+    assert!(sim.alarm_running());
+}
+
+#[test]
+fn test_rematch_keeps_snapshots_flowing() {
+    let mut sim = Sim::new(first_to(1));
+    let (left, right) = started(&mut sim);
+    sim.input(left, 1.0);
+    assert!(sim.run_until(60_000, |gs| gs.match_state == MatchState::GameOver));
+
+    for conn in [left, right] {
+        sim.send(conn, C2S::Rematch { swap_sides: false });
+    }
+    assert!(sim.run_until(80_000, |gs| gs.match_state == MatchState::Playing));
+    let before = [sim.snapshots(left), sim.snapshots(right)];
+
+    // A second of the new game at the default 20Hz cadence
+    sim.run_for(1000);
+    for (conn, before) in [left, right].into_iter().zip(before) {
+        let received = sim.snapshots(conn) - before;
+        assert!(received >= 19, "{received} snapshots after the rematch");
+    }
+}
+
+/// Ticks the clock owes for play up to the last alarm
+fn ticks_played(sim: &Sim) -> u32 {
+    let played_ms = sim.gs.last_tick_time - sim.gs.match_started_ms;
+    TickClock::ticks_in(played_ms as f32 / 1000.0)
+}
+
+#[test]
+fn test_alarm_jitter_does_not_change_game_speed() {
+    for jitter in [&[0][..], &[0, 45, 5, 80, 13], &[33]] {
+        let mut sim = Sim::with_jitter(first_to(5), jitter);
+        started(&mut sim);
+        sim.run_for(2000);
+        assert!(
+            sim.gs.tick.abs_diff(ticks_played(&sim)) <= 1,
+            "{} ticks with jitter {jitter:?}",
+            sim.gs.tick
+        );
+    }
+}
+
+#[test]
+fn test_dropped_player_forfeits_after_grace() {
+    let mut sim = Sim::with_jitter(first_to(5), &[3, 11]);
+    let (left, right) = started(&mut sim);
+    sim.run_for(1000);
+    sim.disconnect(right);
+    assert_eq!(sim.state(), MatchState::AwaitingReconnect);
+    assert!(sim.run_until(60_000, |gs| gs.match_state == MatchState::GameOver));
+
+    let mut expected = with(opening(true, 4044), &["GameState"]);
+    for seconds in (1..=RECONNECT_GRACE_SECONDS).rev() {
+        expected.push(format!(
+            "OpponentReconnecting {{ player_id: 1, seconds: {seconds} }}"
+        ));
+    }
+    expected.push("GameOver { winner: 0 }".to_string());
+    assert_eq!(sim.transcript(left), expected);
+    assert_eq!(sim.closed_reason(left), None);
+}
+
+#[test]
+fn test_idle_players_are_kicked_and_the_loop_stops() {
+    let mut sim = Sim::new(first_to(5));
+    let (left, right) = started(&mut sim);
+
+    // Joined at 1s; nobody has sent anything since
+    let idle_limit_ms = (IDLE_TIMEOUT_SECONDS + 2) * 1000;
+    sim.run_for(idle_limit_ms - sim.now() - 100);
+    assert_eq!(sim.closed_reason(left), None);
+    assert_eq!(sim.state(), MatchState::Playing);
+
+    sim.run_for(200);
+    for conn in [left, right] {
+        assert_eq!(sim.closed_reason(conn).as_deref(), Some("Idle timeout"));
+    }
+    assert_eq!(
+        sim.transcript(left),
+        with(opening(true, 4016), &["GameState"])
+    );
+    assert_eq!(
+        sim.transcript(right),
+        with(
+            opening(false, 4016),
+            &[
+                "GameState",
+                "OpponentReconnecting { player_id: 0, seconds: 15 }"
+            ],
+        )
+    );
+
+    // Everyone is gone, so the match is abandoned and stops scheduling alarms
+    assert_eq!(sim.state(), MatchState::Abandoned);
+    assert!(!sim.alarm_running());
+}
+
+#[test]
+fn test_spectator_follows_the_match_and_rejoin_resumes_play() {
+    let mut sim = Sim::new(first_to(5));
+    let (left, right) = started(&mut sim);
+    let token = sim.gs.clients[&1].session_token;
+    let spectator = sim.connect();
+    sim.send(
+        spectator,
+        C2S::Spectate {
+            code: *b"ABCDE",
+            delayed: false,
+        },
+    );
+    assert!(matches!(
+        sim.role(spectator),
+        Some(SocketRole::Spectator { .. })
+    ));
+
+    sim.run_for(500);
+    sim.disconnect(right);
+    sim.run_for(2500);
+
+    // The dropped player comes back on a new socket
+    let back = sim.connect();
+    sim.send(
+        back,
+        C2S::Rejoin {
+            code: *b"ABCDE",
+            session_token: token,
+        },
+    );
+    assert_eq!(sim.role(back).and_then(SocketRole::player_id), Some(1));
+    assert_eq!(sim.state(), MatchState::Playing);
+    sim.run_for(500);
+
+    assert_eq!(
+        sim.transcript(left),
+        with(
+            opening(true, 4016),
+            &[
+                "SpectatorCount { count: 1 }",
+                "GameState",
+                "OpponentReconnecting { player_id: 1, seconds: 15 }",
+                "OpponentReconnecting { player_id: 1, seconds: 14 }",
+                "OpponentReconnecting { player_id: 1, seconds: 13 }",
+                "OpponentReconnecting { player_id: 1, seconds: 12 }",
+                "OpponentReconnected { player_id: 1 }",
+                "GameState",
+            ],
+        )
+    );
+    assert_eq!(
+        sim.transcript(back),
+        [
+            "Welcome { player_id: 1 }",
+            "GameStart { server_time_ms: 7016 }",
+            "OpponentReconnected { player_id: 1 }",
+            "GameState",
+        ]
+    );
+    assert_eq!(sim.transcript(spectator)[0], "Welcome { spectator }");
+}
+
+#[test]
+fn test_delayed_spectator_sees_everything_a_delay_late() {
+    let mut sim = Sim::new(first_to(1));
+    let (left, right) = started(&mut sim);
+    let [live, delayed] = [false, true].map(|delayed| {
+        let conn = sim.connect();
+        sim.send(
+            conn,
+            C2S::Spectate {
+                code: *b"ABCDE",
+                delayed,
+            },
+        );
+        conn
+    });
+    sim.input(left, 1.0);
+    assert!(sim.run_until(60_000, |gs| gs.match_state == MatchState::GameOver));
+
+    // Nothing from the last delay has reached the delayed spectator yet
+    let game_over = "GameOver { winner: 1 }".to_string();
+    assert!(sim.transcript(live).contains(&game_over));
+    assert!(!sim.transcript(delayed).contains(&game_over));
+
+    // Both players leave, but the loop runs on to release the rest
+    sim.disconnect(left);
+    sim.disconnect(right);
+    let mut expected = sim.transcript(live);
+    sim.run_for(SPECTATOR_DELAY_MS + 100);
+    expected.retain(|line| line != "SpectatorCount { count: 1 }"); // Before it joined
+    assert_eq!(sim.transcript(delayed), expected);
+    assert_eq!(sim.snapshots(delayed), sim.snapshots(live));
+    assert!(!sim.alarm_running());
+}
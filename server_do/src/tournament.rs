@@ -0,0 +1,845 @@
+//! Tournament brackets
+//!
+//! A `TournamentDO` per tournament holds the bracket, creates a `MatchDO`
+//! with the agreed settings for each pairing once both entrants are known,
+//! and advances winners as linked matches report their results. Entrants
+//! are identified in results by their rating profile id; matches between
+//! entrants without one are reported by hand.
+
+use crate::match_history::MatchResult;
+use crate::rating::is_valid_profile_id;
+use game_core::MatchSettings;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fmt;
+use std::time::Duration;
+use worker::*;
+
+pub const MIN_ENTRANTS: usize = 2;
+pub const MAX_ENTRANTS: usize = 32;
+
+/// Longest entrant display name
+pub const MAX_NAME_LEN: usize = 32;
+
+/// Storage key for the tournament state
+const TOURNAMENT_STORAGE_KEY: &str = "tournament";
+
+/// Attempts at finding an unused match code for a pairing
+const CODE_ATTEMPTS: usize = 3;
+
+/// Wait before retrying pairings that could not be given a match
+const ASSIGN_RETRY_MS: u64 = 30_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    SingleElimination,
+    /// Winners and losers brackets, decided by a single grand final
+    DoubleElimination,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entrant {
+    pub name: String,
+    #[serde(default)]
+    pub profile_id: Option<String>, // Matches results to entrants automatically
+}
+
+/// Who fills one side of a bracket match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Slot {
+    /// Decided by an earlier match
+    Pending,
+    /// Index into the entrant list
+    Entrant(usize),
+    /// Nobody will arrive; the other side advances
+    Bye,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Winners,
+    Losers,
+    GrandFinal,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BracketMatch {
+    pub id: usize,
+    pub stage: Stage,
+    pub round: u32,
+    pub slots: [Slot; 2],
+    pub code: Option<String>,  // MatchDO once both entrants are known
+    pub winner: Option<usize>, // Winning slot (0 or 1)
+    pub winner_to: Option<(usize, usize)>, // (match id, slot) the winner moves to
+    pub loser_to: Option<(usize, usize)>, // (match id, slot) the loser drops to
+}
+
+impl BracketMatch {
+    fn new(id: usize, stage: Stage, round: u32, slots: [Slot; 2]) -> Self {
+        Self {
+            id,
+            stage,
+            round,
+            slots,
+            code: None,
+            winner: None,
+            winner_to: None,
+            loser_to: None,
+        }
+    }
+
+    /// Both entrants known and not yet played
+    pub fn is_playable(&self) -> bool {
+        self.winner.is_none() && matches!(self.slots, [Slot::Entrant(_), Slot::Entrant(_)])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TournamentError {
+    EntrantCount(usize),
+    InvalidName(String),
+    DuplicateEntrant(String),
+    InvalidProfileId(String),
+    DuplicateProfile(String),
+    UnknownMatch(usize),
+    NotPlayable(usize),
+    InvalidSlot(usize),
+}
+
+impl fmt::Display for TournamentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TournamentError::EntrantCount(count) => write!(
+                f,
+                "{count} entrants, need between {MIN_ENTRANTS} and {MAX_ENTRANTS}"
+            ),
+            TournamentError::InvalidName(name) => write!(f, "invalid entrant name '{name}'"),
+            TournamentError::DuplicateEntrant(name) => write!(f, "'{name}' entered twice"),
+            TournamentError::InvalidProfileId(id) => write!(f, "invalid profile id '{id}'"),
+            TournamentError::DuplicateProfile(id) => write!(f, "profile '{id}' entered twice"),
+            TournamentError::UnknownMatch(id) => write!(f, "no match {id}"),
+            TournamentError::NotPlayable(id) => {
+                write!(f, "match {id} is decided or still waiting for entrants")
+            }
+            TournamentError::InvalidSlot(slot) => write!(f, "slot {slot} must be 0 or 1"),
+        }
+    }
+}
+
+/// Seed positions for a bracket of `size` (a power of two), 0-based, so the
+/// top seeds meet as late as possible: [0, 3, 1, 2] for 4
+fn seed_order(size: usize) -> Vec<usize> {
+    let mut order = vec![0];
+    while order.len() < size {
+        let len = order.len();
+        order = order
+            .iter()
+            .flat_map(|&seed| [seed, 2 * len - 1 - seed])
+            .collect();
+    }
+    order
+}
+
+/// All matches of a bracket for `count` entrants in seed order
+fn build_bracket(count: usize, format: Format) -> Vec<BracketMatch> {
+    let size = count.next_power_of_two().max(2);
+    let rounds = size.trailing_zeros();
+    let seed_slot = |seed: usize| {
+        if seed < count {
+            Slot::Entrant(seed)
+        } else {
+            Slot::Bye
+        }
+    };
+
+    fn add(matches: &mut Vec<BracketMatch>, stage: Stage, round: u32, slots: [Slot; 2]) -> usize {
+        let id = matches.len();
+        matches.push(BracketMatch::new(id, stage, round, slots));
+        id
+    }
+
+    let mut matches: Vec<BracketMatch> = Vec::new();
+
+    // Winners bracket
+    let order = seed_order(size);
+    let mut winners_rounds: Vec<Vec<usize>> = vec![order
+        .chunks(2)
+        .map(|pair| {
+            add(
+                &mut matches,
+                Stage::Winners,
+                1,
+                [seed_slot(pair[0]), seed_slot(pair[1])],
+            )
+        })
+        .collect()];
+    for round in 2..=rounds {
+        let previous = winners_rounds.last().unwrap().clone();
+        let current: Vec<usize> = (0..previous.len() / 2)
+            .map(|_| add(&mut matches, Stage::Winners, round, [Slot::Pending; 2]))
+            .collect();
+        for (i, &id) in previous.iter().enumerate() {
+            matches[id].winner_to = Some((current[i / 2], i % 2));
+        }
+        winners_rounds.push(current);
+    }
+    let winners_final = winners_rounds.last().unwrap()[0];
+
+    if format == Format::SingleElimination {
+        return matches;
+    }
+
+    let losers_final = if rounds == 1 {
+        // Two entrants: the loser of the only match goes straight to the final
+        None
+    } else {
+        // Odd losers rounds pair survivors; even rounds bring in losers from the winners bracket
+        let first_round = &winners_rounds[0];
+        let mut previous: Vec<usize> = (0..first_round.len() / 2)
+            .map(|_| add(&mut matches, Stage::Losers, 1, [Slot::Pending; 2]))
+            .collect();
+        for (i, &id) in first_round.iter().enumerate() {
+            matches[id].loser_to = Some((previous[i / 2], i % 2));
+        }
+
+        let mut losers_round = 1;
+        for dropping in &winners_rounds[1..] {
+            losers_round += 1;
+            let current: Vec<usize> = (0..dropping.len())
+                .map(|_| {
+                    add(
+                        &mut matches,
+                        Stage::Losers,
+                        losers_round,
+                        [Slot::Pending; 2],
+                    )
+                })
+                .collect();
+            for (i, &id) in previous.iter().enumerate() {
+                matches[id].winner_to = Some((current[i], 0));
+            }
+            for (i, &id) in dropping.iter().enumerate() {
+                matches[id].loser_to = Some((current[i], 1));
+            }
+            previous = current;
+
+            if previous.len() > 1 {
+                losers_round += 1;
+                let current: Vec<usize> = (0..previous.len() / 2)
+                    .map(|_| {
+                        add(
+                            &mut matches,
+                            Stage::Losers,
+                            losers_round,
+                            [Slot::Pending; 2],
+                        )
+                    })
+                    .collect();
+                for (i, &id) in previous.iter().enumerate() {
+                    matches[id].winner_to = Some((current[i / 2], i % 2));
+                }
+                previous = current;
+            }
+        }
+        Some(previous[0])
+    };
+
+    let grand_final = add(&mut matches, Stage::GrandFinal, 1, [Slot::Pending; 2]);
+    matches[winners_final].winner_to = Some((grand_final, 0));
+    match losers_final {
+        Some(id) => matches[id].winner_to = Some((grand_final, 1)),
+        None => matches[winners_final].loser_to = Some((grand_final, 1)),
+    }
+    matches
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tournament {
+    pub id: String,
+    pub name: String,
+    pub format: Format,
+    pub settings: MatchSettings,
+    pub entrants: Vec<Entrant>, // In seed order
+    pub matches: Vec<BracketMatch>,
+    pub champion: Option<usize>, // Entrant index once the final is decided
+    #[serde(skip)]
+    assigning: Vec<usize>, // Matches a MatchDO is being created for right now
+}
+
+impl Tournament {
+    pub fn new(
+        id: String,
+        name: String,
+        format: Format,
+        settings: MatchSettings,
+        entrants: Vec<Entrant>,
+    ) -> std::result::Result<Self, TournamentError> {
+        if !(MIN_ENTRANTS..=MAX_ENTRANTS).contains(&entrants.len()) {
+            return Err(TournamentError::EntrantCount(entrants.len()));
+        }
+        for (i, entrant) in entrants.iter().enumerate() {
+            let name = entrant.name.trim();
+            if name.is_empty() || name.len() > MAX_NAME_LEN {
+                return Err(TournamentError::InvalidName(entrant.name.clone()));
+            }
+            if entrants[..i]
+                .iter()
+                .any(|other| other.name.trim().eq_ignore_ascii_case(name))
+            {
+                return Err(TournamentError::DuplicateEntrant(entrant.name.clone()));
+            }
+            if let Some(profile_id) = &entrant.profile_id {
+                if !is_valid_profile_id(profile_id) {
+                    return Err(TournamentError::InvalidProfileId(profile_id.clone()));
+                }
+                if entrants[..i]
+                    .iter()
+                    .any(|other| other.profile_id.as_ref() == Some(profile_id))
+                {
+                    return Err(TournamentError::DuplicateProfile(profile_id.clone()));
+                }
+            }
+        }
+
+        let mut tournament = Self {
+            id,
+            name,
+            format,
+            settings,
+            matches: build_bracket(entrants.len(), format),
+            entrants,
+            champion: None,
+            assigning: Vec::new(),
+        };
+        tournament.advance_byes();
+        Ok(tournament)
+    }
+
+    /// Ids of matches that need a MatchDO
+    pub fn unassigned_matches(&self) -> Vec<usize> {
+        self.matches
+            .iter()
+            .filter(|m| m.is_playable() && m.code.is_none())
+            .map(|m| m.id)
+            .collect()
+    }
+
+    /// Claim the matches that need a MatchDO and are not already being
+    /// given one, so a request arriving mid-assignment does not repeat it
+    pub fn start_assigning(&mut self) -> Vec<usize> {
+        let claimed: Vec<usize> = self
+            .unassigned_matches()
+            .into_iter()
+            .filter(|match_id| !self.assigning.contains(match_id))
+            .collect();
+        self.assigning.extend(&claimed);
+        claimed
+    }
+
+    pub fn assign_code(&mut self, match_id: usize, code: String) {
+        self.assigning.retain(|&id| id != match_id);
+        if let Some(m) = self.matches.get_mut(match_id) {
+            m.code = Some(code);
+        }
+    }
+
+    /// Release a claimed match that could not be given a MatchDO, so a
+    /// later attempt picks it up again
+    pub fn abandon_assigning(&mut self, match_id: usize) {
+        self.assigning.retain(|&id| id != match_id);
+    }
+
+    /// Record a finished match by hand. `winner_slot` is 0 or 1.
+    pub fn report(
+        &mut self,
+        match_id: usize,
+        winner_slot: usize,
+    ) -> std::result::Result<(), TournamentError> {
+        let m = self
+            .matches
+            .get(match_id)
+            .ok_or(TournamentError::UnknownMatch(match_id))?;
+        if !m.is_playable() {
+            return Err(TournamentError::NotPlayable(match_id));
+        }
+        if winner_slot > 1 {
+            return Err(TournamentError::InvalidSlot(winner_slot));
+        }
+        self.decide(match_id, winner_slot);
+        self.advance_byes();
+        Ok(())
+    }
+
+    /// Apply a result reported by a linked MatchDO. Returns the bracket match
+    /// it decided, or None if it does not match an open pairing (unknown code,
+    /// already decided, or the winner has no matching profile id).
+    pub fn record_result(&mut self, result: &MatchResult) -> Option<usize> {
+        let m = self
+            .matches
+            .iter()
+            .find(|m| m.is_playable() && m.code.as_deref() == Some(result.code.as_str()))?;
+        let winner_profile = result
+            .players
+            .iter()
+            .find(|player| player.player_id == result.winner)?
+            .profile_id
+            .as_deref()?;
+        let winner_slot = m.slots.iter().position(|slot| match slot {
+            Slot::Entrant(i) => self.entrants[*i].profile_id.as_deref() == Some(winner_profile),
+            _ => false,
+        })?;
+
+        let match_id = m.id;
+        self.decide(match_id, winner_slot);
+        self.advance_byes();
+        Some(match_id)
+    }
+
+    /// Set a winner and move both sides on
+    fn decide(&mut self, match_id: usize, winner_slot: usize) {
+        let m = &mut self.matches[match_id];
+        m.winner = Some(winner_slot);
+        let (winner, loser) = (m.slots[winner_slot], m.slots[1 - winner_slot]);
+        let (winner_to, loser_to) = (m.winner_to, m.loser_to);
+
+        match winner_to {
+            Some((next, slot)) => self.matches[next].slots[slot] = winner,
+            None => {
+                if let Slot::Entrant(i) = winner {
+                    self.champion = Some(i);
+                }
+            }
+        }
+        if let Some((next, slot)) = loser_to {
+            // Eliminated players drop out; a bye leaves nobody behind
+            self.matches[next].slots[slot] = match loser {
+                Slot::Entrant(_) => loser,
+                _ => Slot::Bye,
+            };
+        }
+    }
+
+    /// Resolve every match that has a bye on one side
+    fn advance_byes(&mut self) {
+        while let Some(m) = self.matches.iter().find(|m| {
+            m.winner.is_none() && !m.slots.contains(&Slot::Pending) && m.slots.contains(&Slot::Bye)
+        }) {
+            let winner_slot = if m.slots[0] == Slot::Bye { 1 } else { 0 };
+            let match_id = m.id;
+            self.decide(match_id, winner_slot);
+        }
+    }
+}
+
+/// Random tournament id (12 hex characters)
+pub fn new_tournament_id() -> String {
+    let mut bytes = [0u8; 6];
+    if let Err(e) = getrandom::getrandom(&mut bytes) {
+        console_error!("Tournament: No secure randomness for id: {e}");
+        bytes.copy_from_slice(&Date::now().as_millis().to_le_bytes()[..6]);
+    }
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn is_valid_tournament_id(id: &str) -> bool {
+    id.len() == 12 && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Body of a create request
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateTournament {
+    pub name: String,
+    pub format: Format,
+    pub entrants: Vec<Entrant>,
+    #[serde(default)]
+    pub settings: MatchSettings,
+}
+
+/// Body of a manual result report
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportResult {
+    pub match_id: usize,
+    pub winner_slot: usize,
+}
+
+/// One tournament's bracket and match orchestration
+#[durable_object]
+pub struct TournamentDO {
+    state: State,
+    env: Env,
+    tournament: RefCell<Option<Tournament>>,
+}
+
+impl DurableObject for TournamentDO {
+    fn new(state: State, env: Env) -> Self {
+        Self {
+            state,
+            env,
+            tournament: RefCell::new(None),
+        }
+    }
+
+    async fn fetch(&self, mut req: Request) -> Result<Response> {
+        self.load().await?;
+        let path = req.path();
+
+        match (req.method(), path.as_str()) {
+            (Method::Post, "/create") => {
+                if self.tournament.borrow().is_some() {
+                    return Response::error("Tournament already exists", 409);
+                }
+                let create: CreateTournament = req.json().await?;
+                let id = req
+                    .url()?
+                    .query_pairs()
+                    .find(|(key, _)| key == "id")
+                    .map(|(_, value)| value.into_owned())
+                    .unwrap_or_default();
+                if let Err(e) = create.settings.validate() {
+                    return Response::error(format!("Invalid match settings: {e}"), 400);
+                }
+                let tournament = match Tournament::new(
+                    id,
+                    create.name,
+                    create.format,
+                    create.settings,
+                    create.entrants,
+                ) {
+                    Ok(tournament) => tournament,
+                    Err(e) => return Response::error(format!("Invalid tournament: {e}"), 400),
+                };
+                *self.tournament.borrow_mut() = Some(tournament);
+                self.create_matches().await?;
+                self.state_response()
+            }
+            (Method::Get, "/state") => self.state_response(),
+            (Method::Post, "/result") => {
+                let result: MatchResult = req.json().await?;
+                let decided = self
+                    .tournament
+                    .borrow_mut()
+                    .as_mut()
+                    .and_then(|tournament| tournament.record_result(&result));
+                match decided {
+                    Some(match_id) => {
+                        console_log!("Tournament: Match {match_id} decided by {}", result.code);
+                        self.create_matches().await?;
+                        Response::ok("Recorded")
+                    }
+                    None => Response::ok("Ignored"),
+                }
+            }
+            (Method::Post, "/report") => {
+                let report: ReportResult = req.json().await?;
+                let reported = match self.tournament.borrow_mut().as_mut() {
+                    Some(tournament) => tournament.report(report.match_id, report.winner_slot),
+                    None => return Response::error("Tournament not found", 404),
+                };
+                match reported {
+                    Ok(()) => {
+                        self.create_matches().await?;
+                        self.state_response()
+                    }
+                    Err(e) => Response::error(format!("Cannot report result: {e}"), 409),
+                }
+            }
+            _ => Response::error("Not found", 404),
+        }
+    }
+
+    /// Retry pairings that could not be given a match earlier
+    async fn alarm(&self) -> Result<Response> {
+        self.load().await?;
+        self.create_matches().await?;
+        Response::ok("Matches created")
+    }
+}
+
+impl TournamentDO {
+    /// Read the saved tournament, if any, the first time it is needed. A
+    /// failed read is returned so `/create` cannot overwrite what is there.
+    async fn load(&self) -> Result<()> {
+        if self.tournament.borrow().is_some() {
+            return Ok(());
+        }
+        let saved = crate::storage::get_optional::<Tournament>(
+            &self.state.storage(),
+            TOURNAMENT_STORAGE_KEY,
+        )
+        .await?;
+        if let Some(tournament) = saved {
+            *self.tournament.borrow_mut() = Some(tournament);
+        }
+        Ok(())
+    }
+
+    fn state_response(&self) -> Result<Response> {
+        match self.tournament.borrow().as_ref() {
+            Some(tournament) => Response::from_json(tournament),
+            None => Response::error("Tournament not found", 404),
+        }
+    }
+
+    /// Give every newly playable pairing a linked MatchDO, then save.
+    /// Pairings that could not be given one are retried from an alarm.
+    async fn create_matches(&self) -> Result<()> {
+        // Claimed before the first await, so an interleaved request skips them
+        let (id, settings, pending) = match self.tournament.borrow_mut().as_mut() {
+            Some(t) => (t.id.clone(), t.settings, t.start_assigning()),
+            None => return Ok(()),
+        };
+
+        let mut retry = false;
+        for match_id in pending {
+            let assigned = match self.link_new_match(&id, &settings).await {
+                Ok(Some(code)) => Some(code),
+                Ok(None) => {
+                    console_error!("Tournament: No free match code for match {match_id}");
+                    None
+                }
+                Err(e) => {
+                    console_error!("Tournament: Failed to create match {match_id}: {e:?}");
+                    None
+                }
+            };
+            retry |= assigned.is_none();
+            if let Some(tournament) = self.tournament.borrow_mut().as_mut() {
+                match assigned {
+                    Some(code) => tournament.assign_code(match_id, code),
+                    None => tournament.abandon_assigning(match_id),
+                }
+            }
+        }
+        if retry {
+            self.state
+                .storage()
+                .set_alarm(Duration::from_millis(ASSIGN_RETRY_MS))
+                .await?;
+        }
+
+        let saved = self.tournament.borrow().clone();
+        if let Some(tournament) = saved {
+            self.state
+                .storage()
+                .put(TOURNAMENT_STORAGE_KEY, &tournament)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Create a MatchDO with the tournament's settings under a fresh code
+    /// and link it. None if every code tried was already in use.
+    async fn link_new_match(&self, id: &str, settings: &MatchSettings) -> Result<Option<String>> {
+        let matches = self.env.durable_object("MATCH")?;
+        for _ in 0..CODE_ATTEMPTS {
+            let code = crate::generate_match_code();
+            if crate::apply_match_settings(&matches, &code, settings).await?
+                && crate::link_match_to_tournament(&matches, &code, id).await?
+            {
+                return Ok(Some(code));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::match_history::{PlayerResult, RallyStats, Ruleset};
+
+    fn entrants(count: usize) -> Vec<Entrant> {
+        (0..count)
+            .map(|i| Entrant {
+                name: format!("P{i}"),
+                profile_id: Some(format!("profile{i}")),
+            })
+            .collect()
+    }
+
+    fn tournament(count: usize, format: Format) -> Tournament {
+        Tournament::new(
+            "abcdef012345".into(),
+            "Office Cup".into(),
+            format,
+            MatchSettings::default(),
+            entrants(count),
+        )
+        .unwrap()
+    }
+
+    /// Report every playable match as won by the lower seed index until done
+    fn play_out(t: &mut Tournament) -> usize {
+        let mut played = 0;
+        while let Some(m) = t.matches.iter().find(|m| m.is_playable()) {
+            let winner_slot = match m.slots {
+                [Slot::Entrant(a), Slot::Entrant(b)] if b < a => 1,
+                _ => 0,
+            };
+            t.report(m.id, winner_slot).unwrap();
+            played += 1;
+        }
+        played
+    }
+
+    #[test]
+    fn test_seed_order_keeps_top_seeds_apart() {
+        assert_eq!(seed_order(4), [0, 3, 1, 2]);
+        assert_eq!(seed_order(8), [0, 7, 3, 4, 1, 6, 2, 5]);
+    }
+
+    #[test]
+    fn test_single_elimination_with_byes() {
+        let mut t = tournament(5, Format::SingleElimination);
+        assert_eq!(t.matches.len(), 7);
+        // Seeds 0-2 get byes: 3 v 4 plays round one, and 1 v 2 can start at once
+        let ready: Vec<[Slot; 2]> = t
+            .unassigned_matches()
+            .iter()
+            .map(|&id| t.matches[id].slots)
+            .collect();
+        assert_eq!(
+            ready,
+            [
+                [Slot::Entrant(3), Slot::Entrant(4)],
+                [Slot::Entrant(1), Slot::Entrant(2)]
+            ]
+        );
+
+        assert_eq!(play_out(&mut t), 4);
+        assert_eq!(t.champion, Some(0));
+    }
+
+    #[test]
+    fn test_double_elimination_needs_two_losses() {
+        let mut t = tournament(4, Format::DoubleElimination);
+        assert_eq!(t.matches.len(), 6);
+
+        // Seed 3 upsets seed 0, then loses the winners final to seed 1
+        let first = t.unassigned_matches()[0];
+        assert_eq!(t.matches[first].slots, [Slot::Entrant(0), Slot::Entrant(3)]);
+        t.report(first, 1).unwrap();
+        assert_eq!(play_out(&mut t), 5);
+
+        // Seed 0 fought back through the losers bracket and won the grand final
+        let grand_final = t.matches.last().unwrap();
+        assert_eq!(grand_final.stage, Stage::GrandFinal);
+        assert_eq!(grand_final.slots, [Slot::Entrant(1), Slot::Entrant(0)]);
+        assert_eq!(t.champion, Some(0));
+    }
+
+    #[test]
+    fn test_double_elimination_odd_field() {
+        for count in [2, 3, 5, 6, 7, 9, 16] {
+            let mut t = tournament(count, Format::DoubleElimination);
+            play_out(&mut t);
+            assert_eq!(t.champion, Some(0), "{count} entrants");
+            assert!(t.matches.iter().all(|m| m.winner.is_some()));
+        }
+    }
+
+    #[test]
+    fn test_rejects_bad_entrant_lists() {
+        let new = |entrants| {
+            Tournament::new(
+                String::new(),
+                String::new(),
+                Format::SingleElimination,
+                MatchSettings::default(),
+                entrants,
+            )
+        };
+        assert_eq!(new(entrants(1)), Err(TournamentError::EntrantCount(1)));
+        assert_eq!(
+            new(entrants(MAX_ENTRANTS + 1)),
+            Err(TournamentError::EntrantCount(MAX_ENTRANTS + 1))
+        );
+
+        let mut twice = entrants(3);
+        twice[2].name = "p0".into();
+        assert_eq!(
+            new(twice),
+            Err(TournamentError::DuplicateEntrant("p0".into()))
+        );
+
+        let mut bad_profile = entrants(3);
+        bad_profile[1].profile_id = Some("not a profile".into());
+        assert_eq!(
+            new(bad_profile),
+            Err(TournamentError::InvalidProfileId("not a profile".into()))
+        );
+
+        let mut same_profile = entrants(3);
+        same_profile[2].profile_id = Some("profile0".into());
+        assert_eq!(
+            new(same_profile),
+            Err(TournamentError::DuplicateProfile("profile0".into()))
+        );
+    }
+
+    #[test]
+    fn test_result_advances_winner_by_profile() {
+        let mut t = tournament(2, Format::SingleElimination);
+        t.assign_code(0, "ABCDE".into());
+
+        // Entrant 1 joined first, so they were player 0 in the match
+        let result = MatchResult {
+            id: MatchResult::make_id(1000, "ABCDE"),
+            code: "ABCDE".into(),
+            players: vec![
+                PlayerResult {
+                    player_id: 0,
+                    score: 5,
+                    profile_id: Some("profile1".into()),
+                },
+                PlayerResult {
+                    player_id: 1,
+                    score: 2,
+                    profile_id: Some("profile0".into()),
+                },
+            ],
+            winner: 0,
+            forfeit: false,
+            started_ms: 0,
+            ended_ms: 1000,
+            duration_ms: 1000,
+            rallies: RallyStats::default(),
+            ruleset: Ruleset::from(&game_core::Config::new()),
+        };
+
+        assert_eq!(t.record_result(&result), Some(0));
+        assert_eq!(t.champion, Some(1));
+        assert_eq!(t.record_result(&result), None, "Rematches do not count");
+    }
+
+    #[test]
+    fn test_matches_being_assigned_are_not_claimed_twice() {
+        let mut t = tournament(4, Format::SingleElimination);
+        let claimed = t.start_assigning();
+        assert_eq!(claimed.len(), 2);
+
+        // A result arriving mid-assignment finds nothing left to claim
+        assert!(t.start_assigning().is_empty());
+
+        t.assign_code(claimed[0], "ABCDE".into());
+        t.abandon_assigning(claimed[1]);
+        assert_eq!(t.start_assigning(), [claimed[1]]);
+        assert_eq!(t.unassigned_matches(), [claimed[1]]);
+    }
+
+    #[test]
+    fn test_report_validation() {
+        let mut t = tournament(3, Format::SingleElimination);
+        let final_id = t.matches.len() - 1;
+        assert_eq!(
+            t.report(final_id, 0),
+            Err(TournamentError::NotPlayable(final_id))
+        );
+        assert_eq!(t.report(99, 0), Err(TournamentError::UnknownMatch(99)));
+        let open = t.unassigned_matches()[0];
+        assert_eq!(t.report(open, 2), Err(TournamentError::InvalidSlot(2)));
+    }
+}
@@ -0,0 +1,38 @@
+use server_do::game_state::GameClient;
+use tokio::sync::mpsc::UnboundedSender;
+use worker::{Error, Result};
+
+/// What the lobby asks a socket task to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outbound {
+    Binary(Vec<u8>),
+    /// Close the socket with this reason
+    Close(String),
+}
+
+/// A WebSocket as the match sees it. Sends queue to the task that owns the
+/// socket, so the match never waits on the network.
+#[derive(Debug, Clone)]
+pub struct NativeClient {
+    outbound: UnboundedSender<Outbound>,
+}
+
+impl NativeClient {
+    pub fn new(outbound: UnboundedSender<Outbound>) -> Self {
+        Self { outbound }
+    }
+}
+
+impl GameClient for NativeClient {
+    fn send_bytes(&self, bytes: &[u8]) -> Result<()> {
+        self.outbound
+            .send(Outbound::Binary(bytes.to_vec()))
+            .map_err(|_| Error::RustError("Connection closed".to_string()))
+    }
+
+    fn close(&self, reason: &str) -> Result<()> {
+        self.outbound
+            .send(Outbound::Close(reason.to_string()))
+            .map_err(|_| Error::RustError("Connection closed".to_string()))
+    }
+}
@@ -0,0 +1,50 @@
+use server_do::game_state::Environment;
+use server_do::metrics::{ConsoleMetrics, Metrics};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Wall-clock time in ms since the Unix epoch
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// Environment for matches hosted by the native server: system time,
+/// logs and metric summaries on stdout
+pub struct NativeEnv {
+    metrics: ConsoleMetrics,
+    started: Instant, // Origin for `precise_ms`
+}
+
+impl NativeEnv {
+    pub fn new() -> Self {
+        Self {
+            metrics: ConsoleMetrics::new(|line| println!("{line}")),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Default for NativeEnv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment for NativeEnv {
+    fn now(&self) -> u64 {
+        now_ms()
+    }
+
+    fn log(&self, msg: String) {
+        println!("{msg}");
+    }
+
+    fn precise_ms(&self) -> Option<f64> {
+        Some(self.started.elapsed().as_secs_f64() * 1000.0)
+    }
+
+    fn metrics(&self) -> &dyn Metrics {
+        &self.metrics
+    }
+}
@@ -0,0 +1,44 @@
+//! Self-hosted match server
+//!
+//! Runs the same `GameState` and `proto` messages as the Durable Object
+//! deployment behind the lobby's routes, so a LAN game or an end-to-end
+//! test needs nothing but this binary.
+
+pub mod connection;
+pub mod env;
+pub mod lobby;
+pub mod routes;
+
+use server_do::rate_limit::RateLimits;
+use std::path::PathBuf;
+use tokio::net::TcpListener;
+
+/// Server options, read from the environment by the binary
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Directory with the built client (`client_wasm/` as produced by `npm run build:client`)
+    pub assets: PathBuf,
+    /// Bearer token for admin routes; admin actions are disabled without one
+    pub admin_token: Option<String>,
+    /// Message budget for every WebSocket connection
+    pub rate_limits: RateLimits,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            assets: PathBuf::from("worker/pkg"),
+            admin_token: None,
+            rate_limits: RateLimits::default(),
+        }
+    }
+}
+
+/// Start the lobby thread and serve HTTP and WebSocket requests on `listener`
+pub async fn serve(listener: TcpListener, config: Config) -> std::io::Result<()> {
+    let lobby = lobby::spawn(config.rate_limits);
+    axum::serve(listener, routes::router(lobby, config)).await
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,490 @@
+//! Every hosted match and the lobby services around them, on one thread
+//!
+//! `GameState` is single-threaded, like the Durable Object it was written
+//! for, so all matches live on the lobby thread and the HTTP side reaches
+//! them through a `LobbyHandle`. The lobby runs each match's timer the way
+//! the Workers runtime runs `MatchDO::alarm`.
+
+use crate::connection::{NativeClient, Outbound};
+use crate::env::{now_ms, NativeEnv};
+use axum::http::StatusCode;
+use game_core::MatchSettings;
+use server_do::bot::BotFill;
+use server_do::game_state::{GameState, MatchStatus};
+use server_do::host::{self, AlarmDriver, SocketRole};
+use server_do::match_history::{InMemoryHistory, MatchHistory, MatchResult, MAX_RECENT};
+use server_do::matchmaking::{MatchQueue, QueueRequest, TicketStatus};
+use server_do::rate_limit::{ConnectionLimiter, RateLimits};
+use server_do::rating::{self, PlayerRating};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{sleep_until, Instant};
+
+/// Matches with nobody connected are forgotten after this long
+const MATCH_IDLE_TTL: Duration = Duration::from_secs(600);
+
+/// How often forgotten matches are looked for
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Attempts at finding an unused match code
+const CODE_ATTEMPTS: usize = 3;
+
+/// Admin action on one match, as in the lobby's `/match/:code/admin/:action`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdminAction {
+    /// Close every connection and discard the match
+    End,
+    /// Restart from 0-0 with the connected players
+    Reset,
+    Kick {
+        player_id: u8,
+    },
+}
+
+/// Reply to an admin action: a message, or an HTTP error
+pub type AdminReply = Result<String, (StatusCode, String)>;
+
+/// Work for the lobby thread
+pub enum Request {
+    /// New match with these settings; replies with its code
+    Create {
+        settings: MatchSettings,
+        bot_fill: BotFill,
+        reply: oneshot::Sender<Option<String>>,
+    },
+    Status {
+        code: String,
+        reply: oneshot::Sender<Option<MatchStatus>>,
+    },
+    Admin {
+        code: String,
+        action: AdminAction,
+        reply: oneshot::Sender<AdminReply>,
+    },
+    /// A WebSocket opened on a match (created on first use); replies with its connection id
+    Connect {
+        code: String,
+        outbound: mpsc::UnboundedSender<Outbound>,
+        reply: oneshot::Sender<u64>,
+    },
+    Message {
+        code: String,
+        conn_id: u64,
+        bytes: Vec<u8>,
+    },
+    Disconnected {
+        code: String,
+        conn_id: u64,
+    },
+    RecentMatches {
+        limit: usize,
+        reply: oneshot::Sender<Vec<MatchResult>>,
+    },
+    MatchResult {
+        id: String,
+        reply: oneshot::Sender<Option<MatchResult>>,
+    },
+    Leaderboard {
+        limit: usize,
+        reply: oneshot::Sender<Vec<PlayerRating>>,
+    },
+    Rating {
+        profile_id: String,
+        reply: oneshot::Sender<PlayerRating>,
+    },
+    QuickplayJoin {
+        request: QueueRequest,
+        reply: oneshot::Sender<(String, TicketStatus)>,
+    },
+    QuickplayPoll {
+        ticket: String,
+        reply: oneshot::Sender<TicketStatus>,
+    },
+    QuickplayLeave {
+        ticket: String,
+    },
+}
+
+/// Cheap, thread-safe way to reach the lobby
+#[derive(Clone)]
+pub struct LobbyHandle {
+    requests: mpsc::UnboundedSender<Request>,
+}
+
+impl LobbyHandle {
+    /// Send a request without waiting for anything back
+    pub fn send(&self, request: Request) {
+        // Only fails once the lobby thread is gone, when nothing can be done
+        let _ = self.requests.send(request);
+    }
+
+    /// Send a request and wait for its reply. None if the lobby is gone.
+    pub async fn call<T>(&self, request: impl FnOnce(oneshot::Sender<T>) -> Request) -> Option<T> {
+        let (reply, response) = oneshot::channel();
+        self.requests.send(request(reply)).ok()?;
+        response.await.ok()
+    }
+}
+
+/// Start the lobby on its own thread, limiting every connection to `rate_limits`
+pub fn spawn(rate_limits: RateLimits) -> LobbyHandle {
+    let (requests, receiver) = mpsc::unbounded_channel();
+    std::thread::Builder::new()
+        .name("lobby".to_string())
+        .spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_time()
+                .build()
+                .expect("Failed to build lobby runtime");
+            runtime.block_on(Lobby::with_rate_limits(rate_limits).run(receiver));
+        })
+        .expect("Failed to start lobby thread");
+    LobbyHandle { requests }
+}
+
+/// A socket connected to a match
+struct Connection {
+    client: NativeClient,
+    role: Option<SocketRole>, // Bound on join, like a Durable Object socket attachment
+    limiter: ConnectionLimiter,
+}
+
+struct HostedMatch {
+    gs: GameState,
+    conns: HashMap<u64, Connection>,
+    alarm: AlarmDriver,
+    last_used: Instant,
+}
+
+impl HostedMatch {
+    fn new() -> Self {
+        Self {
+            gs: GameState::new(Box::new(NativeEnv::new())),
+            conns: HashMap::new(),
+            alarm: AlarmDriver::new(),
+            last_used: Instant::now(),
+        }
+    }
+
+    /// When the match's next timer run is due, if its loop is running
+    fn alarm_at(&self) -> Option<Instant> {
+        let due_ms = self.alarm.next_ms()?;
+        let wait_ms = due_ms.saturating_sub(self.gs.env.now());
+        Some(Instant::now() + Duration::from_millis(wait_ms))
+    }
+
+    fn is_forgotten(&self, now: Instant) -> bool {
+        self.conns.is_empty()
+            && self.alarm.next_ms().is_none()
+            && now.duration_since(self.last_used) >= MATCH_IDLE_TTL
+    }
+}
+
+pub struct Lobby {
+    matches: HashMap<String, HostedMatch>,
+    history: InMemoryHistory,
+    ratings: HashMap<String, PlayerRating>,
+    queue: MatchQueue,
+    next_conn_id: u64,
+    next_sweep: Instant,
+    rate_limits: RateLimits,
+}
+
+impl Default for Lobby {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lobby {
+    pub fn new() -> Self {
+        Self::with_rate_limits(RateLimits::default())
+    }
+
+    pub fn with_rate_limits(rate_limits: RateLimits) -> Self {
+        Self {
+            matches: HashMap::new(),
+            history: InMemoryHistory::new(),
+            ratings: HashMap::new(),
+            queue: MatchQueue::new(),
+            next_conn_id: 0,
+            next_sweep: Instant::now() + SWEEP_INTERVAL,
+            rate_limits,
+        }
+    }
+
+    /// Handle requests and run match timers until every handle is dropped
+    pub async fn run(mut self, mut requests: mpsc::UnboundedReceiver<Request>) {
+        loop {
+            let wake_at = self
+                .matches
+                .values()
+                .filter_map(HostedMatch::alarm_at)
+                .fold(self.next_sweep, Instant::min);
+            tokio::select! {
+                request = requests.recv() => match request {
+                    Some(request) => self.handle(request).await,
+                    None => break,
+                },
+                _ = sleep_until(wake_at) => self.run_due_timers().await,
+            }
+        }
+    }
+
+    pub async fn handle(&mut self, request: Request) {
+        match request {
+            Request::Create {
+                settings,
+                bot_fill,
+                reply,
+            } => {
+                let _ = reply.send(self.create_match(settings, bot_fill));
+            }
+            Request::Status { code, reply } => {
+                let status = self.matches.get(&code).map(|hosted| hosted.gs.status());
+                let _ = reply.send(status);
+            }
+            Request::Admin {
+                code,
+                action,
+                reply,
+            } => {
+                let _ = reply.send(self.admin(&code, action));
+                self.record_finished(&code).await;
+            }
+            Request::Connect {
+                code,
+                outbound,
+                reply,
+            } => {
+                let conn_id = self.next_conn_id;
+                self.next_conn_id += 1;
+                let hosted = self.matches.entry(code).or_insert_with(HostedMatch::new);
+                hosted.last_used = Instant::now();
+                hosted.conns.insert(
+                    conn_id,
+                    Connection {
+                        client: NativeClient::new(outbound),
+                        role: None,
+                        limiter: ConnectionLimiter::new(self.rate_limits),
+                    },
+                );
+                let _ = reply.send(conn_id);
+            }
+            Request::Message {
+                code,
+                conn_id,
+                bytes,
+            } => {
+                self.message(&code, conn_id, &bytes);
+                self.record_finished(&code).await;
+            }
+            Request::Disconnected { code, conn_id } => {
+                if let Some(hosted) = self.matches.get_mut(&code) {
+                    if let Some(conn) = hosted.conns.remove(&conn_id) {
+                        host::handle_close(&mut hosted.gs, conn.role, "close event");
+                    }
+                    hosted.last_used = Instant::now();
+                }
+                self.record_finished(&code).await;
+            }
+            Request::RecentMatches { limit, reply } => {
+                let recent = self.history.recent(limit).await.unwrap_or_default();
+                let _ = reply.send(recent);
+            }
+            Request::MatchResult { id, reply } => {
+                let result = self.history.get(&id).await.unwrap_or_default();
+                let _ = reply.send(result);
+            }
+            Request::Leaderboard { limit, reply } => {
+                let mut ratings: Vec<PlayerRating> = self.ratings.values().cloned().collect();
+                ratings.sort_by(|a, b| b.rating.rating.total_cmp(&a.rating.rating));
+                ratings.truncate(limit.min(MAX_RECENT));
+                let _ = reply.send(ratings);
+            }
+            Request::Rating { profile_id, reply } => {
+                let _ = reply.send(self.rating(&profile_id));
+            }
+            Request::QuickplayJoin { request, reply } => {
+                let now = now_ms();
+                self.queue.expire(now);
+                let ticket = new_ticket();
+                self.queue.join(ticket.clone(), request, now);
+                self.pair_waiting(now);
+                let status = self.queue.poll(&ticket, now);
+                let _ = reply.send((ticket, status));
+            }
+            Request::QuickplayPoll { ticket, reply } => {
+                let now = now_ms();
+                self.queue.expire(now);
+                self.pair_waiting(now);
+                let _ = reply.send(self.queue.poll(&ticket, now));
+            }
+            Request::QuickplayLeave { ticket } => {
+                self.queue.leave(&ticket);
+            }
+        }
+    }
+
+    /// Claim an unused code and store the settings before anyone can join
+    fn create_match(&mut self, settings: MatchSettings, bot_fill: BotFill) -> Option<String> {
+        for _ in 0..CODE_ATTEMPTS {
+            let code = server_do::generate_match_code();
+            if self.matches.contains_key(&code) {
+                continue;
+            }
+            let mut hosted = HostedMatch::new();
+            if let Err(e) = hosted.gs.apply_settings(settings) {
+                println!("Lobby: Rejected settings for {code}: {e}");
+                return None;
+            }
+            if let Err(e) = hosted.gs.set_bot_fill(bot_fill) {
+                println!("Lobby: Rejected bot fill for {code}: {e}");
+                return None;
+            }
+            self.matches.insert(code.clone(), hosted);
+            return Some(code);
+        }
+        None
+    }
+
+    fn message(&mut self, code: &str, conn_id: u64, bytes: &[u8]) {
+        let Some(hosted) = self.matches.get_mut(code) else {
+            return;
+        };
+        let Some(conn) = hosted.conns.get_mut(&conn_id) else {
+            return;
+        };
+        hosted.last_used = Instant::now();
+
+        let handled = host::handle_bytes(
+            &mut hosted.gs,
+            &conn.client,
+            conn.role,
+            &mut conn.limiter,
+            bytes,
+        );
+        if let Some(role) = handled.bind {
+            conn.role = Some(role);
+        }
+        if handled.sides_swapped {
+            for conn in hosted.conns.values_mut() {
+                conn.role = conn.role.map(SocketRole::swapped);
+            }
+        }
+        hosted.alarm.after_message(&hosted.gs, &handled);
+    }
+
+    fn admin(&mut self, code: &str, action: AdminAction) -> AdminReply {
+        let Some(hosted) = self.matches.get_mut(code) else {
+            return Err((StatusCode::NOT_FOUND, format!("Match {code} not found")));
+        };
+        match action {
+            AdminAction::End => {
+                hosted.gs.env.log("DO: Match ended by admin".to_string());
+                hosted.gs.close_all("Match ended by admin");
+                self.matches.remove(code);
+                Ok("Match ended".to_string())
+            }
+            AdminAction::Reset => {
+                if !hosted.gs.reset_match() {
+                    return Err((
+                        StatusCode::CONFLICT,
+                        "No players to reset the match for".to_string(),
+                    ));
+                }
+                hosted.alarm.start(&hosted.gs);
+                Ok("Match reset".to_string())
+            }
+            AdminAction::Kick { player_id } => {
+                if !hosted.gs.admin_kick(player_id) {
+                    return Err((
+                        StatusCode::NOT_FOUND,
+                        format!("Player {player_id} is not connected"),
+                    ));
+                }
+                Ok(format!("Player {player_id} kicked"))
+            }
+        }
+    }
+
+    /// Run every match timer that is due, and forget long-idle matches
+    async fn run_due_timers(&mut self) {
+        let now = Instant::now();
+        let mut ran = Vec::new();
+        for (code, hosted) in self.matches.iter_mut() {
+            if hosted.alarm.run_due(&mut hosted.gs) {
+                ran.push(code.clone());
+            }
+        }
+        for code in ran {
+            self.record_finished(&code).await;
+        }
+
+        if now >= self.next_sweep {
+            self.next_sweep = now + SWEEP_INTERVAL;
+            self.matches.retain(|_, hosted| !hosted.is_forgotten(now));
+        }
+    }
+
+    /// Keep a just-finished match in history and update its players' ratings
+    async fn record_finished(&mut self, code: &str) {
+        let Some(result) = self
+            .matches
+            .get_mut(code)
+            .and_then(|hosted| hosted.gs.take_finished_result())
+        else {
+            return;
+        };
+        if let Err(e) = self.history.record(&result).await {
+            println!("Lobby: Failed to record result {}: {e:?}", result.id);
+        }
+
+        let Some((winner_id, loser_id)) = result.rated_profiles() else {
+            return;
+        };
+        let mut winner = self.rating(winner_id);
+        let mut loser = self.rating(loser_id);
+        (winner.rating, loser.rating) =
+            rating::rate_match(winner.rating, loser.rating, result.forfeit);
+        for mut player in [winner, loser] {
+            player.games += 1;
+            player.updated_ms = result.ended_ms;
+            self.ratings.insert(player.profile_id.clone(), player);
+        }
+    }
+
+    /// Stored rating for a profile (new profiles start at the default)
+    fn rating(&self, profile_id: &str) -> PlayerRating {
+        self.ratings
+            .get(profile_id)
+            .cloned()
+            .unwrap_or_else(|| PlayerRating::new(profile_id))
+    }
+
+    /// Pair everyone in the quick-play queue who fits and give each pair a fresh match
+    fn pair_waiting(&mut self, now: u64) {
+        for pair in self.queue.take_pairs(now) {
+            match self.create_match(MatchSettings::default(), BotFill::Never) {
+                Some(code) => {
+                    println!(
+                        "Matchmaker: Paired {} and {} in {code}",
+                        pair[0].ticket, pair[1].ticket
+                    );
+                    self.queue.assign(&pair, &code, now);
+                }
+                None => self.queue.requeue(pair),
+            }
+        }
+    }
+}
+
+/// Random quick-play ticket id (16 hex characters)
+fn new_ticket() -> String {
+    let mut bytes = [0u8; 8];
+    if getrandom::getrandom(&mut bytes).is_err() {
+        bytes = now_ms().to_le_bytes();
+    }
+    format!("{:016x}", u64::from_le_bytes(bytes))
+}
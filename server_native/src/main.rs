@@ -0,0 +1,27 @@
+//! `cargo run -p server_native` serves the game on http://localhost:8787
+//!
+//! PONGO_ADDR sets the listen address, PONGO_ASSETS the directory holding
+//! the built client and ADMIN_TOKEN enables the admin routes. RATE_LIMIT_*
+//! variables override the per-connection message limits (see
+//! `server_do::rate_limit`).
+
+use server_do::rate_limit::RateLimits;
+use server_native::Config;
+use std::path::PathBuf;
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let addr = std::env::var("PONGO_ADDR").unwrap_or_else(|_| "0.0.0.0:8787".to_string());
+    let mut config = Config::default();
+    if let Ok(assets) = std::env::var("PONGO_ASSETS") {
+        config.assets = PathBuf::from(assets);
+    }
+    config.admin_token = std::env::var("ADMIN_TOKEN").ok().filter(|t| !t.is_empty());
+    config.rate_limits = RateLimits::from_vars(|name| std::env::var(name).ok())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    println!("Pongo listening on http://{}", listener.local_addr()?);
+    println!("Serving client assets from {}", config.assets.display());
+    server_native::serve(listener, config).await
+}
@@ -0,0 +1,354 @@
+//! The lobby's HTTP and WebSocket routes, answered by the native lobby
+//!
+//! Paths, query parameters and responses follow `lobby_worker` so the same
+//! client works against either server.
+
+use crate::connection::Outbound;
+use crate::lobby::{AdminAction, LobbyHandle, Request};
+use crate::Config;
+use axum::extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use server_do::matchmaking::QueueRequest;
+use server_do::params::{self, constant_time_eq};
+use tokio::sync::mpsc;
+use tower_http::services::ServeDir;
+
+const INDEX_HTML: &str = include_str!("../../lobby_worker/index.html");
+const SCRIPT_JS: &str = include_str!("../../lobby_worker/script.js");
+const STYLE_CSS: &str = include_str!("../../lobby_worker/style.css");
+
+#[derive(Clone)]
+struct AppState {
+    lobby: LobbyHandle,
+    admin_token: Option<String>,
+}
+
+type Params = Query<Vec<(String, String)>>;
+
+pub fn router(lobby: LobbyHandle, config: Config) -> Router {
+    let state = AppState {
+        lobby,
+        admin_token: config.admin_token,
+    };
+
+    Router::new()
+        .route("/", get(|| async { Html(INDEX_HTML) }))
+        .route(
+            "/script.js",
+            get(|| async { asset("text/javascript", SCRIPT_JS) }),
+        )
+        .route("/style.css", get(|| async { asset("text/css", STYLE_CSS) }))
+        .route("/create", get(handle_create))
+        .route("/join/{code}", get(handle_join))
+        .route("/ws/{code}", get(handle_websocket))
+        .route("/match/{code}/status", get(handle_match_status))
+        .route("/match/{code}/admin/{action}", post(handle_match_admin))
+        .route("/matches", get(handle_recent_matches))
+        .route("/matches/{id}", get(handle_match_result))
+        .route("/ratings", get(handle_leaderboard))
+        .route("/ratings/{profile}", get(handle_player_rating))
+        .route("/tournaments", post(tournaments_unavailable))
+        .route("/tournaments/{id}", get(tournaments_unavailable))
+        .route("/tournaments/{id}/report", post(tournaments_unavailable))
+        .route("/quickplay", post(handle_quickplay_join))
+        .route(
+            "/quickplay/{ticket}",
+            get(handle_quickplay_poll).delete(handle_quickplay_leave),
+        )
+        .fallback_service(ServeDir::new(config.assets))
+        .with_state(state)
+}
+
+fn asset(content_type: &'static str, body: &'static str) -> Response {
+    ([(header::CONTENT_TYPE, content_type)], body).into_response()
+}
+
+fn error(message: impl Into<String>, status: StatusCode) -> Response {
+    (status, message.into()).into_response()
+}
+
+fn lobby_gone() -> Response {
+    error("Lobby unavailable", StatusCode::SERVICE_UNAVAILABLE)
+}
+
+fn is_valid_code(code: &str) -> bool {
+    code.len() == 5
+}
+
+/// Create a match. Same options as the hosted lobby:
+/// win_score, speed, paddle, layout and bot=now|wait
+async fn handle_create(State(state): State<AppState>, Query(options): Params) -> Response {
+    let (settings, bot_fill) = match params::create_options(&options) {
+        Ok(created) => created,
+        Err(e) => return error(e, StatusCode::BAD_REQUEST),
+    };
+
+    let created = state.lobby.call(|reply| Request::Create {
+        settings,
+        bot_fill,
+        reply,
+    });
+    match created.await {
+        Some(Some(code)) => Json(serde_json::json!({
+            "code": code,
+            "settings": settings,
+            "bot": bot_fill,
+        }))
+        .into_response(),
+        Some(None) => error(
+            "Match code already in use, please try again",
+            StatusCode::CONFLICT,
+        ),
+        None => lobby_gone(),
+    }
+}
+
+async fn handle_join(Path(code): Path<String>) -> Response {
+    if !is_valid_code(&code) {
+        return error("Invalid match code", StatusCode::BAD_REQUEST);
+    }
+    format!("Match {code} found. Connect via WebSocket at /ws/{code}").into_response()
+}
+
+async fn handle_websocket(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    if !is_valid_code(&code) {
+        return error("Invalid match code", StatusCode::BAD_REQUEST);
+    }
+    upgrade.on_upgrade(move |socket| run_socket(state.lobby, code, socket))
+}
+
+/// Pump one WebSocket: binary frames go to the match, and whatever the match
+/// sends back goes out, until either side closes
+async fn run_socket(lobby: LobbyHandle, code: String, mut socket: WebSocket) {
+    let (outbound, mut to_send) = mpsc::unbounded_channel();
+    let connected = lobby.call(|reply| Request::Connect {
+        code: code.clone(),
+        outbound,
+        reply,
+    });
+    let Some(conn_id) = connected.await else {
+        return;
+    };
+
+    loop {
+        tokio::select! {
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Binary(bytes))) => lobby.send(Request::Message {
+                    code: code.clone(),
+                    conn_id,
+                    bytes: bytes.to_vec(),
+                }),
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {} // Text and control frames are ignored
+            },
+            outgoing = to_send.recv() => match outgoing {
+                Some(Outbound::Binary(bytes)) => {
+                    if socket.send(Message::Binary(bytes.into())).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Outbound::Close(reason)) => {
+                    let frame = CloseFrame {
+                        code: 1000,
+                        reason: reason.into(),
+                    };
+                    let _ = socket.send(Message::Close(Some(frame))).await;
+                    break;
+                }
+                None => break,
+            },
+        }
+    }
+    lobby.send(Request::Disconnected { code, conn_id });
+}
+
+/// Live state of a match as JSON
+async fn handle_match_status(State(state): State<AppState>, Path(code): Path<String>) -> Response {
+    if !is_valid_code(&code) {
+        return error("Invalid match code", StatusCode::BAD_REQUEST);
+    }
+    let status = state.lobby.call(|reply| Request::Status {
+        code: code.clone(),
+        reply,
+    });
+    match status.await {
+        Some(Some(status)) => Json(status).into_response(),
+        Some(None) => error(format!("Match {code} not found"), StatusCode::NOT_FOUND),
+        None => lobby_gone(),
+    }
+}
+
+/// Admin action on a match: end, reset or kick?player=N.
+/// Requires `Authorization: Bearer <ADMIN_TOKEN>`; disabled when no token is set.
+async fn handle_match_admin(
+    State(state): State<AppState>,
+    Path((code, action)): Path<(String, String)>,
+    Query(params): Params,
+    headers: HeaderMap,
+) -> Response {
+    let Some(admin_token) = &state.admin_token else {
+        return error("Admin actions are disabled", StatusCode::FORBIDDEN);
+    };
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let expected = format!("Bearer {admin_token}");
+    if !constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+        return error("Unauthorized", StatusCode::UNAUTHORIZED);
+    }
+    if !is_valid_code(&code) {
+        return error("Invalid match code", StatusCode::BAD_REQUEST);
+    }
+
+    let action = match action.as_str() {
+        "end" => AdminAction::End,
+        "reset" => AdminAction::Reset,
+        "kick" => {
+            let player_id = params
+                .iter()
+                .find(|(key, _)| key == "player")
+                .and_then(|(_, value)| value.parse::<u8>().ok());
+            match player_id {
+                Some(player_id) => AdminAction::Kick { player_id },
+                None => return error("Missing or invalid player", StatusCode::BAD_REQUEST),
+            }
+        }
+        _ => return error("Unknown admin action", StatusCode::NOT_FOUND),
+    };
+    let done = state.lobby.call(|reply| Request::Admin {
+        code,
+        action,
+        reply,
+    });
+    match done.await {
+        Some(Ok(message)) => message.into_response(),
+        Some(Err((status, message))) => error(message, status),
+        None => lobby_gone(),
+    }
+}
+
+/// Recently finished matches, newest first (optional ?limit=N)
+async fn handle_recent_matches(State(state): State<AppState>, Query(params): Params) -> Response {
+    let limit = params::limit(&params);
+    match state
+        .lobby
+        .call(|reply| Request::RecentMatches { limit, reply })
+        .await
+    {
+        Some(recent) => Json(recent).into_response(),
+        None => lobby_gone(),
+    }
+}
+
+/// One finished match by id
+async fn handle_match_result(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    if !params::is_valid_result_id(&id) {
+        return error("Invalid match id", StatusCode::BAD_REQUEST);
+    }
+    match state
+        .lobby
+        .call(|reply| Request::MatchResult { id, reply })
+        .await
+    {
+        Some(Some(result)) => Json(result).into_response(),
+        Some(None) => error("Match not found", StatusCode::NOT_FOUND),
+        None => lobby_gone(),
+    }
+}
+
+/// Highest rated players (optional ?limit=N)
+async fn handle_leaderboard(State(state): State<AppState>, Query(params): Params) -> Response {
+    let limit = params::limit(&params);
+    match state
+        .lobby
+        .call(|reply| Request::Leaderboard { limit, reply })
+        .await
+    {
+        Some(ratings) => Json(ratings).into_response(),
+        None => lobby_gone(),
+    }
+}
+
+/// Rating for one persistent profile id (new profiles get the starting rating)
+async fn handle_player_rating(
+    State(state): State<AppState>,
+    Path(profile_id): Path<String>,
+) -> Response {
+    if !server_do::rating::is_valid_profile_id(&profile_id) {
+        return error("Invalid profile id", StatusCode::BAD_REQUEST);
+    }
+    match state
+        .lobby
+        .call(|reply| Request::Rating { profile_id, reply })
+        .await
+    {
+        Some(rating) => Json(rating).into_response(),
+        None => lobby_gone(),
+    }
+}
+
+/// Brackets need the tournament object, which only the hosted deployment runs
+async fn tournaments_unavailable() -> Response {
+    error(
+        "Tournaments are not available on the native server",
+        StatusCode::NOT_IMPLEMENTED,
+    )
+}
+
+/// Enter the quick-play queue. Optional JSON body: {"rating": f64, "latency_ms": u16}
+async fn handle_quickplay_join(State(state): State<AppState>, body: String) -> Response {
+    let request: QueueRequest = serde_json::from_str(&body).unwrap_or_default();
+    match state
+        .lobby
+        .call(|reply| Request::QuickplayJoin { request, reply })
+        .await
+    {
+        Some((ticket, result)) => {
+            Json(serde_json::json!({ "ticket": ticket, "result": result })).into_response()
+        }
+        None => lobby_gone(),
+    }
+}
+
+/// Poll a quick-play ticket: waiting, matched (with a match code) or timed out
+async fn handle_quickplay_poll(
+    State(state): State<AppState>,
+    Path(ticket): Path<String>,
+) -> Response {
+    if !is_valid_ticket(&ticket) {
+        return error("Invalid ticket", StatusCode::BAD_REQUEST);
+    }
+    match state
+        .lobby
+        .call(|reply| Request::QuickplayPoll { ticket, reply })
+        .await
+    {
+        Some(status) => Json(status).into_response(),
+        None => lobby_gone(),
+    }
+}
+
+/// Leave the quick-play queue
+async fn handle_quickplay_leave(
+    State(state): State<AppState>,
+    Path(ticket): Path<String>,
+) -> Response {
+    if !is_valid_ticket(&ticket) {
+        return error("Invalid ticket", StatusCode::BAD_REQUEST);
+    }
+    state.lobby.send(Request::QuickplayLeave { ticket });
+    "Left queue".into_response()
+}
+
+fn is_valid_ticket(ticket: &str) -> bool {
+    ticket.len() == 16 && ticket.chars().all(|c| c.is_ascii_hexdigit())
+}
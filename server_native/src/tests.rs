@@ -0,0 +1,120 @@
+use crate::lobby::{Lobby, Request};
+use crate::{serve, Config};
+use futures_util::{SinkExt, StreamExt};
+use proto::{Role, C2S, S2C};
+use server_do::matchmaking::{QueueRequest, TicketStatus};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio_tungstenite::tungstenite::Message;
+
+type Socket =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn join(addr: std::net::SocketAddr, code: &str) -> Socket {
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws/{code}"))
+        .await
+        .unwrap();
+    let join = C2S::Join {
+        code: code.as_bytes().try_into().unwrap(),
+        profile_id: None,
+    };
+    socket
+        .send(Message::Binary(join.to_bytes().unwrap().into()))
+        .await
+        .unwrap();
+    socket
+}
+
+/// Next server message matching `wanted`, skipping the rest
+async fn expect<T>(socket: &mut Socket, wanted: impl Fn(S2C) -> Option<T>) -> T {
+    let read = async {
+        loop {
+            match socket.next().await {
+                Some(Ok(Message::Binary(bytes))) => {
+                    if let Some(found) = wanted(S2C::from_bytes(&bytes).unwrap()) {
+                        return found;
+                    }
+                }
+                Some(Ok(_)) => {}
+                other => panic!("Socket ended early: {other:?}"),
+            }
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), read)
+        .await
+        .expect("Timed out waiting for a server message")
+}
+
+#[tokio::test]
+async fn test_two_players_over_websockets_reach_countdown() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(serve(listener, Config::default()));
+
+    let mut left = join(addr, "ABCDE").await;
+    let left_id = expect(&mut left, |msg| match msg {
+        S2C::Welcome {
+            player_id,
+            role: Role::Player,
+            ..
+        } => Some(player_id),
+        _ => None,
+    })
+    .await;
+
+    let mut right = join(addr, "ABCDE").await;
+    let right_id = expect(&mut right, |msg| match msg {
+        S2C::Welcome { player_id, .. } => Some(player_id),
+        _ => None,
+    })
+    .await;
+    assert_eq!((left_id, right_id), (0, 1));
+
+    // The lobby's timer runs the countdown for both
+    for socket in [&mut left, &mut right] {
+        let seconds = expect(socket, |msg| match msg {
+            S2C::Countdown { seconds } => Some(seconds),
+            _ => None,
+        })
+        .await;
+        assert!(seconds > 0);
+    }
+}
+
+#[tokio::test]
+async fn test_quickplay_pairs_into_one_hosted_match() {
+    let mut lobby = Lobby::new();
+    let mut tickets = Vec::new();
+    for _ in 0..2 {
+        let (reply, response) = oneshot::channel();
+        lobby
+            .handle(Request::QuickplayJoin {
+                request: QueueRequest::default(),
+                reply,
+            })
+            .await;
+        tickets.push(response.await.unwrap().0);
+    }
+
+    let mut codes = Vec::new();
+    for ticket in tickets {
+        let (reply, response) = oneshot::channel();
+        lobby.handle(Request::QuickplayPoll { ticket, reply }).await;
+        match response.await.unwrap() {
+            TicketStatus::Matched { code } => codes.push(code),
+            other => panic!("Expected a match, got {other:?}"),
+        }
+    }
+    assert_eq!(codes[0], codes[1]);
+
+    // The match exists before either player connects
+    let (reply, response) = oneshot::channel();
+    lobby
+        .handle(Request::Status {
+            code: codes[0].clone(),
+            reply,
+        })
+        .await;
+    assert!(response.await.unwrap().is_some());
+}